@@ -0,0 +1,306 @@
+use std::path::PathBuf;
+use crate::modules::app::OscTarget;
+use crate::osc::capture::{CaptureFormat, CaptureMode};
+
+pub const USAGE: &str = "Usage:
+  rustychatbox [options]
+  rustychatbox replay <capture file> [--target <ip:port>] [--fast] [--log-level <level>]
+  rustychatbox systemd-unit [options]   Print a systemd user unit that runs headless with these options
+
+Options:
+  --config <path>            Use this file instead of config.json in the config directory
+  --profile <name>           Start with this profile, creating it if needed
+  --osc-target <host:port>   Send to this address for this run instead of the configured targets
+  --start-minimized          Start with the window minimized
+  --headless                 Run without a window until SIGTERM
+  --log-level <level>        error, warn, info, debug or trace (overrides RUST_LOG)
+  --portable                 Keep config and logs in RustyChatBox-data next to the AppImage
+  --capture                  Record outgoing OSC packets
+  --capture-format jsonl|raw Format for --capture and --dry-run
+  --dry-run                  Record outgoing OSC packets without sending them
+  -h, --help                 Show this help";
+
+pub struct Options {
+    pub capture_override: Option<(CaptureMode, CaptureFormat)>,
+    pub config: Option<PathBuf>,
+    pub profile: Option<String>,
+    pub osc_target: Option<OscTarget>,
+    pub start_minimized: bool,
+    pub headless: bool,
+    pub log_level: Option<log::LevelFilter>,
+    pub portable: bool,
+}
+
+pub enum Launch {
+    Run(Options),
+    Replay {
+        path: PathBuf,
+        ip: String,
+        port: u16,
+        fast: bool,
+        log_level: Option<log::LevelFilter>,
+    },
+    // Options are checked, then passed through to the unit's command line
+    SystemdUnit(Vec<String>),
+    Help,
+}
+
+fn parse_address(address: &str, flag: &str) -> Result<(String, u16), String> {
+    // OSC goes out over an IPv4 socket, so an IPv6 address could never be reached
+    if address.starts_with('[') || address.matches(':').count() > 1 {
+        return Err(format!("{} must be an IPv4 address or host name with a port, not {}", flag, address));
+    }
+    let (host, port) = address
+        .rsplit_once(':')
+        .filter(|(host, _)| !host.is_empty())
+        .ok_or_else(|| format!("{} must be host:port", flag))?;
+    let port = port.parse().map_err(|_| format!("Invalid port in {}", address))?;
+    Ok((host.to_string(), port))
+}
+
+fn parse_log_level(level: Option<&String>) -> Result<log::LevelFilter, String> {
+    match level.map(|l| l.to_ascii_lowercase()).as_deref() {
+        Some("error") => Ok(log::LevelFilter::Error),
+        Some("warn") => Ok(log::LevelFilter::Warn),
+        Some("info") => Ok(log::LevelFilter::Info),
+        Some("debug") => Ok(log::LevelFilter::Debug),
+        Some("trace") => Ok(log::LevelFilter::Trace),
+        _ => Err("--log-level must be error, warn, info, debug or trace".to_string()),
+    }
+}
+
+pub fn parse_args(args: &[String]) -> Result<Launch, String> {
+    if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        return Ok(Launch::Help);
+    }
+
+    if args.first().map(String::as_str) == Some("replay") {
+        let mut path = None;
+        let mut target = "127.0.0.1:9000".to_string();
+        let mut fast = false;
+        let mut log_level = None;
+        let mut args = args[1..].iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--target" => target = args.next().ok_or("--target needs an ip:port")?.clone(),
+                "--fast" => fast = true,
+                "--log-level" => log_level = Some(parse_log_level(args.next())?),
+                _ if path.is_none() && !arg.starts_with('-') => path = Some(PathBuf::from(arg)),
+                _ => return Err(format!("Unexpected argument: {}", arg)),
+            }
+        }
+        let (ip, port) = parse_address(&target, "--target")?;
+        return Ok(Launch::Replay {
+            path: path.ok_or("replay needs a capture file")?,
+            ip,
+            port,
+            fast,
+            log_level,
+        });
+    }
+
+    if args.first().map(String::as_str) == Some("systemd-unit") {
+        return match parse_args(&args[1..])? {
+            Launch::Run(options) if options.headless || options.start_minimized => {
+                Err("systemd-unit adds --headless itself and can't take --start-minimized".to_string())
+            }
+            Launch::Run(_) => Ok(Launch::SystemdUnit(args[1..].to_vec())),
+            _ => Err("systemd-unit takes the options of a normal run".to_string()),
+        };
+    }
+
+    let mut mode = None;
+    let mut format = CaptureFormat::JsonLines;
+    let mut options = Options {
+        capture_override: None,
+        config: None,
+        profile: None,
+        osc_target: None,
+        start_minimized: false,
+        headless: false,
+        log_level: None,
+        portable: false,
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--capture" => mode = Some(mode.unwrap_or(CaptureMode::Record)),
+            "--dry-run" => mode = Some(CaptureMode::DryRun),
+            "--capture-format" => {
+                format = match args.next().map(String::as_str) {
+                    Some("jsonl") => CaptureFormat::JsonLines,
+                    Some("raw") => CaptureFormat::Raw,
+                    _ => return Err("--capture-format must be jsonl or raw".to_string()),
+                }
+            }
+            "--config" => options.config = Some(PathBuf::from(args.next().ok_or("--config needs a path")?)),
+            "--profile" => options.profile = Some(args.next().ok_or("--profile needs a name")?.clone()),
+            "--osc-target" => {
+                let (ip, port) = parse_address(args.next().ok_or("--osc-target needs a host:port")?, "--osc-target")?;
+                options.osc_target = Some(OscTarget {
+                    name: "Command line".to_string(),
+                    ip,
+                    port,
+                    follow_oscquery: false,
+                    ..OscTarget::default()
+                });
+            }
+            "--start-minimized" => options.start_minimized = true,
+            "--headless" => options.headless = true,
+            "--log-level" => options.log_level = Some(parse_log_level(args.next())?),
+            "--portable" => options.portable = true,
+            _ => return Err(format!("Unexpected argument: {}", arg)),
+        }
+    }
+    if options.headless && options.start_minimized {
+        return Err("--start-minimized can't be combined with --headless".to_string());
+    }
+    options.capture_override = mode.map(|mode| (mode, format));
+    Ok(Launch::Run(options))
+}
+
+// Next to the AppImage when run as one, otherwise next to the binary
+pub fn portable_dir() -> Result<PathBuf, String> {
+    let base = match std::env::var_os("APPIMAGE") {
+        Some(appimage) => PathBuf::from(appimage),
+        None => std::env::current_exe().map_err(|e| format!("Failed to locate the executable: {}", e))?,
+    };
+    let dir = base.parent().ok_or("Failed to locate the executable's directory")?;
+    Ok(dir.join("RustyChatBox-data"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Launch, String> {
+        parse_args(&args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>())
+    }
+
+    fn run(args: &[&str]) -> Options {
+        match parse(args) {
+            Ok(Launch::Run(options)) => options,
+            Ok(_) => panic!("{:?} isn't a normal run", args),
+            Err(e) => panic!("{:?} failed: {}", args, e),
+        }
+    }
+
+    fn error(args: &[&str]) -> String {
+        match parse(args) {
+            Err(e) => e,
+            Ok(_) => panic!("{:?} was accepted", args),
+        }
+    }
+
+    #[test]
+    fn runs_with_defaults() {
+        let options = run(&[]);
+        assert!(options.capture_override.is_none());
+        assert!(options.config.is_none());
+        assert!(options.profile.is_none());
+        assert!(options.osc_target.is_none());
+        assert!(!options.start_minimized && !options.headless && !options.portable);
+        assert!(options.log_level.is_none());
+    }
+
+    #[test]
+    fn parses_every_option() {
+        let options = run(&[
+            "--config", "/tmp/chatbox.json",
+            "--profile", "Streaming",
+            "--osc-target", "192.168.1.20:9010",
+            "--start-minimized",
+            "--log-level", "DEBUG",
+            "--portable",
+        ]);
+        assert_eq!(options.config, Some(PathBuf::from("/tmp/chatbox.json")));
+        assert_eq!(options.profile.as_deref(), Some("Streaming"));
+        let target = options.osc_target.unwrap();
+        assert_eq!((target.ip.as_str(), target.port), ("192.168.1.20", 9010));
+        assert!(target.enabled && !target.follow_oscquery);
+        assert!(options.start_minimized);
+        assert_eq!(options.log_level, Some(log::LevelFilter::Debug));
+        assert!(options.portable);
+        assert!(run(&["--headless"]).headless);
+        assert_eq!(run(&["--osc-target", "quest.local:9000"]).osc_target.unwrap().ip, "quest.local");
+    }
+
+    #[test]
+    fn parses_capture_options() {
+        assert_eq!(run(&["--capture"]).capture_override, Some((CaptureMode::Record, CaptureFormat::JsonLines)));
+        assert_eq!(
+            run(&["--capture-format", "raw", "--capture"]).capture_override,
+            Some((CaptureMode::Record, CaptureFormat::Raw))
+        );
+        // A dry run wins over recording, whichever comes first
+        assert_eq!(run(&["--dry-run", "--capture"]).capture_override, Some((CaptureMode::DryRun, CaptureFormat::JsonLines)));
+        assert_eq!(run(&["--capture", "--dry-run"]).capture_override, Some((CaptureMode::DryRun, CaptureFormat::JsonLines)));
+        assert!(run(&["--capture-format", "raw"]).capture_override.is_none());
+        assert_eq!(error(&["--capture-format", "pcap"]), "--capture-format must be jsonl or raw");
+    }
+
+    #[test]
+    fn rejects_bad_arguments() {
+        assert_eq!(error(&["--verbose"]), "Unexpected argument: --verbose");
+        assert_eq!(error(&["--config"]), "--config needs a path");
+        assert_eq!(error(&["--profile"]), "--profile needs a name");
+        assert_eq!(error(&["--osc-target"]), "--osc-target needs a host:port");
+        assert!(error(&["--log-level", "loud"]).starts_with("--log-level must be"));
+        assert!(error(&["--log-level"]).starts_with("--log-level must be"));
+        assert_eq!(error(&["--headless", "--start-minimized"]), "--start-minimized can't be combined with --headless");
+    }
+
+    #[test]
+    fn rejects_malformed_targets() {
+        assert_eq!(error(&["--osc-target", "localhost"]), "--osc-target must be host:port");
+        assert_eq!(error(&["--osc-target", ":9000"]), "--osc-target must be host:port");
+        assert_eq!(error(&["--osc-target", "localhost:osc"]), "Invalid port in localhost:osc");
+        assert_eq!(error(&["--osc-target", "localhost:70000"]), "Invalid port in localhost:70000");
+        for ipv6 in ["[::1]:9000", "::1:9000"] {
+            let e = error(&["--osc-target", ipv6]);
+            assert!(e.contains("IPv4") && e.contains(ipv6), "{}", e);
+        }
+    }
+
+    #[test]
+    fn shows_help_anywhere() {
+        assert!(matches!(parse(&["--help"]), Ok(Launch::Help)));
+        assert!(matches!(parse(&["--portable", "-h"]), Ok(Launch::Help)));
+        assert!(matches!(parse(&["replay", "capture.jsonl", "--help"]), Ok(Launch::Help)));
+    }
+
+    #[test]
+    fn parses_replay() {
+        let Ok(Launch::Replay { path, ip, port, fast, log_level }) = parse(&["replay", "capture.jsonl"]) else {
+            panic!("not a replay");
+        };
+        assert_eq!(path, PathBuf::from("capture.jsonl"));
+        assert_eq!((ip.as_str(), port, fast, log_level), ("127.0.0.1", 9000, false, None));
+
+        let Ok(Launch::Replay { ip, port, fast, log_level, .. }) =
+            parse(&["replay", "--fast", "capture.raw", "--target", "10.0.0.5:9100", "--log-level", "trace"])
+        else {
+            panic!("not a replay");
+        };
+        assert_eq!((ip.as_str(), port, fast, log_level), ("10.0.0.5", 9100, true, Some(log::LevelFilter::Trace)));
+
+        assert_eq!(error(&["replay"]), "replay needs a capture file");
+        assert_eq!(error(&["replay", "one.jsonl", "two.jsonl"]), "Unexpected argument: two.jsonl");
+        assert_eq!(error(&["replay", "capture.jsonl", "--target"]), "--target needs an ip:port");
+        assert!(error(&["replay", "capture.jsonl", "--target", "[::1]:9000"]).contains("IPv4"));
+    }
+
+    #[test]
+    fn passes_systemd_unit_options_through() {
+        let Ok(Launch::SystemdUnit(args)) = parse(&["systemd-unit", "--profile", "Desk", "--osc-target", "127.0.0.1:9000"]) else {
+            panic!("not a systemd unit");
+        };
+        assert_eq!(args, ["--profile", "Desk", "--osc-target", "127.0.0.1:9000"]);
+        assert!(matches!(parse(&["systemd-unit"]), Ok(Launch::SystemdUnit(args)) if args.is_empty()));
+
+        assert!(error(&["systemd-unit", "--headless"]).starts_with("systemd-unit adds --headless"));
+        assert!(error(&["systemd-unit", "--start-minimized"]).starts_with("systemd-unit adds --headless"));
+        assert_eq!(error(&["systemd-unit", "replay", "capture.jsonl"]), "systemd-unit takes the options of a normal run");
+        assert_eq!(error(&["systemd-unit", "--osc-target", "nowhere"]), "--osc-target must be host:port");
+    }
+}
@@ -0,0 +1,608 @@
+// Turns integration output and chat messages into the exact chatbox lines sent to VRChat.
+// Nothing in here samples integrations or touches the network, so the same input
+// always produces the same output.
+//
+// Two measures are used: VRChat caps a message by its C# string length, i.e. UTF-16
+// code units (chatbox_len), while line width is about what the user sees, so it
+// counts grapheme clusters (display_width). Cuts only ever land between graphemes.
+
+use unicode_segmentation::UnicodeSegmentation;
+
+// VRChat's chatbox limits
+pub const MAX_CHARS: usize = 144;
+pub const MAX_LINES: usize = 9;
+// Roughly what fits on one chatbox line before VRChat wraps it itself
+pub const MAX_LINE_WIDTH: usize = 27;
+// Chat input limit, leaving room for the speech bubble prefix
+pub const MAX_CHAT_INPUT: usize = 140;
+
+const INLINE_SEPARATOR: &str = " | ";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layout {
+    // Put every integration on its own line instead of joining them with " | "
+    pub separate_lines: bool,
+    pub line_width: usize,
+    pub max_chars: usize,
+    pub max_lines: usize,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Layout {
+            separate_lines: true,
+            line_width: MAX_LINE_WIDTH,
+            max_chars: MAX_CHARS,
+            max_lines: MAX_LINES,
+        }
+    }
+}
+
+impl Layout {
+    pub fn new(separate_lines: bool) -> Self {
+        Layout {
+            separate_lines,
+            ..Layout::default()
+        }
+    }
+}
+
+// Length as VRChat counts it
+pub fn chatbox_len(text: &str) -> usize {
+    text.encode_utf16().count()
+}
+
+// Number of user-perceived characters
+pub fn display_width(text: &str) -> usize {
+    text.graphemes(true).count()
+}
+
+// Longest prefix of text that fits in max_len UTF-16 units without splitting a grapheme
+pub fn truncate_to_len(text: &str, max_len: usize) -> &str {
+    let mut used = 0;
+    for (index, grapheme) in text.grapheme_indices(true) {
+        used += chatbox_len(grapheme);
+        if used > max_len {
+            return &text[..index];
+        }
+    }
+    text
+}
+
+// Shortens text to max_width graphemes, ending in "..." when something was cut
+pub fn truncate_with_ellipsis(text: &str, max_width: usize) -> String {
+    if display_width(text) <= max_width {
+        return text.to_string();
+    }
+    if max_width <= 3 {
+        return text.graphemes(true).take(max_width).collect();
+    }
+    let mut truncated: String = text.graphemes(true).take(max_width - 3).collect();
+    truncated.push_str("...");
+    truncated
+}
+
+// Shown between the end of the text and its start again while scrolling
+const MARQUEE_GAP: &str = "   ";
+
+// A width-grapheme window into text that wraps around, starting offset graphemes in
+pub fn marquee_window(text: &str, width: usize, offset: usize) -> String {
+    if display_width(text) <= width {
+        return text.to_string();
+    }
+    let graphemes: Vec<&str> = text.graphemes(true).chain(MARQUEE_GAP.graphemes(true)).collect();
+    graphemes
+        .iter()
+        .cycle()
+        .skip(offset % graphemes.len())
+        .take(width)
+        .copied()
+        .collect()
+}
+
+// Scroll position for one field, moved along once per send tick
+#[derive(Debug, Clone, Default)]
+pub struct Marquee {
+    text: String,
+    offset: usize,
+}
+
+impl Marquee {
+    // The window to show on this tick; starts over whenever text changes
+    pub fn advance(&mut self, text: &str, width: usize, step: usize) -> String {
+        if text == self.text {
+            self.offset += step;
+        } else {
+            self.text = text.to_string();
+            self.offset = 0;
+        }
+        marquee_window(text, width, self.offset)
+    }
+}
+
+// Greedy word wrap; words wider than a line are split across lines
+pub fn wrap_words(text: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut lines = Vec::new();
+    let mut current_line = String::new();
+    for word in text.split_whitespace() {
+        let word_len = display_width(word);
+        let line_len = display_width(&current_line);
+        let space_len = if current_line.is_empty() { 0 } else { 1 };
+        if line_len + space_len + word_len <= width {
+            if !current_line.is_empty() {
+                current_line.push(' ');
+            }
+            current_line.push_str(word);
+            continue;
+        }
+        if !current_line.is_empty() {
+            lines.push(std::mem::take(&mut current_line));
+        }
+        if word_len <= width {
+            current_line.push_str(word);
+            continue;
+        }
+        let graphemes: Vec<&str> = word.graphemes(true).collect();
+        let mut chunks = graphemes.chunks(width).map(|chunk| chunk.concat()).peekable();
+        while let Some(chunk) = chunks.next() {
+            if chunks.peek().is_some() {
+                lines.push(chunk);
+            } else {
+                // The tail can still share a line with the next word
+                current_line = chunk;
+            }
+        }
+    }
+    if !current_line.is_empty() {
+        lines.push(current_line);
+    }
+    lines
+}
+
+// Lays out integration segments, before any chat message is added or limits applied
+pub fn integration_lines(segments: &[String], layout: &Layout) -> Vec<String> {
+    let segments: Vec<&String> = segments.iter().filter(|s| !s.trim().is_empty()).collect();
+    let mut lines: Vec<String> = Vec::new();
+    for (i, segment) in segments.iter().enumerate() {
+        for segment_line in segment.split('\n') {
+            lines.extend(wrap_words(segment_line, layout.line_width));
+        }
+        let is_last_segment = i == segments.len() - 1;
+        if !is_last_segment && !layout.separate_lines {
+            match lines.last_mut() {
+                Some(last) if display_width(last) + INLINE_SEPARATOR.len() <= layout.line_width => {
+                    last.push_str(INLINE_SEPARATOR);
+                }
+                _ => lines.push(INLINE_SEPARATOR.trim().to_string()),
+            }
+        }
+    }
+    lines
+}
+
+// One integration's output, with how it should be treated when the chatbox is full
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment {
+    pub name: String,
+    pub text: String,
+    // Lower is more important; the least important segment is trimmed first
+    pub priority: usize,
+    // 0 for no limit
+    pub max_lines: usize,
+}
+
+impl Segment {
+    pub fn new(name: &str, text: String) -> Self {
+        Segment {
+            name: name.to_string(),
+            text,
+            priority: 0,
+            max_lines: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TrimAction {
+    Shortened,
+    Dropped,
+}
+
+// A segment that didn't make it into the chatbox in full
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trimmed {
+    pub name: String,
+    pub action: TrimAction,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Composition {
+    pub lines: Vec<String>,
+    pub trimmed: Vec<Trimmed>,
+}
+
+fn record_trim(trimmed: &mut Vec<Trimmed>, name: &str, action: TrimAction, reason: String) {
+    match trimmed.iter_mut().find(|t| t.name == name) {
+        Some(existing) => {
+            existing.action = action;
+            existing.reason = reason;
+        }
+        None => trimmed.push(Trimmed {
+            name: name.to_string(),
+            action,
+            reason,
+        }),
+    }
+}
+
+// A segment's wrapped lines, capped at its max_lines, and whether the cap cut anything
+fn segment_lines(segment: &Segment, layout: &Layout) -> (Vec<String>, bool) {
+    let mut lines: Vec<String> = segment
+        .text
+        .split('\n')
+        .flat_map(|line| wrap_words(line, layout.line_width))
+        .collect();
+    let shortened = segment.max_lines > 0 && lines.len() > segment.max_lines;
+    if shortened {
+        lines.truncate(segment.max_lines);
+    }
+    (lines, shortened)
+}
+
+// Chat message first, then integrations, cut down to VRChat's limits
+pub fn compose(chat_message: Option<&str>, segments: &[String], layout: &Layout) -> Vec<String> {
+    let segments: Vec<Segment> = segments.iter().map(|text| Segment::new("", text.clone())).collect();
+    compose_prioritized(chat_message, &segments, layout).lines
+}
+
+// Like compose, but when the result is over the limits the least important segment
+// loses its last line, one line at a time, and is dropped once it has only one left.
+// This repeats until everything fits; the chat message itself is never trimmed here.
+pub fn compose_prioritized(chat_message: Option<&str>, segments: &[Segment], layout: &Layout) -> Composition {
+    let mut trimmed = Vec::new();
+    let chat_lines = chat_message
+        .map(|message| wrap_words(message, layout.line_width))
+        .unwrap_or_default();
+
+    // Each kept segment as (index into segments, wrapped lines)
+    let mut kept: Vec<(usize, Vec<String>)> = Vec::new();
+    for (index, segment) in segments.iter().enumerate() {
+        let (lines, shortened) = segment_lines(segment, layout);
+        if lines.is_empty() {
+            continue;
+        }
+        if shortened {
+            record_trim(&mut trimmed, &segment.name, TrimAction::Shortened, format!("limited to {} lines", segment.max_lines));
+        }
+        kept.push((index, lines));
+    }
+
+    loop {
+        let texts: Vec<String> = kept.iter().map(|(_, lines)| lines.join("\n")).collect();
+        let mut lines = chat_lines.clone();
+        lines.extend(integration_lines(&texts, layout));
+        let over_lines = lines.len() > layout.max_lines;
+        let over_chars = chatbox_len(&lines.join("\n")) > layout.max_chars;
+        if !over_lines && !over_chars {
+            return Composition { lines, trimmed };
+        }
+
+        // Ties go to the segment shown last
+        let Some(victim) = (0..kept.len()).max_by_key(|&i| (segments[kept[i].0].priority, i)) else {
+            return Composition {
+                lines: fit_limits(lines, layout),
+                trimmed,
+            };
+        };
+        let reason = if over_lines {
+            format!("over the {} line limit", layout.max_lines)
+        } else {
+            format!("over the {} character limit", layout.max_chars)
+        };
+        let name = &segments[kept[victim].0].name;
+        if kept[victim].1.len() > 1 {
+            kept[victim].1.pop();
+            record_trim(&mut trimmed, name, TrimAction::Shortened, reason);
+        } else {
+            kept.remove(victim);
+            record_trim(&mut trimmed, name, TrimAction::Dropped, reason);
+        }
+    }
+}
+
+// Drops lines past max_lines and cuts the text so the joined result fits in max_chars
+pub fn fit_limits(lines: Vec<String>, layout: &Layout) -> Vec<String> {
+    let mut fitted = Vec::new();
+    let mut used = 0;
+    for line in lines.into_iter().take(layout.max_lines) {
+        let newline = if fitted.is_empty() { 0 } else { 1 };
+        let remaining = layout.max_chars.saturating_sub(used + newline);
+        if remaining == 0 {
+            break;
+        }
+        let line_len = chatbox_len(&line);
+        if line_len <= remaining {
+            used += newline + line_len;
+            fitted.push(line);
+        } else {
+            let truncated = truncate_to_len(&line, remaining);
+            if !truncated.is_empty() {
+                fitted.push(truncated.to_string());
+            }
+            break;
+        }
+    }
+    fitted
+}
+
+// Room kept free on every page for an indicator like " 12/12"
+const PAGE_INDICATOR_RESERVE: usize = 6;
+
+fn page_fits(page: &[Segment], layout: &Layout) -> bool {
+    let texts: Vec<String> = page.iter().map(|segment| segment_lines(segment, layout).0.join("\n")).collect();
+    let lines = integration_lines(&texts, layout);
+    lines.len() <= layout.max_lines && chatbox_len(&lines.join("\n")) <= layout.max_chars
+}
+
+// Appends "n/total" to the last line, or on a line of its own when the last one is full
+fn add_page_indicator(lines: &mut Vec<String>, page: usize, total: usize, layout: &Layout) {
+    let indicator = format!("{}/{}", page, total);
+    let lines_full = lines.len() >= layout.max_lines;
+    match lines.last_mut() {
+        Some(last) if display_width(last) + 1 + indicator.len() <= layout.line_width || lines_full => {
+            last.push(' ');
+            last.push_str(&indicator);
+        }
+        _ => lines.push(indicator),
+    }
+}
+
+// Splits segments into pages that each fit the chatbox on their own, keeping segments
+// whole and in order. A segment too big for a page by itself is trimmed as in
+// compose_prioritized. Page indicators are only added when there is more than one page.
+pub fn paginate(segments: &[Segment], layout: &Layout, indicator: bool) -> Vec<Composition> {
+    let page_layout = Layout {
+        max_chars: layout.max_chars.saturating_sub(if indicator { PAGE_INDICATOR_RESERVE } else { 0 }),
+        ..layout.clone()
+    };
+    let mut pages: Vec<Vec<Segment>> = Vec::new();
+    let mut current: Vec<Segment> = Vec::new();
+    for segment in segments {
+        if segment_lines(segment, layout).0.is_empty() {
+            continue;
+        }
+        current.push(segment.clone());
+        if current.len() > 1 && !page_fits(&current, &page_layout) {
+            let overflow = current.pop().into_iter().collect();
+            pages.push(std::mem::replace(&mut current, overflow));
+        }
+    }
+    if !current.is_empty() {
+        pages.push(current);
+    }
+
+    if pages.len() == 1 {
+        return vec![compose_prioritized(None, &pages[0], layout)];
+    }
+    let total = pages.len();
+    pages
+        .iter()
+        .enumerate()
+        .map(|(index, page)| {
+            let mut composition = compose_prioritized(None, page, &page_layout);
+            if indicator {
+                add_page_indicator(&mut composition.lines, index + 1, total, layout);
+            }
+            composition
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn wraps_at_word_boundaries() {
+        assert_eq!(wrap_words("the quick brown fox", 10), strings(&["the quick", "brown fox"]));
+        assert_eq!(wrap_words("  spaced   out  ", 27), strings(&["spaced out"]));
+        assert!(wrap_words("", 27).is_empty());
+    }
+
+    #[test]
+    fn splits_words_longer_than_a_line() {
+        let word = "a".repeat(60);
+        assert_eq!(wrap_words(&word, 27), strings(&[&"a".repeat(27), &"a".repeat(27), &"a".repeat(6)]));
+        // The leftover piece shares its line with what follows
+        assert_eq!(wrap_words("abcdefgh ij", 5), strings(&["abcde", "fgh", "ij"]));
+        assert_eq!(wrap_words("abcdefg hi", 5), strings(&["abcde", "fg hi"]));
+    }
+
+    #[test]
+    fn skips_empty_segments() {
+        let layout = Layout::new(true);
+        assert_eq!(integration_lines(&strings(&["", "one", "  ", "two"]), &layout), strings(&["one", "two"]));
+        assert!(compose(None, &strings(&["", " "]), &layout).is_empty());
+    }
+
+    #[test]
+    fn separates_segments_inline() {
+        let layout = Layout::new(false);
+        assert_eq!(integration_lines(&strings(&["one", "two", "three"]), &layout), strings(&["one | ", "two | ", "three"]));
+        // A separator that doesn't fit goes on its own line
+        let long = "a".repeat(26);
+        assert_eq!(integration_lines(&strings(&[&long, "b"]), &layout), strings(&[&long, "|", "b"]));
+        // Nothing is separated from an empty segment
+        assert_eq!(integration_lines(&strings(&["one", "", "two"]), &layout), strings(&["one | ", "two"]));
+        assert_eq!(integration_lines(&strings(&["one", "two"]), &Layout::new(true)), strings(&["one", "two"]));
+    }
+
+    #[test]
+    fn keeps_to_the_line_limit() {
+        let layout = Layout::new(true);
+        let segments: Vec<String> = (1..=12).map(|i| format!("line {}", i)).collect();
+        let lines = compose(None, &segments, &layout);
+        assert_eq!(lines.len(), MAX_LINES);
+        assert_eq!(lines[0], "line 1");
+        assert_eq!(lines[8], "line 9");
+    }
+
+    #[test]
+    fn keeps_to_the_character_limit() {
+        let layout = Layout::new(true);
+        let segments: Vec<String> = (0..6).map(|_| "x".repeat(27)).collect();
+        let lines = compose(None, &segments, &layout);
+        assert!(chatbox_len(&lines.join("\n")) <= MAX_CHARS);
+
+        let lines = fit_limits(vec!["y".repeat(100), "z".repeat(100)], &layout);
+        assert_eq!(lines, strings(&[&"y".repeat(100), &"z".repeat(43)]));
+        assert_eq!(chatbox_len(&lines.join("\n")), MAX_CHARS);
+    }
+
+    #[test]
+    fn exactly_at_the_limits_is_kept_whole() {
+        let layout = Layout::new(true);
+        let exact = vec!["c".repeat(MAX_CHARS)];
+        assert_eq!(fit_limits(exact.clone(), &layout), exact);
+        assert_eq!(fit_limits(vec!["c".repeat(MAX_CHARS + 1)], &layout), exact);
+
+        // Nine lines of 15 plus eight newlines is 143
+        let nine: Vec<String> = (0..MAX_LINES).map(|_| "d".repeat(15)).collect();
+        assert_eq!(fit_limits(nine.clone(), &layout), nine);
+        let mut ten = nine.clone();
+        ten.push("e".to_string());
+        assert_eq!(fit_limits(ten, &layout), nine);
+    }
+
+    #[test]
+    fn chat_message_comes_first() {
+        let layout = Layout::new(true);
+        let lines = compose(Some("hello there"), &strings(&["status"]), &layout);
+        assert_eq!(lines, strings(&["hello there", "status"]));
+    }
+
+    // Each is a single grapheme of a different UTF-16 length
+    const SAMPLES: [&str; 7] = [
+        "a",
+        "漢",
+        "e\u{301}",
+        "👍",
+        "🇯🇵",
+        "👍🏽",
+        "👨\u{200d}👩\u{200d}👧",
+    ];
+
+    // Every mix of three samples, with and without spaces, repeated to go past the limits
+    fn multibyte_texts() -> Vec<String> {
+        let mut texts = Vec::new();
+        for a in SAMPLES {
+            for b in SAMPLES {
+                for c in SAMPLES {
+                    for pattern in [format!("{}{}{}", a, b, c), format!("{} {}{} ", a, b, c)] {
+                        texts.push(pattern.repeat(MAX_CHARS / chatbox_len(&pattern) + 1));
+                    }
+                }
+            }
+        }
+        texts
+    }
+
+    fn is_grapheme_prefix(prefix: &str, text: &str) -> bool {
+        text.starts_with(prefix)
+            && (prefix.is_empty() || text.grapheme_indices(true).any(|(i, g)| i + g.len() == prefix.len()))
+    }
+
+    #[test]
+    fn measures_multibyte_text() {
+        assert_eq!(chatbox_len("漢字"), 2);
+        assert_eq!(chatbox_len("e\u{301}"), 2);
+        assert_eq!(chatbox_len("👍"), 2);
+        assert_eq!(chatbox_len("👨\u{200d}👩\u{200d}👧"), 8);
+        assert_eq!(display_width("漢字"), 2);
+        assert_eq!(display_width("e\u{301}👍🇯🇵"), 3);
+        assert_eq!(display_width("👨\u{200d}👩\u{200d}👧"), 1);
+    }
+
+    #[test]
+    fn truncation_never_splits_a_grapheme() {
+        for text in multibyte_texts() {
+            for max_len in 0..40 {
+                let truncated = truncate_to_len(&text, max_len);
+                assert!(chatbox_len(truncated) <= max_len, "{:?} cut to {}", text, max_len);
+                assert!(is_grapheme_prefix(truncated, &text), "{:?} cut to {}", text, max_len);
+                // Only stops short when the next grapheme wouldn't fit
+                if let Some(next) = text[truncated.len()..].graphemes(true).next() {
+                    assert!(chatbox_len(truncated) + chatbox_len(next) > max_len);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn ellipsis_counts_towards_the_width() {
+        for text in multibyte_texts() {
+            for max_width in 0..12 {
+                let truncated = truncate_with_ellipsis(&text, max_width);
+                assert!(display_width(&truncated) <= max_width, "{:?} at {}", truncated, max_width);
+                if max_width > 3 {
+                    assert_eq!(display_width(&truncated), max_width);
+                    let kept = truncated.strip_suffix("...").expect("cut text ends in an ellipsis");
+                    assert!(is_grapheme_prefix(kept, &text));
+                }
+            }
+        }
+        assert_eq!(truncate_with_ellipsis("👍👍👍👍👍👍", 5), "👍👍...");
+        assert_eq!(truncate_with_ellipsis("👍👍👍", 3), "👍👍👍");
+    }
+
+    #[test]
+    fn wrapping_keeps_graphemes_whole() {
+        for text in multibyte_texts() {
+            for width in 1..8 {
+                let lines = wrap_words(&text, width);
+                let rejoined: Vec<&str> = lines.iter().flat_map(|line| line.graphemes(true)).filter(|g| *g != " ").collect();
+                let original: Vec<&str> = text.graphemes(true).filter(|g| *g != " ").collect();
+                assert_eq!(rejoined, original);
+                assert!(lines.iter().all(|line| display_width(line) <= width));
+            }
+        }
+    }
+
+    #[test]
+    fn composed_output_fits_in_utf16_units() {
+        for layout in [Layout::new(true), Layout::new(false)] {
+            // A fifth of the mixes, since composing is slow in debug builds
+            for text in multibyte_texts().into_iter().step_by(5) {
+                let lines = fit_limits(wrap_words(&text, MAX_LINE_WIDTH), &layout);
+                assert!(chatbox_len(&lines.join("\n")) <= MAX_CHARS);
+                assert!(lines.len() <= MAX_LINES);
+                let chat_message = truncate_to_len(&text, MAX_CHARS / 2);
+                let lines = compose(Some(chat_message), &[text.clone(), text.clone()], &layout);
+                assert!(chatbox_len(&lines.join("\n")) <= MAX_CHARS, "{:?}", lines);
+            }
+        }
+    }
+
+    #[test]
+    fn measures_styled_text_in_utf16_units() {
+        use crate::stylize::{stylize, TextStyle};
+        let layout = Layout::new(true);
+        let plain = "word ".repeat(40);
+        let bold = stylize(&plain, TextStyle::Bold);
+        // Each bold letter is a surrogate pair, twice the length of the plain one
+        assert_eq!(chatbox_len(&stylize("word", TextStyle::Bold)), 8);
+
+        let lines = compose(None, &[bold], &layout);
+        let text = lines.join("\n");
+        assert!(chatbox_len(&text) <= MAX_CHARS, "{}", chatbox_len(&text));
+        assert_eq!(lines[0], stylize("word word word word word", TextStyle::Bold));
+        let plain_text = compose(None, &[plain], &layout).join("\n");
+        assert!(display_width(&text) < display_width(&plain_text));
+    }
+}
@@ -1,15 +1,25 @@
+use chrono::Local;
+use log::{error, info};
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use serde_json::Value;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
-use crate::ui::types::{ChatTab, StatusTab, Tab};
+use crate::ui::types::{self, IntegrationPriority, StatusTab, Tab};
 use crate::modules::{
     activity::WindowActivityOptions,
-    app::AppOptions,
+    app::{AppOptions, OscTarget},
     chatting::ChatOptions,
     component::ComponentStatsOptions,
     extra::ExtraOptions,
+    layout::LayoutOptions,
     media::MediaLinkOptions,
-    network::{NetworkStatsOptions, NetworkOptions},
+    network::NetworkStatsOptions,
+    parameters::ParameterOptions,
+    router::RouterOptions,
+    rules::RuleOptions,
     status::StatusOptions,
     time::TimeOptions,
 };
@@ -17,6 +27,7 @@ use crate::modules::{
 #[derive(Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct Config {
+    pub version: u32,
     pub app_options: AppOptions,
     pub personal_status_enabled: bool,
     pub component_stats_enabled: bool,
@@ -25,12 +36,16 @@ pub struct Config {
     pub medialink_enabled: bool,
     pub window_activity_enabled: Option<bool>,
     pub window_activity_options: Option<WindowActivityOptions>,
+    pub integration_priorities: Vec<IntegrationPriority>,
     pub chat_options: ChatOptions,
-    pub chat_tab: ChatTab,
     pub component_stats_options: ComponentStatsOptions,
     pub extra_options: ExtraOptions,
     pub media_link_options: MediaLinkOptions,
     pub network_stats_options: NetworkStatsOptions,
+    pub parameter_options: ParameterOptions,
+    pub router_options: RouterOptions,
+    pub layout_options: LayoutOptions,
+    pub rule_options: RuleOptions,
     pub status_options: StatusOptions,
     pub status_tab: StatusTab,
     pub status_messages: Vec<String>,
@@ -43,6 +58,7 @@ pub struct Config {
 impl Default for Config {
     fn default() -> Self {
         Config {
+            version: CONFIG_VERSION,
             app_options: AppOptions::default(),
             personal_status_enabled: true,
             component_stats_enabled: false,
@@ -51,15 +67,16 @@ impl Default for Config {
             medialink_enabled: true,
             window_activity_enabled: Some(true),
             window_activity_options: Some(WindowActivityOptions::default()),
+            integration_priorities: types::default_priorities(),
             chat_options: ChatOptions::default(),
-            chat_tab: ChatTab {
-                message: String::new(),
-                is_focused: false,
-            },
             component_stats_options: ComponentStatsOptions::default(),
             extra_options: ExtraOptions::default(),
             media_link_options: MediaLinkOptions::default(),
             network_stats_options: NetworkStatsOptions::default(),
+            parameter_options: ParameterOptions::default(),
+            router_options: RouterOptions::default(),
+            layout_options: LayoutOptions::default(),
+            rule_options: RuleOptions::default(),
             status_options: StatusOptions::default(),
             status_tab: StatusTab {
                 new_message: String::new(),
@@ -73,19 +90,212 @@ impl Default for Config {
     }
 }
 
+static DATA_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+// Used instead of the user's config directory, as in portable mode. Set once before startup.
+pub fn set_data_dir(dir: PathBuf) {
+    let _ = DATA_DIR.set(dir);
+}
+
+// Where config, profiles, logs and captures live
+pub fn data_dir() -> PathBuf {
+    DATA_DIR.get().cloned().unwrap_or_else(|| {
+        dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("RustyChatBox")
+    })
+}
+
+// Bump when a change needs more than serde defaults and aliases to read older files,
+// and add the step that upgrades from the previous version to MIGRATIONS
+pub const CONFIG_VERSION: u32 = 2;
+
+type Migration = fn(&mut Value) -> Result<(), String>;
+
+// MIGRATIONS[n] upgrades a version n file to version n + 1
+const MIGRATIONS: [Migration; CONFIG_VERSION as usize] = [migrate_v0, migrate_v1];
+
+// Unversioned files kept a single OSC destination as app_options.osc_options.ip/port
+fn migrate_v0(config: &mut Value) -> Result<(), String> {
+    let Some(osc_options) = config
+        .get_mut("app_options")
+        .and_then(|app_options| app_options.get_mut("osc_options"))
+        .and_then(Value::as_object_mut)
+    else {
+        return Ok(());
+    };
+    let ip = osc_options.remove("ip").filter(|ip| !ip.is_null());
+    let port = osc_options.remove("port").filter(|port| !port.is_null());
+    if ip.is_none() && port.is_none() {
+        return Ok(());
+    }
+    if let Some(ip) = ip.as_ref().filter(|ip| !ip.is_string()) {
+        return Err(format!("ip {} is not a string", ip));
+    }
+    if let Some(port) = port.as_ref().filter(|port| !port.is_u64()) {
+        return Err(format!("port {} is not a number", port));
+    }
+    let mut target = serde_json::to_value(OscTarget::default()).map_err(|e| e.to_string())?;
+    if let Some(ip) = ip {
+        target["ip"] = ip;
+    }
+    if let Some(port) = port {
+        target["port"] = port;
+    }
+    osc_options.insert("targets".to_string(), Value::Array(vec![target]));
+    Ok(())
+}
+
+// Version 1 saved the unsent chat draft and input focus, which are no longer kept
+fn migrate_v1(config: &mut Value) -> Result<(), String> {
+    if let Some(fields) = config.as_object_mut() {
+        fields.remove("chat_tab");
+    }
+    Ok(())
+}
+
+fn migrate(config: &mut Value) -> Result<(), String> {
+    let Some(fields) = config.as_object() else {
+        return Err("Expected a JSON object".to_string());
+    };
+    let version = match fields.get("version") {
+        None => 0,
+        Some(version) => version
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| format!("Invalid config version {}", version))?,
+    };
+    if version > CONFIG_VERSION {
+        return Err(format!(
+            "Config version {} is newer than this build supports ({})",
+            version, CONFIG_VERSION
+        ));
+    }
+    for (from, step) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        info!("Migrating config from version {} to {}", from, from + 1);
+        step(config).map_err(|e| format!("Migration from version {} failed: {}", from, e))?;
+    }
+    config["version"] = Value::from(CONFIG_VERSION);
+    Ok(())
+}
+
 impl Config {
-    pub fn load_or_create<P: AsRef<Path>>(path: P, default_network_options: NetworkOptions) -> Self {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let mut value: Value = serde_json::from_str(&contents).map_err(|e| e.to_string())?;
+        migrate(&mut value)?;
+        let mut config: Config = serde_json::from_value(value).map_err(|e| e.to_string())?;
+        types::normalize_priorities(&mut config.integration_priorities);
+        Ok(config)
+    }
+
+    // Returns defaults when the file is missing. A file that can't be read is copied aside
+    // first so the next save doesn't destroy it, and the returned error says where it went.
+    pub fn load_or_create<P: AsRef<Path>>(path: P) -> (Self, Option<LoadError>) {
         let path = path.as_ref();
-        if path.exists() {
-            if let Ok(contents) = std::fs::read_to_string(path) {
-                if let Ok(mut config) = serde_json::from_str::<Config>(&contents) {
-                    config.network_stats_options = NetworkStatsOptions::new(default_network_options);
-                    return config;
-                }
+        if !path.exists() {
+            return (Config::default(), None);
+        }
+        match Config::load(path) {
+            Ok(config) => (config, None),
+            Err(e) => {
+                let (backup_note, held) = match backup(path) {
+                    Ok(backup_path) => (format!(" The original was backed up to {}.", backup_path.display()), false),
+                    Err(e) if e.kind() == ErrorKind::NotFound => (String::new(), false),
+                    Err(e) => (
+                        format!(" Backing up the original failed: {}, so changes won't be saved until it loads again.", e),
+                        true,
+                    ),
+                };
+                let message = format!("Failed to load {}: {}. Using default settings.{}", path.display(), e, backup_note);
+                error!("{}", message);
+                (Config::default(), Some(LoadError { message, held }))
             }
         }
-        let mut config = Config::default();
-        config.network_stats_options = NetworkStatsOptions::new(default_network_options);
-        config
     }
-}
\ No newline at end of file
+}
+
+pub struct LoadError {
+    pub message: String,
+    // Saving would overwrite the only copy of the file that failed to load
+    pub held: bool,
+}
+
+fn backup(path: &Path) -> std::io::Result<PathBuf> {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".corrupt-{}", Local::now().format("%Y%m%d-%H%M%S")));
+    let backup_path = path.with_file_name(name);
+    fs::copy(path, &backup_path)?;
+    Ok(backup_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn moves_a_v0_address_into_targets() {
+        let mut config = json!({
+            "version": 0,
+            "app_options": { "osc_options": { "ip": "192.168.1.20", "port": 9002, "update_rate": 3.0 } },
+        });
+        migrate(&mut config).unwrap();
+        let osc_options = &config["app_options"]["osc_options"];
+        assert_eq!(osc_options.get("ip"), None);
+        assert_eq!(osc_options.get("port"), None);
+        assert_eq!(osc_options["update_rate"], json!(3.0));
+        let targets: Vec<OscTarget> = serde_json::from_value(osc_options["targets"].clone()).unwrap();
+        assert_eq!(targets.len(), 1);
+        assert_eq!(targets[0].ip, "192.168.1.20");
+        assert_eq!(targets[0].port, 9002);
+        assert_eq!(config["version"], json!(CONFIG_VERSION));
+    }
+
+    #[test]
+    fn keeps_the_default_target_without_a_v0_address() {
+        let mut config = json!({ "version": 0, "app_options": { "osc_options": {} } });
+        migrate(&mut config).unwrap();
+        assert_eq!(config["app_options"]["osc_options"].get("targets"), None);
+    }
+
+    #[test]
+    fn drops_the_v1_chat_tab() {
+        let mut config = json!({ "version": 1, "chat_tab": { "draft": "unsent" }, "send_to_vrchat": false });
+        migrate(&mut config).unwrap();
+        assert_eq!(config, json!({ "version": CONFIG_VERSION, "send_to_vrchat": false }));
+    }
+
+    #[test]
+    fn treats_a_file_without_a_version_as_v0() {
+        let mut config = json!({ "app_options": { "osc_options": { "port": 9010 } }, "chat_tab": {} });
+        migrate(&mut config).unwrap();
+        assert_eq!(config["app_options"]["osc_options"]["targets"][0]["port"], json!(9010));
+        assert_eq!(config.get("chat_tab"), None);
+        assert_eq!(config["version"], json!(CONFIG_VERSION));
+    }
+
+    #[test]
+    fn leaves_a_current_file_alone() {
+        let mut config = json!({ "version": CONFIG_VERSION, "chat_tab": "kept" });
+        migrate(&mut config).unwrap();
+        assert_eq!(config, json!({ "version": CONFIG_VERSION, "chat_tab": "kept" }));
+    }
+
+    #[test]
+    fn rejects_a_newer_file() {
+        let mut config = json!({ "version": CONFIG_VERSION + 1 });
+        let error = migrate(&mut config).unwrap_err();
+        assert!(error.contains("newer than this build"), "{}", error);
+        assert_eq!(config["version"], json!(CONFIG_VERSION + 1));
+        assert!(migrate(&mut json!({ "version": "two" })).unwrap_err().contains("Invalid config version"));
+        assert!(migrate(&mut json!([])).is_err());
+    }
+
+    #[test]
+    fn names_the_step_that_failed() {
+        let mut config = json!({ "app_options": { "osc_options": { "ip": 127, "port": 9000 } } });
+        let error = migrate(&mut config).unwrap_err();
+        assert_eq!(error, "Migration from version 0 failed: ip 127 is not a string");
+    }
+}
@@ -0,0 +1,176 @@
+// Runs the integrations and the send loop without opening a window, for use as a
+// background service. Stops on SIGTERM or SIGINT after saving any pending changes.
+
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+use log::{error, info, warn};
+use single_instance::SingleInstance;
+use crate::cli::Options;
+use crate::config::{self, Config};
+use crate::osc::{self, OscClient};
+use crate::profiles::Profiles;
+use crate::ui::App;
+
+// Same pace as the GUI's repaints
+const TICK: Duration = Duration::from_millis(100);
+
+static STOP: AtomicBool = AtomicBool::new(false);
+
+// Only touches an atomic, which is all a signal handler may safely do
+extern "C" fn request_stop(_signum: libc::c_int) {
+    STOP.store(true, Ordering::SeqCst);
+}
+
+fn install_signal_handlers() {
+    let handler = request_stop as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    for signum in [libc::SIGTERM, libc::SIGINT] {
+        if unsafe { libc::signal(signum, handler) } == libc::SIG_ERR {
+            warn!("Failed to handle signal {}: {}", signum, std::io::Error::last_os_error());
+        }
+    }
+}
+
+pub fn run(options: Options) -> Result<(), String> {
+    let instance = SingleInstance::new("RustyChatBox").map_err(|e| format!("Failed to create lock: {}", e))?;
+    if !instance.is_single() {
+        return Err("Another instance is already running".to_string());
+    }
+    install_signal_handlers();
+
+    let config_path = options.config.unwrap_or_else(|| config::data_dir().join("config.json"));
+    if let Some(config_dir) = config_path.parent() {
+        fs::create_dir_all(config_dir)
+            .map_err(|e| format!("Failed to create config directory {}: {}", config_dir.display(), e))?;
+    }
+    let profiles = Profiles::load(&config_path, options.profile.as_deref());
+    info!("Using profile {}", profiles.active());
+    // A load error is already logged, and the unreadable file backed up
+    let (config, config_error) = Config::load_or_create(profiles.active_path());
+
+    if let Err(e) = crate::deps::check_dependencies() {
+        warn!("Dependency error: {}. Window activity may not work.", e);
+    }
+
+    let osc_options = &config.app_options.osc_options;
+    let targets = match &options.osc_target {
+        Some(target) => vec![target.clone()],
+        None => osc_options.targets.clone(),
+    };
+    let mut client = OscClient::new(&targets).map_err(|e| format!("Failed to initialize OSC client: {}", e))?;
+    let (mode, format) = options
+        .capture_override
+        .unwrap_or((osc_options.capture_mode, osc_options.capture_format));
+    if let Err(e) = client.set_capture(mode, format) {
+        error!("Failed to start OSC capture: {}", e);
+    }
+    let (server, oscquery) = match osc::start_receiver(osc_options) {
+        Ok((server, oscquery)) => (Some(server), oscquery),
+        // Sending still works without it, as in the GUI
+        Err(e) => {
+            error!("{}", e);
+            (None, None)
+        }
+    };
+
+    let mut app = App::new(client, server, oscquery, config, config_error, profiles, None)
+        .with_osc_target_override(options.osc_target);
+    info!("Running headless; send SIGTERM to stop");
+    while !STOP.load(Ordering::SeqCst) {
+        app.tick();
+        app.reload_config_if_changed();
+        app.save_config_if_needed();
+        thread::sleep(TICK);
+    }
+    info!("Stopping");
+    app.flush_config();
+    Ok(())
+}
+
+// systemd splits ExecStart on whitespace and treats quotes, backslashes and % specially
+fn quote_arg(arg: &str) -> String {
+    let escaped = arg.replace('\\', "\\\\").replace('"', "\\\"").replace('%', "%%");
+    if escaped.is_empty() || escaped.contains(char::is_whitespace) || escaped != arg {
+        format!("\"{}\"", escaped)
+    } else {
+        escaped
+    }
+}
+
+// A user unit that starts this binary headless with the given options
+pub fn systemd_unit(args: &[String]) -> Result<String, String> {
+    // Prefer the AppImage itself, since the extracted binary lives in a temporary mount
+    let exe = match std::env::var_os("APPIMAGE") {
+        Some(appimage) => appimage.into(),
+        None => std::env::current_exe().map_err(|e| format!("Failed to locate the executable: {}", e))?,
+    };
+    let exe = exe.to_str().ok_or("The executable's path is not valid UTF-8")?.to_string();
+    let cwd = std::env::current_dir().map_err(|e| format!("Failed to read the current directory: {}", e))?;
+    Ok(unit_file(&exe, &cwd, args))
+}
+
+fn unit_file(exe: &str, cwd: &Path, args: &[String]) -> String {
+    // The service runs from the home directory, so a relative --config would point elsewhere
+    let args = args.iter().enumerate().map(|(i, arg)| {
+        if i > 0 && args[i - 1] == "--config" {
+            cwd.join(arg).to_string_lossy().to_string()
+        } else {
+            arg.clone()
+        }
+    });
+    let exec_start = std::iter::once(exe.to_string())
+        .chain(std::iter::once("--headless".to_string()))
+        .chain(args)
+        .map(|arg| quote_arg(&arg))
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!(
+        "[Unit]
+Description=RustyChatBox (headless)
+Documentation=https://github.com/Voiasis/RustyChatBox
+After=graphical-session.target
+
+[Service]
+Type=simple
+ExecStart={}
+Restart=on-failure
+RestartSec=5
+
+[Install]
+WantedBy=graphical-session.target
+",
+        exec_start
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_only_what_systemd_would_split_or_expand() {
+        assert_eq!(quote_arg("--headless"), "--headless");
+        assert_eq!(quote_arg("/home/me/My Configs/config.json"), "\"/home/me/My Configs/config.json\"");
+        assert_eq!(quote_arg("say \"hi\""), "\"say \\\"hi\\\"\"");
+        assert_eq!(quote_arg("100%"), "\"100%%\"");
+        assert_eq!(quote_arg("C:\\dir"), "\"C:\\\\dir\"");
+        assert_eq!(quote_arg(""), "\"\"");
+    }
+
+    #[test]
+    fn builds_exec_start_from_the_options() {
+        let args: Vec<String> = ["--config", "configs/100% \"main\".json", "--profile", "Late Night"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        let unit = unit_file("/opt/Rusty ChatBox.AppImage", Path::new("/home/me"), &args);
+        let exec_start = unit.lines().find(|line| line.starts_with("ExecStart=")).unwrap();
+        assert_eq!(
+            exec_start,
+            "ExecStart=\"/opt/Rusty ChatBox.AppImage\" --headless --config \"/home/me/configs/100%% \\\"main\\\".json\" --profile \"Late Night\""
+        );
+        assert!(unit.contains("WantedBy=graphical-session.target"));
+    }
+}
@@ -0,0 +1,453 @@
+// Imports settings and status messages from a MagicChatBox data folder (or one of its files).
+// MagicChatBox keeps its status list as JSON in StatusList.xml, its general settings as a flat
+// <Settings> element in settings.xml and newer module settings in *Settings.json files. Only
+// fields with a clear counterpart here are taken over; everything else ends up in the report.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use chrono_tz::Tz;
+use log::info;
+use serde_json::Value;
+use crate::modules::{
+    component::ComponentStatsOptions,
+    media::MediaLinkOptions,
+    status::StatusOptions,
+    time::TimeOptions,
+};
+
+const STATUS_LIST_FILES: [&str; 2] = ["StatusList.xml", "StatusList.json"];
+const SETTINGS_XML: &str = "settings.xml";
+
+// What an import can change, starting from the current values
+pub struct ImportSettings {
+    pub status_messages: Vec<String>,
+    pub active_status: Option<String>,
+    pub status_options: StatusOptions,
+    pub time_options: TimeOptions,
+    pub media_link_options: MediaLinkOptions,
+    pub component_stats_options: ComponentStatsOptions,
+}
+
+#[derive(Default)]
+pub struct ImportReport {
+    pub files: Vec<String>,
+    pub imported: Vec<String>,
+    pub status_added: usize,
+    pub status_duplicates: usize,
+    // "file: field" for fields RustyChatBox has no equivalent for
+    pub unmapped: Vec<String>,
+    // "file: field: reason" for recognised fields whose value couldn't be used
+    pub invalid: Vec<String>,
+}
+
+enum Mapped {
+    Applied,
+    Unmapped,
+}
+
+pub fn import_magicchatbox(path: &Path, settings: &mut ImportSettings) -> Result<ImportReport, String> {
+    let mut report = ImportReport::default();
+    let files = if path.is_dir() {
+        data_files(path)
+    } else if path.is_file() {
+        vec![path.to_path_buf()]
+    } else {
+        return Err(format!("{} does not exist", path.display()));
+    };
+    for file in files {
+        let name = file.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        let contents = fs::read_to_string(&file).map_err(|e| format!("Failed to read {}: {}", file.display(), e))?;
+        let trimmed = contents.trim_start_matches('\u{feff}').trim_start();
+        if trimmed.starts_with('<') {
+            let fields = parse_settings_xml(trimmed).map_err(|e| format!("Failed to parse {}: {}", name, e))?;
+            apply_fields(&name, fields, settings, &mut report);
+        } else {
+            let value: Value = serde_json::from_str(trimmed).map_err(|e| format!("Failed to parse {}: {}", name, e))?;
+            match value {
+                Value::Array(items) => import_status_list(&items, settings, &mut report),
+                Value::Object(_) => {
+                    let mut fields = BTreeMap::new();
+                    flatten_json("", &value, &mut fields);
+                    apply_fields(&name, fields, settings, &mut report);
+                }
+                _ => return Err(format!("{} is not a MagicChatBox settings or status list file", name)),
+            }
+        }
+        report.files.push(name);
+    }
+    if report.files.is_empty() {
+        return Err(format!("No MagicChatBox files found in {}", path.display()));
+    }
+    info!(
+        "Imported {} settings and {} status messages from {}",
+        report.imported.len(),
+        report.status_added,
+        path.display()
+    );
+    Ok(report)
+}
+
+// The status list, then the general settings, then the per-module settings files
+fn data_files(dir: &Path) -> Vec<std::path::PathBuf> {
+    let mut files: Vec<_> = STATUS_LIST_FILES.iter().map(|name| dir.join(name)).filter(|p| p.is_file()).collect();
+    if dir.join(SETTINGS_XML).is_file() {
+        files.push(dir.join(SETTINGS_XML));
+    }
+    for settings_dir in [dir.to_path_buf(), dir.join("settings")] {
+        let mut json: Vec<_> = fs::read_dir(&settings_dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|p| p.file_name().and_then(|n| n.to_str()).is_some_and(|n| n.ends_with("Settings.json")))
+            .collect();
+        json.sort();
+        files.extend(json);
+    }
+    files
+}
+
+fn import_status_list(items: &[Value], settings: &mut ImportSettings, report: &mut ImportReport) {
+    for item in items {
+        let Some(message) = item.get("msg").and_then(Value::as_str).map(str::trim) else {
+            continue;
+        };
+        if message.is_empty() {
+            continue;
+        }
+        if settings.status_messages.iter().any(|m| m == message) {
+            report.status_duplicates += 1;
+        } else {
+            settings.status_messages.push(message.to_string());
+            report.status_added += 1;
+        }
+        if item.get("IsActive").and_then(Value::as_bool) == Some(true) {
+            settings.active_status = Some(message.to_string());
+        }
+    }
+}
+
+// Nested objects become dotted names. Arrays of component items are keyed by their type,
+// so the CPU entry's IsEnabled becomes CPU.IsEnabled.
+fn flatten_json(prefix: &str, value: &Value, fields: &mut BTreeMap<String, String>) {
+    let join = |name: &str| if prefix.is_empty() { name.to_string() } else { format!("{}.{}", prefix, name) };
+    match value {
+        Value::Object(map) => {
+            for (name, value) in map {
+                flatten_json(&join(name), value, fields);
+            }
+        }
+        Value::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                let type_field = ["ComponentType", "Type"]
+                    .into_iter()
+                    .find_map(|k| item.get(k).and_then(Value::as_str).map(|t| (k, t)));
+                match (type_field, item) {
+                    (Some((type_key, item_type)), Value::Object(map)) => {
+                        let prefix = join(item_type);
+                        for (name, value) in map.iter().filter(|(name, _)| name.as_str() != type_key) {
+                            flatten_json(&format!("{}.{}", prefix, name), value, fields);
+                        }
+                    }
+                    _ => flatten_json(&join(&index.to_string()), item, fields),
+                }
+            }
+        }
+        Value::Null => {}
+        Value::String(s) => {
+            fields.insert(prefix.to_string(), s.clone());
+        }
+        other => {
+            fields.insert(prefix.to_string(), other.to_string());
+        }
+    }
+}
+
+// Reads <Settings><Name>value</Name>...</Settings>. Nothing in settings.xml nests deeper.
+fn parse_settings_xml(xml: &str) -> Result<BTreeMap<String, String>, String> {
+    let mut fields = BTreeMap::new();
+    let mut rest = xml;
+    // Skip the declaration, comments and the root element's opening tag
+    loop {
+        rest = rest.trim_start();
+        if rest.starts_with("<?") || rest.starts_with("<!--") {
+            let end = if rest.starts_with("<?") { "?>" } else { "-->" };
+            rest = rest.find(end).map(|i| &rest[i + end.len()..]).ok_or("Unterminated declaration")?;
+        } else if rest.starts_with('<') {
+            let end = rest.find('>').ok_or("Unterminated root element")?;
+            rest = &rest[end + 1..];
+            break;
+        } else {
+            return Err("Expected a root element".to_string());
+        }
+    }
+    loop {
+        rest = rest.trim_start();
+        if rest.starts_with("</") || rest.is_empty() {
+            break;
+        }
+        if rest.starts_with("<!--") {
+            rest = rest.find("-->").map(|i| &rest[i + 3..]).ok_or("Unterminated comment")?;
+            continue;
+        }
+        if !rest.starts_with('<') {
+            return Err("Expected an element".to_string());
+        }
+        let tag_end = rest.find('>').ok_or("Unterminated element")?;
+        let tag = &rest[1..tag_end];
+        if let Some(tag) = tag.strip_suffix('/') {
+            let name = tag.split_whitespace().next().unwrap_or_default();
+            fields.insert(name.to_string(), String::new());
+            rest = &rest[tag_end + 1..];
+            continue;
+        }
+        let name = tag.split_whitespace().next().unwrap_or_default();
+        let close = format!("</{}>", name);
+        let body = &rest[tag_end + 1..];
+        let body_end = body.find(&close).ok_or_else(|| format!("Missing {}", close))?;
+        fields.insert(name.to_string(), unescape_xml(body[..body_end].trim()));
+        rest = &body[body_end + close.len()..];
+    }
+    Ok(fields)
+}
+
+fn unescape_xml(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+fn apply_fields(file: &str, fields: BTreeMap<String, String>, settings: &mut ImportSettings, report: &mut ImportReport) {
+    for (name, value) in fields {
+        match apply_field(&name, &value, settings) {
+            Ok(Mapped::Applied) => report.imported.push(format!("{}: {}", file, name)),
+            Ok(Mapped::Unmapped) => report.unmapped.push(format!("{}: {}", file, name)),
+            Err(e) => report.invalid.push(format!("{}: {}: {}", file, name, e)),
+        }
+    }
+}
+
+fn parse_bool(value: &str) -> Result<bool, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => Err(format!("{} is not true or false", value)),
+    }
+}
+
+fn parse_u32(value: &str) -> Result<u32, String> {
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|v| v.is_finite() && *v >= 0.0)
+        .map(|v| v.round().min(u32::MAX as f64) as u32)
+        .ok_or_else(|| format!("{} is not a number of 0 or more", value))
+}
+
+fn parse_model(value: &str) -> Option<String> {
+    Some(value.trim().to_string()).filter(|v| !v.is_empty())
+}
+
+// MagicChatBox offers a fixed list of zones; anything else is tried as an IANA name
+fn parse_timezone(value: &str) -> Result<String, String> {
+    let zone = match value {
+        "UTC" => "UTC",
+        "GMT" => "Europe/London",
+        "EST" => "America/New_York",
+        "CST" => "America/Chicago",
+        "PST" => "America/Los_Angeles",
+        "CET" => "Europe/Berlin",
+        "AEST" => "Australia/Sydney",
+        "IST" => "Asia/Kolkata",
+        "JST" => "Asia/Tokyo",
+        other => other,
+    };
+    zone.parse::<Tz>().map(|_| zone.to_string()).map_err(|_| format!("Unknown timezone {}", value))
+}
+
+fn apply_field(name: &str, value: &str, settings: &mut ImportSettings) -> Result<Mapped, String> {
+    let status = &mut settings.status_options;
+    let time = &mut settings.time_options.config;
+    let media = &mut settings.media_link_options;
+    let components = &mut settings.component_stats_options;
+    // Component entries are matched on their type and field, everything else on the field alone
+    let key = name.to_ascii_lowercase();
+    let mut parts = key.rsplit('.');
+    let short = parts.next().unwrap_or_default();
+    let component = parts.next().map(|component| format!("{}.{}", component, short)).unwrap_or_default();
+    match component.as_str() {
+        "cpu.isenabled" => components.show_cpu = parse_bool(value)?,
+        "gpu.isenabled" => components.show_gpu = parse_bool(value)?,
+        "vram.isenabled" => components.show_vram = parse_bool(value)?,
+        "ram.isenabled" => components.show_ram = parse_bool(value)?,
+        "cpu.removenumbertrailing" => components.cpu_round_usage = parse_bool(value)?,
+        "gpu.removenumbertrailing" => components.gpu_round_usage = parse_bool(value)?,
+        "vram.removenumbertrailing" => components.vram_round_usage = parse_bool(value)?,
+        "ram.removenumbertrailing" => components.ram_round_usage = parse_bool(value)?,
+        "cpu.replacewithhardwarename" => components.cpu_display_model = parse_bool(value)?,
+        "gpu.replacewithhardwarename" => components.gpu_display_model = parse_bool(value)?,
+        "cpu.customhardwarename" => components.cpu_custom_model = parse_model(value),
+        "gpu.customhardwarename" => components.gpu_custom_model = parse_model(value),
+        "vram.showmaxvalue" => components.vram_show_max = parse_bool(value)?,
+        "ram.showmaxvalue" => components.ram_show_max = parse_bool(value)?,
+        _ => match short {
+            "intgrstatus" => status.enabled = parse_bool(value)?,
+            "cyclestatus" => status.cycle_status = parse_bool(value)?,
+            "switchstatusinterval" => status.cycle_interval = parse_u32(value)?.max(1),
+            "israndomcycling" => status.cycle_random = parse_bool(value)?,
+            "prefixiconstatus" => status.add_speech_bubble = parse_bool(value)?,
+            "enableemojishuffle" => status.enable_custom_prefix_shuffle = parse_bool(value)?,
+            "intgrcurrenttime" => time.enabled = parse_bool(value)?,
+            "prefixtime" => time.show_my_time_prefix = parse_bool(value)?,
+            "time24h" => time.use_24_hour = parse_bool(value)?,
+            "usesystemculture" => time.use_system_culture = parse_bool(value)?,
+            "autosetdaylight" => time.auto_dst = parse_bool(value)?,
+            "selectedtimezone" => time.custom_timezone = Some(parse_timezone(value)?),
+            "intgrscanmedialink" => media.enabled = parse_bool(value)?,
+            "prefixiconmusic" => media.use_music_note_prefix = parse_bool(value)?,
+            "pauseiconmusic" => media.show_pause_emoji = parse_bool(value)?,
+            "mediasession_autoswitchstate" => media.auto_switch_state = parse_bool(value)?,
+            "mediasession_autoswitch" => media.auto_switch_session = parse_bool(value)?,
+            "mediasession_timeout" => media.forget_session_seconds = parse_u32(value)?,
+            "intgrcomponentstats" => components.enabled = parse_bool(value)?,
+            _ => return Ok(Mapped::Unmapped),
+        },
+    }
+    Ok(Mapped::Applied)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn fixtures() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/magicchatbox")
+    }
+
+    fn current_settings() -> ImportSettings {
+        ImportSettings {
+            status_messages: vec!["Existing".to_string()],
+            active_status: Some("Existing".to_string()),
+            status_options: StatusOptions::default(),
+            time_options: TimeOptions::default(),
+            media_link_options: MediaLinkOptions::default(),
+            component_stats_options: ComponentStatsOptions::default(),
+        }
+    }
+
+    #[test]
+    fn reads_the_data_folder_in_order() {
+        let mut settings = current_settings();
+        let report = import_magicchatbox(&fixtures(), &mut settings).unwrap();
+        assert_eq!(report.files, ["StatusList.xml", "settings.xml", "ComponentStatsSettings.json"]);
+    }
+
+    #[test]
+    fn imports_status_messages_without_duplicates() {
+        let mut settings = current_settings();
+        let report = import_magicchatbox(&fixtures().join("StatusList.xml"), &mut settings).unwrap();
+        assert_eq!(settings.status_messages, ["Existing", "Working on a world", "AFK"]);
+        assert_eq!(report.status_added, 2);
+        // "Existing" was already there and "Working on a world" comes twice
+        assert_eq!(report.status_duplicates, 2);
+        assert_eq!(settings.active_status.as_deref(), Some("Working on a world"));
+        assert_eq!(report.files, ["StatusList.xml"]);
+    }
+
+    #[test]
+    fn keeps_the_active_status_when_none_is_marked() {
+        let dir = crate::testing::temp_dir("import-inactive");
+        let file = dir.join("StatusList.xml");
+        fs::write(&file, r#"[{"msg": "Existing", "IsActive": false}, {"msg": "New"}]"#).unwrap();
+        let mut settings = current_settings();
+        let report = import_magicchatbox(&file, &mut settings).unwrap();
+        assert_eq!(settings.active_status.as_deref(), Some("Existing"));
+        assert_eq!((report.status_added, report.status_duplicates), (1, 1));
+    }
+
+    #[test]
+    fn maps_general_settings() {
+        let mut settings = current_settings();
+        settings.status_options.enabled = false;
+        settings.status_options.cycle_status = false;
+        settings.time_options.config.use_24_hour = false;
+        let report = import_magicchatbox(&fixtures().join("settings.xml"), &mut settings).unwrap();
+        assert!(settings.status_options.enabled);
+        assert!(settings.status_options.cycle_status);
+        assert_eq!(settings.status_options.cycle_interval, 45);
+        assert!(settings.time_options.config.use_24_hour);
+        assert_eq!(settings.time_options.config.custom_timezone.as_deref(), Some("Asia/Tokyo"));
+        assert!(!settings.media_link_options.enabled);
+        assert!(settings.media_link_options.show_pause_emoji);
+        assert!(report.imported.contains(&"settings.xml: Time24H".to_string()));
+        assert_eq!(
+            report.unmapped,
+            ["settings.xml: LastUsedVersion", "settings.xml: OSCIP", "settings.xml: Theme"]
+        );
+        assert_eq!(
+            report.invalid,
+            [
+                "settings.xml: MediaSession_Timeout: -5 is not a number of 0 or more",
+                "settings.xml: PrefixTime: maybe is not true or false",
+            ]
+        );
+    }
+
+    #[test]
+    fn leaves_invalid_fields_untouched() {
+        let mut settings = current_settings();
+        let timeout = settings.media_link_options.forget_session_seconds;
+        let prefix = settings.time_options.config.show_my_time_prefix;
+        import_magicchatbox(&fixtures().join("settings.xml"), &mut settings).unwrap();
+        assert_eq!(settings.media_link_options.forget_session_seconds, timeout);
+        assert_eq!(settings.time_options.config.show_my_time_prefix, prefix);
+    }
+
+    #[test]
+    fn maps_component_settings_by_type() {
+        let mut settings = current_settings();
+        settings.component_stats_options.show_gpu = true;
+        settings.component_stats_options.gpu_custom_model = Some("Old".to_string());
+        let report = import_magicchatbox(&fixtures().join("ComponentStatsSettings.json"), &mut settings).unwrap();
+        let components = &settings.component_stats_options;
+        assert!(components.show_cpu);
+        assert!(components.cpu_round_usage);
+        assert!(components.cpu_display_model);
+        assert_eq!(components.cpu_custom_model.as_deref(), Some("Ryzen 7 5800X3D"));
+        assert!(!components.show_gpu);
+        // An empty name clears the model rather than setting it to ""
+        assert_eq!(components.gpu_custom_model, None);
+        assert!(components.ram_show_max);
+        assert_eq!(
+            report.unmapped,
+            [
+                "ComponentStatsSettings.json: ComponentStatsList.RAM.ShowSmallName",
+                "ComponentStatsSettings.json: UpdateInterval",
+            ]
+        );
+        assert_eq!(
+            report.invalid,
+            ["ComponentStatsSettings.json: ComponentStatsList.RAM.IsEnabled: sometimes is not true or false"]
+        );
+    }
+
+    #[test]
+    fn accepts_zero() {
+        assert_eq!(parse_u32("0"), Ok(0));
+        assert_eq!(parse_u32("2.6"), Ok(3));
+        assert!(parse_u32("-1").is_err());
+        assert!(parse_u32("NaN").is_err());
+    }
+
+    #[test]
+    fn reports_missing_and_empty_paths() {
+        assert!(import_magicchatbox(&fixtures().join("missing"), &mut current_settings()).is_err());
+        let empty = crate::testing::temp_dir("import-empty");
+        let error = import_magicchatbox(&empty, &mut current_settings()).err().unwrap();
+        assert!(error.starts_with("No MagicChatBox files found"));
+    }
+}
@@ -6,14 +6,27 @@ use eframe::egui;
 use eframe::egui::{IconData, FontDefinitions, FontFamily, Color32, RichText, Visuals, Stroke, Rounding};
 use image::io::Reader as ImageReader;
 use arboard::Clipboard;
-use crate::config::Config;
+use crate::config::{Config, LoadError};
+use crate::profiles::Profiles;
 use crate::ui::App;
-use crate::osc::OscClient;
-use crate::modules::network::NetworkOptions;
+use crate::osc::{OscClient, OscQueryService, OscServer};
+use crate::osc::capture::{CaptureFormat, CaptureMode};
+use crate::modules::app::OscTarget;
+use crate::cli::{Launch, USAGE};
 
+mod cli;
+mod compose;
 mod deps;
+mod headless;
 mod osc;
 mod config;
+mod import;
+mod persist;
+mod profiles;
+mod template;
+mod stylize;
+#[cfg(test)]
+mod testing;
 mod ui;
 mod modules {
     pub mod time;
@@ -25,6 +38,11 @@ mod modules {
     pub mod chatting;
     pub mod app;
     pub mod activity;
+    pub mod integration;
+    pub mod parameters;
+    pub mod router;
+    pub mod layout;
+    pub mod rules;
 }
 
 #[derive(Clone, PartialEq)]
@@ -44,31 +62,45 @@ struct LoadingApp {
     state: LoadingState,
     progress: f32,
     message: String,
+    // The default profile's file
     config_path: std::path::PathBuf,
+    // Profile asked for on the command line
+    requested_profile: Option<String>,
+    // Destination given on the command line, used instead of the configured targets
+    osc_target: Option<OscTarget>,
+    profiles: Option<Profiles>,
     config: Option<Config>,
+    config_error: Option<LoadError>,
     osc_client: Option<OscClient>,
+    osc_server: Option<OscServer>,
+    oscquery: Option<OscQueryService>,
     icon_data: Option<IconData>,
     font_definitions: Option<FontDefinitions>,
     error: Option<String>,
     spinner_frame: usize,
+    capture_override: Option<(CaptureMode, CaptureFormat)>,
 }
 
 impl LoadingApp {
-    fn new() -> Self {
+    fn new(options: cli::Options) -> Self {
         Self {
             state: LoadingState::SingleInstance,
             progress: 0.0,
             message: "Checking single instance...".to_string(),
-            config_path: dirs::config_dir()
-                .unwrap_or_else(|| std::path::PathBuf::from("."))
-                .join("RustyChatBox")
-                .join("config.json"),
+            config_path: options.config.unwrap_or_else(|| config::data_dir().join("config.json")),
+            requested_profile: options.profile,
+            osc_target: options.osc_target,
+            profiles: None,
             config: None,
+            config_error: None,
             osc_client: None,
+            osc_server: None,
+            oscquery: None,
             icon_data: None,
             font_definitions: None,
             error: None,
             spinner_frame: 0,
+            capture_override: options.capture_override,
         }
     }
 
@@ -104,7 +136,7 @@ impl LoadingApp {
             }
             LoadingState::ConfigDir => {
                 log::info!("Setting up config directory");
-                let config_dir = self.config_path.parent().unwrap();
+                let config_dir = self.config_path.parent().unwrap_or(std::path::Path::new("."));
                 if !config_dir.exists() {
                     if let Err(e) = fs::create_dir_all(config_dir) {
                         let error_msg = format!("Failed to create config directory: {}", e);
@@ -120,19 +152,12 @@ impl LoadingApp {
             }
             LoadingState::LoadConfig => {
                 log::info!("Loading configuration");
-                let default_network_options = NetworkOptions {
-                    use_interface_max_speed: false,
-                    show_download_speed: false,
-                    show_upload_speed: false,
-                    show_max_download: false,
-                    show_max_upload: false,
-                    show_total_download: false,
-                    show_total_upload: false,
-                    show_utilization: false,
-                    stylized_chars: false,
-                };
-                let config = Config::load_or_create(&self.config_path, default_network_options);
+                let profiles = Profiles::load(&self.config_path, self.requested_profile.as_deref());
+                log::info!("Using profile {}", profiles.active());
+                let (config, config_error) = Config::load_or_create(profiles.active_path());
+                self.profiles = Some(profiles);
                 self.config = Some(config);
+                self.config_error = config_error;
                 self.state = LoadingState::OscClient;
                 self.progress = 0.375;
                 self.message = "Initializing OSC client...".to_string();
@@ -140,16 +165,37 @@ impl LoadingApp {
             LoadingState::OscClient => {
                 log::info!("Initializing OSC client");
                 if let Some(config) = &self.config {
-                    match OscClient::new(&config.app_options.osc_options.ip, config.app_options.osc_options.port) {
-                        Ok(client) => self.osc_client = Some(client),
+                    let targets = match &self.osc_target {
+                        Some(target) => vec![target.clone()],
+                        None => config.app_options.osc_options.targets.clone(),
+                    };
+                    match OscClient::new(&targets) {
+                        Ok(mut client) => {
+                            let osc_options = &config.app_options.osc_options;
+                            let (mode, format) = self
+                                .capture_override
+                                .unwrap_or((osc_options.capture_mode, osc_options.capture_format));
+                            if let Err(e) = client.set_capture(mode, format) {
+                                log::error!("Failed to start OSC capture: {}", e);
+                            }
+                            self.osc_client = Some(client);
+                        }
                         Err(e) => {
                             let error_msg = format!("Failed to initialize OSC client: {}", e);
                             log::error!("{}", error_msg);
-                            self.error = Some(format!("{} Check the IP ({}) and port ({}).", error_msg, config.app_options.osc_options.ip, config.app_options.osc_options.port));
+                            self.error = Some(error_msg);
                             self.state = LoadingState::Done;
                             return None::<RustyGUI>;
                         }
                     }
+                    match osc::start_receiver(&config.app_options.osc_options) {
+                        Ok((server, oscquery)) => {
+                            self.osc_server = Some(server);
+                            self.oscquery = oscquery;
+                        }
+                        // Another OSC app may already own the port; sending still works without it
+                        Err(e) => log::error!("{}", e),
+                    }
                 }
                 self.state = LoadingState::Dependencies;
                 self.progress = 0.5;
@@ -213,6 +259,16 @@ impl LoadingApp {
                 );
                 fonts.families.entry(FontFamily::Proportional).or_insert_with(Vec::new).push("NotoEmoji".to_owned());
                 fonts.families.entry(FontFamily::Monospace).or_insert_with(Vec::new).push("NotoEmoji".to_owned());
+                // The bundled fonts lack the letterlike blocks used by text styles, so borrow a system font if there is one
+                for path in SYMBOL_FONT_PATHS {
+                    if let Ok(data) = std::fs::read(path) {
+                        log::info!("Using {} for styled text", path);
+                        fonts.font_data.insert("Symbols".to_owned(), egui::FontData::from_owned(data));
+                        fonts.families.entry(FontFamily::Proportional).or_insert_with(Vec::new).push("Symbols".to_owned());
+                        fonts.families.entry(FontFamily::Monospace).or_insert_with(Vec::new).push("Symbols".to_owned());
+                        break;
+                    }
+                }
                 self.font_definitions = Some(fonts);
                 self.state = LoadingState::InitializeApp;
                 self.progress = 0.875;
@@ -220,7 +276,7 @@ impl LoadingApp {
             }
             LoadingState::InitializeApp => {
                 log::info!("Initializing application");
-                if let (Some(config), Some(osc_client), Some(font_definitions)) = (self.config.take(), self.osc_client.take(), self.font_definitions.take()) {
+                if let (Some(config), Some(profiles), Some(osc_client), Some(font_definitions)) = (self.config.take(), self.profiles.take(), self.osc_client.take(), self.font_definitions.take()) {
                     let clipboard = match Clipboard::new() {
                         Ok(clipboard) => clipboard,
                         Err(e) => {
@@ -231,14 +287,14 @@ impl LoadingApp {
                             return None::<RustyGUI>;
                         }
                     };
-                    let app = App::new(osc_client, config, clipboard);
+                    let app = App::new(osc_client, self.osc_server.take(), self.oscquery.take(), config, self.config_error.take(), profiles, Some(clipboard))
+                        .with_osc_target_override(self.osc_target.take());
                     self.state = LoadingState::Done;
                     self.progress = 1.0;
                     self.message = "Complete".to_string();
                     log::info!("Application initialized");
                     return Some(RustyGUI {
                         app,
-                        config_path: self.config_path.clone(),
                         font_definitions,
                     });
                 }
@@ -251,7 +307,6 @@ impl LoadingApp {
 
 struct RustyGUI {
     app: App,
-    config_path: std::path::PathBuf,
     font_definitions: FontDefinitions,
 }
 
@@ -259,7 +314,8 @@ impl eframe::App for RustyGUI {
     fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
         ctx.set_fonts(self.font_definitions.clone());
         self.app.update(ctx, frame);
-        self.app.save_config_if_needed(&self.config_path);
+        self.app.reload_config_if_changed();
+        self.app.save_config_if_needed();
     }
 }
 
@@ -270,18 +326,31 @@ enum AppState {
 
 struct RustyChatBoxApp {
     state: AppState,
+    // Minimize on the first frame; the viewport builder has no option for it
+    start_minimized: bool,
 }
 
 impl RustyChatBoxApp {
-    fn new() -> Self {
+    fn new(options: cli::Options) -> Self {
         Self {
-            state: AppState::Loading(LoadingApp::new()),
+            start_minimized: options.start_minimized,
+            state: AppState::Loading(LoadingApp::new(options)),
         }
     }
 }
 
 impl eframe::App for RustyChatBoxApp {
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        if let AppState::Running(gui) = &mut self.state {
+            gui.app.flush_config();
+        }
+    }
+
     fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        if self.start_minimized {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(true));
+            self.start_minimized = false;
+        }
         let hex_to_color = |hex: &str| {
             let hex = hex.trim_start_matches('#');
             let r = u8::from_str_radix(&hex[0..2], 16).unwrap_or(0);
@@ -401,23 +470,22 @@ impl eframe::App for RustyChatBoxApp {
     }
 }
 
-fn setup_logger() -> Result<(), fern::InitError> {
-    let log_dir = dirs::config_dir()
-        .ok_or_else(|| fern::InitError::Io(std::io::Error::new(std::io::ErrorKind::NotFound, "Config dir not found")))?
-        .join("RustyChatBox");
+fn setup_logger(level: Option<log::LevelFilter>) -> Result<(), fern::InitError> {
+    let log_dir = config::data_dir();
     let logs_folder = log_dir.join("logs");
     std::fs::create_dir_all(&logs_folder)?;
     let latest_log = log_dir.join("latestlog.txt");
     let timestamp = chrono::Local::now().format("%Y-%m-%d_%H-%M-%S");
     let timestamped_log = logs_folder.join(format!("{}.txt", timestamp));
     let _ = std::fs::remove_file(&latest_log);
-    let log_level = match std::env::var("RUST_LOG").as_deref() {
+    let log_level = level.unwrap_or(match std::env::var("RUST_LOG").as_deref() {
+        Ok("trace") => log::LevelFilter::Trace,
         Ok("debug") => log::LevelFilter::Debug,
         Ok("info") => log::LevelFilter::Info,
         Ok("warn") => log::LevelFilter::Warn,
         Ok("error") => log::LevelFilter::Error,
         _ => log::LevelFilter::Info,
-    };
+    });
 
     fern::Dispatch::new()
         .format(|out, message, record| {
@@ -437,12 +505,82 @@ fn setup_logger() -> Result<(), fern::InitError> {
     Ok(())
 }
 
+// Tried in order; any one of these covers the characters text styles produce
+const SYMBOL_FONT_PATHS: &[&str] = &[
+    "/usr/share/fonts/noto/NotoSansMath-Regular.ttf",
+    "/usr/share/fonts/truetype/noto/NotoSansMath-Regular.ttf",
+    "/usr/share/fonts/google-noto/NotoSansMath-Regular.ttf",
+    "/usr/share/fonts/TTF/DejaVuSans.ttf",
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/dejavu-sans-fonts/DejaVuSans.ttf",
+];
+
 fn main() {
-    if let Err(e) = setup_logger() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let launch = match cli::parse_args(&args) {
+        Ok(launch) => launch,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    let log_level = match &launch {
+        Launch::Run(options) => options.log_level,
+        Launch::Replay { log_level, .. } => *log_level,
+        Launch::Help => {
+            println!("{}", USAGE);
+            return;
+        }
+        Launch::SystemdUnit(args) => {
+            match headless::systemd_unit(args) {
+                Ok(unit) => print!("{}", unit),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+    };
+    if let Launch::Run(cli::Options { portable: true, .. }) = launch {
+        match cli::portable_dir() {
+            Ok(dir) => config::set_data_dir(dir),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if let Err(e) = setup_logger(log_level) {
         eprintln!("Failed to initialize logger: {}", e);
         std::process::exit(1);
     }
+
+    let options = match launch {
+        Launch::Run(options) => options,
+        Launch::Replay { path, ip, port, fast, .. } => {
+            match osc::capture::replay(&path, &ip, port, fast) {
+                Ok(count) => log::info!("Replayed {} packets", count),
+                Err(e) => {
+                    log::error!("Replay of {} failed: {}", path.display(), e);
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+        Launch::Help | Launch::SystemdUnit(_) => return,
+    };
     log::info!("Starting RustyChatBox");
+    log::info!("Using data directory {}", config::data_dir().display());
+    if options.headless {
+        if let Err(e) = headless::run(options) {
+            log::error!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
 
     let native_options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
@@ -454,7 +592,7 @@ fn main() {
     if let Err(e) = eframe::run_native(
         "RustyChatBox",
         native_options,
-        Box::new(|_cc| Ok(Box::new(RustyChatBoxApp::new()))),
+        Box::new(move |_cc| Ok(Box::new(RustyChatBoxApp::new(options)))),
     ) {
         log::error!("Failed to run the application: {}", e);
         eprintln!("Failed to run the application: {}", e);
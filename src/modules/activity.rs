@@ -5,6 +5,7 @@ use wayland_protocols_wlr::foreign_toplevel::v1::client::{
     zwlr_foreign_toplevel_handle_v1::{self, ZwlrForeignToplevelHandleV1},
     zwlr_foreign_toplevel_manager_v1::ZwlrForeignToplevelManagerV1,
 };
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use eframe::egui;
 use log::{debug, error, info};
@@ -12,13 +13,18 @@ use std::process::Command;
 use openxr as xr;
 extern crate ini;
 use ini::Ini;
-
-const MAX_LINE_WIDTH: usize = 27;
+use crate::compose;
+use crate::stylize::{self, TextStyle};
 
 #[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct WindowActivityOptions {
     pub enabled: bool,
     pub max_title_length: u32,
+    // Scroll long titles through a max_title_length window instead of cutting them
+    pub marquee: bool,
+    pub marquee_step: u32,
+    pub style: TextStyle,
     pub show_desktop_app: bool,
     pub desktop_prefix: String,
     pub desktop_middle: String,
@@ -34,6 +40,9 @@ impl Default for WindowActivityOptions {
         Self {
             enabled: true,
             max_title_length: 50,
+            marquee: false,
+            marquee_step: 3,
+            style: TextStyle::None,
             show_desktop_app: true,
             desktop_prefix: "On desktop".to_string(),
             desktop_middle: "in".to_string(),
@@ -56,6 +65,18 @@ impl WindowActivityOptions {
                 let r = ui.add(egui::DragValue::new(&mut self.max_title_length).range(1..=100));
                 response |= r;
             });
+            ui.horizontal(|ui| {
+                response |= ui.checkbox(&mut self.marquee, "Scroll long titles");
+                if self.marquee {
+                    ui.label("by");
+                    response |= ui.add(egui::DragValue::new(&mut self.marquee_step).range(1..=20));
+                    ui.label("characters per update");
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Text style: ");
+                response |= stylize::style_combo(ui, "window_activity_style", &mut self.style);
+            });
 
             ui.heading("Desktop Activity");
             let r = ui.checkbox(&mut self.show_desktop_app, "Show desktop focused app details");
@@ -114,12 +135,14 @@ impl WindowActivityOptions {
 pub struct WindowActivityModule {
     wayland_conn: Option<Connection>,
     x11_worker: Option<std::thread::JoinHandle<()>>,
+    x11_running: Arc<AtomicBool>,
     is_vr_active: Arc<Mutex<bool>>,
     current_title: Arc<Mutex<String>>,
 }
 
 impl Drop for WindowActivityModule {
     fn drop(&mut self) {
+        self.x11_running.store(false, Ordering::Relaxed);
         if let Some(worker) = self.x11_worker.take() {
             debug!("Joining X11 worker thread");
             worker.join().expect("Failed to join X11 worker thread");
@@ -128,9 +151,13 @@ impl Drop for WindowActivityModule {
 }
 
 impl WindowActivityModule {
-    /*pub fn is_vr_active(&self) -> bool {
+    pub fn is_vr_active(&self) -> bool {
         *self.is_vr_active.lock().unwrap()
-    }*/
+    }
+
+    pub fn current_title(&self) -> String {
+        self.current_title.lock().unwrap().clone()
+    }
 
     pub fn new(_options: &WindowActivityOptions) -> Self {
         let wayland_conn = Connection::connect_to_env().ok();
@@ -140,6 +167,7 @@ impl WindowActivityModule {
         let mut module = Self {
             wayland_conn,
             x11_worker: None,
+            x11_running: Arc::new(AtomicBool::new(true)),
             is_vr_active,
             current_title,
         };
@@ -401,9 +429,10 @@ impl WindowActivityModule {
         } else {
             debug!("Starting X11 window detection (via xdotool)");
             let current_title = Arc::clone(&self.current_title);
+            let running = Arc::clone(&self.x11_running);
             self.x11_worker = Some(std::thread::spawn(move || {
                 let mut last_title = String::new();
-                loop {
+                while running.load(Ordering::Relaxed) {
                     let title = get_x11_window_title()
                         .unwrap_or_else(|| "No active window".to_string());
                     if title != last_title {
@@ -418,83 +447,66 @@ impl WindowActivityModule {
         }
     }
 
-    pub fn get_formatted_activity(&self, options: &WindowActivityOptions) -> Option<String> {
+    // marquee is only given on send ticks, so the title scrolls once per update
+    pub fn get_formatted_activity(&self, options: &WindowActivityOptions, marquee: Option<&mut compose::Marquee>) -> Option<String> {
         let title = self.current_title.lock().unwrap().clone();
         let is_vr_active = *self.is_vr_active.lock().unwrap();
-        let (prefix, middle, suffix) = if is_vr_active {
-            (&options.vr_prefix, &options.vr_middle, &options.vr_suffix)
-        } else {
-            (&options.desktop_prefix, &options.desktop_middle, &options.desktop_suffix)
-        };
-    
-        if title.is_empty() || title == "No active window" || title == "No display server" || 
-           title == "Window detection unavailable" || title == "Wayland detection failed" {
-            return if !prefix.is_empty() {
-                Some(prefix.to_string())
-            } else {
-                None
-            };
-        }
-    
-        let formatted_title = if title.len() > options.max_title_length as usize {
-            format!("{}...", &title[..options.max_title_length as usize - 3])
+        format_activity(&title, is_vr_active, options, marquee)
+    }
+}
+
+fn format_activity(
+    title: &str,
+    is_vr_active: bool,
+    options: &WindowActivityOptions,
+    marquee: Option<&mut compose::Marquee>,
+) -> Option<String> {
+    let (prefix, middle, suffix) = if is_vr_active {
+        (&options.vr_prefix, &options.vr_middle, &options.vr_suffix)
+    } else {
+        (&options.desktop_prefix, &options.desktop_middle, &options.desktop_suffix)
+    };
+
+    if title.is_empty() || title == "No active window" || title == "No display server" || 
+       title == "Window detection unavailable" || title == "Wayland detection failed" {
+        return if !prefix.is_empty() {
+            Some(prefix.to_string())
         } else {
-            title
+            None
         };
-    
-        let mut parts = Vec::new();
-        if (is_vr_active && options.show_vr_app) || (!is_vr_active && options.show_desktop_app) {
-            parts.push(prefix);
-            parts.push(middle);
-            parts.push(suffix);
-        } else {
-            parts.push(prefix);
-        }
-    
-        let result = parts
-            .into_iter()
-            .filter(|s| !s.is_empty())
-            .map(|s| s.to_string())
-            .collect::<Vec<String>>()
-            .join(" ");
-        
-        let final_result = result.replace("%app%", &formatted_title);
-    
-        let mut lines = Vec::new();
-        let mut current_line = String::new();
-        let words = final_result.split_whitespace();
-    
-        for word in words {
-            let word_len = word.len();
-            let space_len = if current_line.is_empty() { 0 } else { 1 };
-            let potential_len = current_line.len() + space_len + word_len;
-    
-            if potential_len > MAX_LINE_WIDTH && !current_line.is_empty() {
-                lines.push(current_line);
-                current_line = word.to_string();
-            }
-            else if word_len > MAX_LINE_WIDTH {
-                if !current_line.is_empty() {
-                    lines.push(current_line);
-                }
-                lines.push(word[..MAX_LINE_WIDTH].to_string());
-                current_line = String::new();
-            }
-            else {
-                if !current_line.is_empty() {
-                    current_line.push(' ');
-                }
-                current_line.push_str(word);
-            }
-        }
-    
-        if !current_line.is_empty() {
-            lines.push(current_line);
-        }
-    
-        Some(lines.join("\n"))
     }
 
+    // Styled first, since a style can change how long the title is
+    let title = stylize::stylize(title, options.style);
+    let formatted_title = match marquee.filter(|_| options.marquee) {
+        Some(marquee) => marquee.advance(&title, options.max_title_length as usize, options.marquee_step as usize),
+        None => compose::truncate_with_ellipsis(&title, options.max_title_length as usize),
+    };
+
+    let mut parts = Vec::new();
+    if (is_vr_active && options.show_vr_app) || (!is_vr_active && options.show_desktop_app) {
+        parts.push(prefix);
+        parts.push(middle);
+        parts.push(suffix);
+    } else {
+        parts.push(prefix);
+    }
+
+    let result = parts
+        .into_iter()
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+        .collect::<Vec<String>>()
+        .join(" ");
+    
+    // The placeholder is left unstyled so it can still be found
+    let final_result = result
+        .split("%app%")
+        .map(|part| stylize::stylize(part, options.style))
+        .collect::<Vec<_>>()
+        .join(&formatted_title);
+
+    Some(compose::wrap_words(&final_result, compose::MAX_LINE_WIDTH).join("\n"))
 }
 
 fn get_kwin_active_application_name() -> Result<String, String> {
@@ -732,4 +744,49 @@ impl wayland_client::Dispatch<ZwlrForeignToplevelHandleV1, ()> for WaylandState
             _ => {}
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(style: TextStyle) -> WindowActivityOptions {
+        WindowActivityOptions {
+            style,
+            max_title_length: 10,
+            ..WindowActivityOptions::default()
+        }
+    }
+
+    #[test]
+    fn truncates_the_styled_title() {
+        let formatted = format_activity("Blender Foundation", false, &options(TextStyle::Uppercase), None).unwrap();
+        assert_eq!(formatted, "ON DESKTOP IN BLENDER...");
+        // The ß grows to SS when uppercased, and the cut is measured after that
+        let formatted = format_activity("Straßenbahn", false, &options(TextStyle::Uppercase), None).unwrap();
+        assert_eq!(formatted, "ON DESKTOP IN STRASSE...");
+    }
+
+    #[test]
+    fn styles_around_the_placeholder() {
+        let formatted = format_activity("Blender", false, &options(TextStyle::Bold), None).unwrap();
+        assert_eq!(formatted, stylize::stylize("On desktop in Blender", TextStyle::Bold));
+        let formatted = format_activity("Blender Foundation", false, &options(TextStyle::Fullwidth), None).unwrap();
+        assert!(formatted.ends_with("Ｂｌｅｎｄｅｒ..."), "{}", formatted);
+    }
+
+    #[test]
+    fn scrolls_the_styled_title() {
+        let mut marquee = compose::Marquee::default();
+        let options = WindowActivityOptions {
+            marquee: true,
+            marquee_step: 1,
+            ..options(TextStyle::Bold)
+        };
+        let first = format_activity("Blender Foundation", false, &options, Some(&mut marquee)).unwrap();
+        let second = format_activity("Blender Foundation", false, &options, Some(&mut marquee)).unwrap();
+        let bold_title = stylize::stylize("Blender Fo", TextStyle::Bold);
+        assert!(first.ends_with(&bold_title), "{}", first);
+        assert!(second.ends_with(&stylize::stylize("lender Fou", TextStyle::Bold)), "{}", second);
+    }
+}
@@ -1,21 +1,66 @@
 use serde::{Serialize, Deserialize};
 use eframe::egui;
+use crate::osc::capture::{self, CaptureFormat, CaptureMode};
 
-#[derive(Clone, Serialize, Deserialize)]
-pub struct OscOptions {
+#[derive(Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct OscTarget {
+    pub name: String,
+    pub enabled: bool,
     pub ip: String,
     pub port: u16,
+    // Send to the VRChat client found over OSCQuery instead of ip/port while one is known
+    pub follow_oscquery: bool,
+}
+
+impl Default for OscTarget {
+    fn default() -> Self {
+        OscTarget {
+            name: "VRChat".to_string(),
+            enabled: true,
+            ip: "127.0.0.1".to_string(),
+            port: 9000,
+            follow_oscquery: true,
+        }
+    }
+}
+
+impl OscTarget {
+    pub fn addr(&self) -> String {
+        format!("{}:{}", self.ip, self.port)
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OscOptions {
+    pub targets: Vec<OscTarget>,
+    pub receive_port: u16,
+    pub oscquery_enabled: bool,
     pub update_rate: f32,
     pub separate_lines: bool,
+    // Rotate through pages of output instead of dropping what doesn't fit
+    pub paging: bool,
+    pub page_dwell: f32,
+    pub page_indicator: bool,
+    #[serde(serialize_with = "capture::save_mode", deserialize_with = "capture::load_mode")]
+    pub capture_mode: CaptureMode,
+    pub capture_format: CaptureFormat,
 }
 
 impl Default for OscOptions {
     fn default() -> Self {
         OscOptions {
-            ip: "127.0.0.1".to_string(),
-            port: 9000,
+            targets: vec![OscTarget::default()],
+            receive_port: 9001,
+            oscquery_enabled: true,
             update_rate: 1.6,
             separate_lines: true,
+            paging: false,
+            page_dwell: 5.0,
+            page_indicator: true,
+            capture_mode: CaptureMode::Off,
+            capture_format: CaptureFormat::JsonLines,
         }
     }
 }
@@ -55,14 +100,45 @@ impl AppOptionsOptions {
             ui.id().with("app_options"),
             egui::Sense::hover(),
         );
-        ui.horizontal(|ui| {
-            ui.label("OSC IP: ");
-            response |= ui.text_edit_singleline(&mut self.app_options.osc_options.ip);
-        });
-        ui.horizontal(|ui| {
-            ui.label("OSC Port: ");
-            response |= ui.add(egui::DragValue::new(&mut self.app_options.osc_options.port).speed(1.0));
-        });
+        ui.label("OSC Targets:");
+        let mut remove_index = None;
+        for (index, target) in self.app_options.osc_options.targets.iter_mut().enumerate() {
+            ui.push_id(index, |ui| {
+                ui.horizontal(|ui| {
+                    response |= ui.checkbox(&mut target.enabled, "");
+                    response |= ui.add(egui::TextEdit::singleline(&mut target.name).desired_width(100.0).hint_text("Name"));
+                    response |= ui.add(egui::TextEdit::singleline(&mut target.ip).desired_width(110.0).hint_text("IP"));
+                    response |= ui.add(egui::DragValue::new(&mut target.port).speed(1.0));
+                    response |= ui.checkbox(&mut target.follow_oscquery, "Follow OSCQuery")
+                        .on_hover_text("Send to the VRChat client found over OSCQuery instead of this address");
+                    if ui.button("X").clicked() {
+                        remove_index = Some(index);
+                    }
+                });
+            });
+        }
+        if let Some(index) = remove_index {
+            self.app_options.osc_options.targets.remove(index);
+            response.mark_changed();
+        }
+        if ui.button("Add Target").clicked() {
+            self.app_options.osc_options.targets.push(OscTarget {
+                name: format!("Target {}", self.app_options.osc_options.targets.len() + 1),
+                follow_oscquery: false,
+                ..OscTarget::default()
+            });
+            response.mark_changed();
+        }
+        response |= ui.checkbox(
+            &mut self.app_options.osc_options.oscquery_enabled,
+            "Use OSCQuery to find VRChat and share its OSC output",
+        );
+        if !self.app_options.osc_options.oscquery_enabled {
+            ui.horizontal(|ui| {
+                ui.label("OSC Receive Port: ");
+                response |= ui.add(egui::DragValue::new(&mut self.app_options.osc_options.receive_port).speed(1.0));
+            });
+        }
         ui.horizontal(|ui| {
             ui.label("Update Rate: ");
             response |= ui.add(
@@ -75,6 +151,38 @@ impl AppOptionsOptions {
             &mut self.app_options.osc_options.separate_lines,
             "Separate lines in OSC output",
         );
+        response |= ui.checkbox(
+            &mut self.app_options.osc_options.paging,
+            "Rotate pages when the chatbox is full",
+        );
+        if self.app_options.osc_options.paging {
+            let osc_options = &mut self.app_options.osc_options;
+            ui.horizontal(|ui| {
+                ui.label("Page Time: ");
+                response |= ui.add(
+                    egui::Slider::new(&mut osc_options.page_dwell, osc_options.update_rate..=30.0)
+                        .step_by(0.1)
+                        .text("seconds"),
+                );
+            });
+            response |= ui.checkbox(&mut osc_options.page_indicator, "Show page number (1/3)");
+        }
+        ui.horizontal(|ui| {
+            ui.label("Capture: ");
+            let osc_options = &mut self.app_options.osc_options;
+            for (mode, label) in [
+                (CaptureMode::Off, "Off"),
+                (CaptureMode::Record, "Record"),
+                (CaptureMode::DryRun, "Dry run (this session)"),
+            ] {
+                response |= ui.selectable_value(&mut osc_options.capture_mode, mode, label);
+            }
+            if osc_options.capture_mode != CaptureMode::Off {
+                ui.separator();
+                response |= ui.selectable_value(&mut osc_options.capture_format, CaptureFormat::JsonLines, "JSON Lines");
+                response |= ui.selectable_value(&mut osc_options.capture_format, CaptureFormat::Raw, "Raw OSC");
+            }
+        });
         response
     }
 }
\ No newline at end of file
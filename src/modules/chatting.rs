@@ -11,6 +11,7 @@ pub struct ChatMessage {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ChatOptions {
     pub enabled: bool,
     pub chat_timeout: u32,
@@ -24,6 +25,7 @@ pub struct ChatOptions {
     pub display_time_seconds: f32,
     pub edit_messages: bool,
     pub live_editing: bool,
+    pub typing_indicator: bool,
     pub messages: VecDeque<ChatMessage>,
     pub last_send_ms: Option<u64>,
     pub queued_message: Option<String>,
@@ -44,6 +46,7 @@ impl Default for ChatOptions {
             display_time_seconds: 2.0,
             edit_messages: false,
             live_editing: false,
+            typing_indicator: true,
             messages: VecDeque::new(),
             last_send_ms: None,
             queued_message: None,
@@ -131,6 +134,7 @@ impl ChatOptions {
         if self.play_fx_sound {
             response |= ui.checkbox(&mut self.play_fx_resend, "Play FX when clicking resend");
         }
+        response |= ui.checkbox(&mut self.typing_indicator, "Show typing indicator in VRChat while writing a message");
         response |= ui.checkbox(&mut self.small_delay, "Small delay when sending a message");
         if self.small_delay {
             response |= ui.add(egui::Slider::new(&mut self.delay_seconds, 0.1..=2.0).text("seconds"));
@@ -4,6 +4,7 @@ use std::fs;
 use std::path::Path;
 use std::process::Command;
 use eframe::egui;
+use crate::stylize::{self, TextStyle};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ComponentStatsOptions {
@@ -15,17 +16,21 @@ pub struct ComponentStatsOptions {
     pub cpu_display_model: bool,
     pub cpu_custom_model: Option<String>,
     pub cpu_round_usage: bool,
-    pub cpu_stylized_uppercase: bool,
+    #[serde(default, alias = "cpu_stylized_uppercase", deserialize_with = "stylize::deserialize_style")]
+    pub cpu_style: TextStyle,
     pub gpu_display_model: bool,
     pub gpu_custom_model: Option<String>,
     pub gpu_round_usage: bool,
-    pub gpu_stylized_uppercase: bool,
+    #[serde(default, alias = "gpu_stylized_uppercase", deserialize_with = "stylize::deserialize_style")]
+    pub gpu_style: TextStyle,
     pub vram_round_usage: bool,
     pub vram_show_max: bool,
-    pub vram_stylized_uppercase: bool,
+    #[serde(default, alias = "vram_stylized_uppercase", deserialize_with = "stylize::deserialize_style")]
+    pub vram_style: TextStyle,
     pub ram_round_usage: bool,
     pub ram_show_max: bool,
-    pub ram_stylized_uppercase: bool,
+    #[serde(default, alias = "ram_stylized_uppercase", deserialize_with = "stylize::deserialize_style")]
+    pub ram_style: TextStyle,
 }
 
 impl Default for ComponentStatsOptions {
@@ -39,17 +44,17 @@ impl Default for ComponentStatsOptions {
             cpu_display_model: false,
             cpu_custom_model: None,
             cpu_round_usage: false,
-            cpu_stylized_uppercase: false,
+            cpu_style: TextStyle::None,
             gpu_display_model: false,
             gpu_custom_model: None,
             gpu_round_usage: false,
-            gpu_stylized_uppercase: false,
+            gpu_style: TextStyle::None,
             vram_round_usage: false,
             vram_show_max: false,
-            vram_stylized_uppercase: false,
+            vram_style: TextStyle::None,
             ram_round_usage: false,
             ram_show_max: false,
-            ram_stylized_uppercase: false,
+            ram_style: TextStyle::None,
         }
     }
 }
@@ -69,7 +74,10 @@ impl ComponentStatsOptions {
                 response |= ui.text_edit_singleline(self.cpu_custom_model.as_mut().unwrap_or(&mut String::new()));
             }
             response |= ui.checkbox(&mut self.cpu_round_usage, "Round CPU usage");
-            response |= ui.checkbox(&mut self.cpu_stylized_uppercase, "Stylized uppercase for CPU");
+            ui.horizontal(|ui| {
+                ui.label("CPU text style: ");
+                response |= stylize::style_combo(ui, "cpu_style", &mut self.cpu_style);
+            });
         }
         response |= ui.checkbox(&mut self.show_gpu, "Show GPU stats");
         if self.show_gpu {
@@ -79,19 +87,28 @@ impl ComponentStatsOptions {
                 response |= ui.text_edit_singleline(self.gpu_custom_model.as_mut().unwrap_or(&mut String::new()));
             }
             response |= ui.checkbox(&mut self.gpu_round_usage, "Round GPU usage");
-            response |= ui.checkbox(&mut self.gpu_stylized_uppercase, "Stylized uppercase for GPU");
+            ui.horizontal(|ui| {
+                ui.label("GPU text style: ");
+                response |= stylize::style_combo(ui, "gpu_style", &mut self.gpu_style);
+            });
         }
         response |= ui.checkbox(&mut self.show_vram, "Show VRAM stats");
         if self.show_vram {
             response |= ui.checkbox(&mut self.vram_round_usage, "Round VRAM usage");
             response |= ui.checkbox(&mut self.vram_show_max, "Show max VRAM");
-            response |= ui.checkbox(&mut self.vram_stylized_uppercase, "Stylized uppercase for VRAM");
+            ui.horizontal(|ui| {
+                ui.label("VRAM text style: ");
+                response |= stylize::style_combo(ui, "vram_style", &mut self.vram_style);
+            });
         }
         response |= ui.checkbox(&mut self.show_ram, "Show RAM stats");
         if self.show_ram {
             response |= ui.checkbox(&mut self.ram_round_usage, "Round RAM usage");
             response |= ui.checkbox(&mut self.ram_show_max, "Show max RAM");
-            response |= ui.checkbox(&mut self.ram_stylized_uppercase, "Stylized uppercase for RAM");
+            ui.horizontal(|ui| {
+                ui.label("RAM text style: ");
+                response |= stylize::style_combo(ui, "ram_style", &mut self.ram_style);
+            });
         }
         response
     }
@@ -284,11 +301,7 @@ impl ComponentStatsModule {
                 cpu_usage
             };
             let cpu_text = format!("{}: {}%", cpu_label, cpu_usage);
-            let cpu_text = if options.cpu_stylized_uppercase {
-                cpu_text.to_uppercase()
-            } else {
-                cpu_text
-            };
+            let cpu_text = stylize::stylize(&cpu_text, options.cpu_style);
             parts.push(cpu_text);
         }
         if options.show_gpu {
@@ -309,11 +322,7 @@ impl ComponentStatsModule {
                     gpu_usage
                 };
                 let gpu_text = format!("{}: {}%", gpu_label, gpu_usage);
-                let gpu_text = if options.gpu_stylized_uppercase {
-                    gpu_text.to_uppercase()
-                } else {
-                    gpu_text
-                };
+                let gpu_text = stylize::stylize(&gpu_text, options.gpu_style);
                 parts.push(gpu_text);
             } else {
                 eprintln!("GPU usage unavailable, skipping");
@@ -332,11 +341,7 @@ impl ComponentStatsModule {
                 if options.vram_show_max {
                     vram_text = format!("VRAM: {}/{}GB", vram_usage, total_gb.round());
                 }
-                let vram_text = if options.vram_stylized_uppercase {
-                    vram_text.to_uppercase()
-                } else {
-                    vram_text
-                };
+                let vram_text = stylize::stylize(&vram_text, options.vram_style);
                 parts.push(vram_text);
             } else {
                 eprintln!("VRAM usage unavailable, skipping");
@@ -355,11 +360,7 @@ impl ComponentStatsModule {
             if options.ram_show_max {
                 ram_text = format!("RAM: {}/{}GB", ram_usage, total_gb.round());
             }
-            let ram_text = if options.ram_stylized_uppercase {
-                ram_text.to_uppercase()
-            } else {
-                ram_text
-            };
+            let ram_text = stylize::stylize(&ram_text, options.ram_style);
             parts.push(ram_text);
         }
         self.cached_stats = parts.join("|");
@@ -0,0 +1,33 @@
+use serde::{Serialize, Deserialize};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum Integration {
+    Status,
+    Activity,
+    Components,
+    Time,
+    Network,
+    Media,
+}
+
+impl Integration {
+    pub const ALL: [Integration; 6] = [
+        Integration::Status,
+        Integration::Activity,
+        Integration::Components,
+        Integration::Time,
+        Integration::Network,
+        Integration::Media,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Integration::Status => "Personal Status",
+            Integration::Activity => "Window Activity",
+            Integration::Components => "Component Stats",
+            Integration::Time => "Current Time",
+            Integration::Network => "Network Stats",
+            Integration::Media => "MediaLink",
+        }
+    }
+}
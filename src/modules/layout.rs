@@ -0,0 +1,99 @@
+use serde::{Serialize, Deserialize};
+use eframe::egui;
+use crate::template;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LayoutTemplate {
+    pub name: String,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LayoutOptions {
+    pub enabled: bool,
+    pub active: usize,
+    pub templates: Vec<LayoutTemplate>,
+}
+
+impl Default for LayoutOptions {
+    fn default() -> Self {
+        LayoutOptions {
+            enabled: false,
+            active: 0,
+            templates: vec![
+                LayoutTemplate {
+                    name: "Default".to_string(),
+                    text: "{status}\n{activity}\n{components}\n{time}\n{network}\n{media}".to_string(),
+                },
+                LayoutTemplate {
+                    name: "Music".to_string(),
+                    text: "{status}\n{?media.artist:{media.artist} - }{media.title}{?media.paused: ⏸}\n{time.clock}".to_string(),
+                },
+            ],
+        }
+    }
+}
+
+impl LayoutOptions {
+    pub fn active_template(&self) -> Option<&LayoutTemplate> {
+        self.templates.get(self.active)
+    }
+
+    pub fn show_layout_options(&mut self, ui: &mut egui::Ui) -> egui::Response {
+        let mut response = ui.interact(
+            egui::Rect::EVERYTHING,
+            ui.id().with("layout_options"),
+            egui::Sense::hover(),
+        );
+        ui.label(egui::RichText::new("Replace the fixed integration order with a template").text_style(egui::TextStyle::Small));
+        ui.horizontal(|ui| {
+            ui.label("Template: ");
+            let selected = self.active_template().map(|t| t.name.clone()).unwrap_or_default();
+            let combo_response = egui::ComboBox::from_id_source("layout_template")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    for (index, template) in self.templates.iter().enumerate() {
+                        if ui.selectable_value(&mut self.active, index, &template.name).changed() {
+                            response.mark_changed();
+                        }
+                    }
+                });
+            response |= combo_response.response;
+            if ui.button("New").clicked() {
+                self.templates.push(LayoutTemplate {
+                    name: format!("Template {}", self.templates.len() + 1),
+                    text: "{status}\n{time}".to_string(),
+                });
+                self.active = self.templates.len() - 1;
+                response.mark_changed();
+            }
+            if self.templates.len() > 1 && ui.button("Delete").clicked() {
+                self.templates.remove(self.active);
+                self.active = self.active.min(self.templates.len() - 1);
+                response.mark_changed();
+            }
+        });
+        if let Some(template) = self.templates.get_mut(self.active) {
+            ui.horizontal(|ui| {
+                ui.label("Name: ");
+                response |= ui.text_edit_singleline(&mut template.name);
+            });
+            response |= ui.add(
+                egui::TextEdit::multiline(&mut template.text)
+                    .code_editor()
+                    .desired_rows(6)
+                    .desired_width(f32::INFINITY),
+            );
+            if let Err(e) = template::validate(&template.text) {
+                ui.colored_label(egui::Color32::RED, e);
+            }
+        }
+        ui.label(egui::RichText::new(
+            "Fields: status, activity, activity.app, activity.vr, components, cpu, gpu, memory, time, time.clock, \
+             time.hour, time.minute, network, media, media.artist, media.title, media.status, media.playing, media.paused. \
+             {?field:text} shows text when the field is set, {!field:text} when it isn't.",
+        ).text_style(egui::TextStyle::Small));
+        response
+    }
+}
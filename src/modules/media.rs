@@ -1,8 +1,34 @@
 use serde::{Deserialize, Serialize};
 use std::process::Command;
 use eframe::egui;
+use crate::compose;
+use crate::stylize::{self, TextStyle};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum SeekbarStyle {
+    // Names match the strings older configs stored
+    #[serde(rename = "Small numbers")]
+    SmallNumbers,
+    Bar,
+    Custom,
+    None,
+}
+
+impl SeekbarStyle {
+    pub const ALL: [SeekbarStyle; 4] = [SeekbarStyle::SmallNumbers, SeekbarStyle::Bar, SeekbarStyle::Custom, SeekbarStyle::None];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SeekbarStyle::SmallNumbers => "Small numbers",
+            SeekbarStyle::Bar => "Bar",
+            SeekbarStyle::Custom => "Custom",
+            SeekbarStyle::None => "None",
+        }
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct MediaLinkOptions {
     pub enabled: bool,
     pub use_music_note_prefix: bool,
@@ -11,7 +37,17 @@ pub struct MediaLinkOptions {
     pub auto_switch_session: bool,
     pub forget_session_seconds: u32,
     pub show_progress: bool,
-    pub seekbar_style: String,
+    pub seekbar_style: SeekbarStyle,
+    // Bar width in characters, knob included
+    pub seekbar_width: u32,
+    pub seekbar_fill: String,
+    pub seekbar_empty: String,
+    pub seekbar_knob: String,
+    // Scroll long tracks through a fixed-width window
+    pub marquee: bool,
+    pub marquee_width: u32,
+    pub marquee_step: u32,
+    pub style: TextStyle,
 }
 
 impl Default for MediaLinkOptions {
@@ -24,7 +60,15 @@ impl Default for MediaLinkOptions {
             auto_switch_session: true,
             forget_session_seconds: 30,
             show_progress: false,
-            seekbar_style: "Small numbers".to_string(),
+            seekbar_style: SeekbarStyle::SmallNumbers,
+            seekbar_width: 10,
+            seekbar_fill: "▓".to_string(),
+            seekbar_empty: "░".to_string(),
+            seekbar_knob: "".to_string(),
+            marquee: false,
+            marquee_width: 20,
+            marquee_step: 3,
+            style: TextStyle::None,
         }
     }
 }
@@ -47,25 +91,108 @@ impl MediaLinkOptions {
             response |= ui.add(egui::DragValue::new(&mut self.forget_session_seconds).speed(1.0));
             ui.label("seconds");
         });
+        response |= ui.checkbox(&mut self.marquee, "Scroll long track names");
+        if self.marquee {
+            ui.horizontal(|ui| {
+                ui.label("Width");
+                response |= ui.add(egui::DragValue::new(&mut self.marquee_width).range(5..=compose::MAX_LINE_WIDTH as u32));
+                ui.label("Step");
+                response |= ui.add(egui::DragValue::new(&mut self.marquee_step).range(1..=20));
+            });
+        }
+        ui.horizontal(|ui| {
+            ui.label("Text style: ");
+            response |= stylize::style_combo(ui, "medialink_style", &mut self.style);
+        });
         ui.label("Media progress bar");
         ui.label(egui::RichText::new("Customize how your seek bar looks").text_style(egui::TextStyle::Small));
         ui.label("Seekbar style");
-        let combo_response = egui::ComboBox::from_label("")
-            .selected_text(&self.seekbar_style)
+        let combo_response = egui::ComboBox::from_id_source("seekbar_style")
+            .selected_text(self.seekbar_style.name())
             .show_ui(ui, |ui| {
-                let seekbar_styles = ["Small numbers", "Custom", "None"];
-                let mut combo_response = ui.selectable_value(&mut self.seekbar_style, seekbar_styles[0].to_string(), seekbar_styles[0]);
-                for style in seekbar_styles.iter().skip(1) {
-                    combo_response |= ui.selectable_value(&mut self.seekbar_style, style.to_string(), *style);
+                for style in SeekbarStyle::ALL {
+                    if ui.selectable_value(&mut self.seekbar_style, style, style.name()).changed() {
+                        response.mark_changed();
+                    }
                 }
-                combo_response
             });
         response |= combo_response.response;
+        if matches!(self.seekbar_style, SeekbarStyle::Bar | SeekbarStyle::Custom) {
+            ui.horizontal(|ui| {
+                ui.label("Width");
+                response |= ui.add(egui::DragValue::new(&mut self.seekbar_width).range(2..=compose::MAX_LINE_WIDTH as u32));
+            });
+        }
+        if self.seekbar_style == SeekbarStyle::Custom {
+            ui.horizontal(|ui| {
+                ui.label("Fill");
+                response |= ui.add(egui::TextEdit::singleline(&mut self.seekbar_fill).desired_width(30.0));
+                ui.label("Empty");
+                response |= ui.add(egui::TextEdit::singleline(&mut self.seekbar_empty).desired_width(30.0));
+                ui.label("Knob");
+                response |= ui.add(egui::TextEdit::singleline(&mut self.seekbar_knob).desired_width(30.0));
+            });
+        }
+        if let Some(sample) = render_seekbar(self, 83.0, 225.0) {
+            ui.label(format!("Example: {}", sample));
+        }
         response |= ui.checkbox(&mut self.show_progress, "Show media progress");
         response
     }
 }
 
+// "m:ss", or "h:mm:ss" from an hour up
+pub fn format_time(seconds: f32) -> String {
+    let total = if seconds.is_finite() { seconds.max(0.0) as u64 } else { 0 };
+    let (hours, minutes, seconds) = (total / 3600, total / 60 % 60, total % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+// width characters with the knob where position falls in duration; without a knob the
+// fill alone shows progress. Unknown or zero durations show an empty bar.
+pub fn render_bar(position: f32, duration: f32, width: usize, fill: &str, empty: &str, knob: &str) -> String {
+    let ratio = if duration > 0.0 && position.is_finite() {
+        (position / duration).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let width = width.max(1);
+    if knob.is_empty() {
+        let filled = (ratio * width as f32).round() as usize;
+        return format!("{}{}", fill.repeat(filled), empty.repeat(width - filled));
+    }
+    let before = (ratio * (width - 1) as f32).round() as usize;
+    format!("{}{}{}", fill.repeat(before), knob, empty.repeat(width - 1 - before))
+}
+
+// "1:23 / 3:45" in superscript digits
+pub fn render_small_numbers(position: f32, duration: f32) -> String {
+    let position = if position.is_finite() { position.clamp(0.0, duration.max(0.0)) } else { 0.0 };
+    let text = format!("{} / {}", format_time(position), format_time(duration));
+    stylize::stylize(&text, TextStyle::Superscript)
+}
+
+pub fn render_seekbar(options: &MediaLinkOptions, position: f32, duration: f32) -> Option<String> {
+    let width = options.seekbar_width as usize;
+    match options.seekbar_style {
+        SeekbarStyle::SmallNumbers => Some(render_small_numbers(position, duration)),
+        SeekbarStyle::Bar => Some(render_bar(position, duration, width, "━", "─", "●")),
+        SeekbarStyle::Custom => Some(render_bar(
+            position,
+            duration,
+            width,
+            &options.seekbar_fill,
+            &options.seekbar_empty,
+            &options.seekbar_knob,
+        )),
+        SeekbarStyle::None => None,
+    }
+}
+
 pub struct MediaLinkModule;
 
 impl MediaLinkModule {
@@ -73,35 +200,72 @@ impl MediaLinkModule {
         Self
     }
 
-    pub fn get_formatted_track(&self, options: &MediaLinkOptions) -> Option<String> {
-        let status = Command::new("playerctl")
-            .arg("status")
-            .output()
-            .ok()
-            .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())?;
+    // marquee is only given on send ticks, so the track scrolls once per update
+    pub fn get_formatted_track(&self, options: &MediaLinkOptions, marquee: Option<&mut compose::Marquee>) -> Option<String> {
+        self.format_track(self.get_track_info(), options, marquee)
+    }
+
+    // Formats what get_track_info returned, so a caller that also wants the raw fields only
+    // runs playerctl once
+    pub fn format_track(
+        &self,
+        info: Option<(String, String, String)>,
+        options: &MediaLinkOptions,
+        marquee: Option<&mut compose::Marquee>,
+    ) -> Option<String> {
+        let (status, artist, title) = info?;
         if status == "Paused" {
-            return Some(if options.show_pause_emoji {
-                "⏸".to_string()
-            } else {
-                "Paused".to_string()
-            });
+            let paused = if options.show_pause_emoji { "⏸" } else { "Paused" };
+            return Some(self.with_seekbar(paused.to_string(), options));
         }
-        let output = Command::new("playerctl")
-            .arg("metadata")
-            .arg("--format")
-            .arg("{{artist}} - {{title}}")
-            .output()
-            .ok()?;
-        let track = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        let track = [artist.trim(), title.trim()]
+            .into_iter()
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join(" - ");
         if track.is_empty() {
             return None;
         }
+        // Styled before scrolling, since a style can change how long the track is
+        let mut track = stylize::stylize(&track, options.style);
+        if let Some(marquee) = marquee.filter(|_| options.marquee) {
+            track = marquee.advance(&track, options.marquee_width as usize, options.marquee_step as usize);
+        }
         let prefix = if options.use_music_note_prefix {
             "🎵 "
         } else {
             "Listening to: "
         };
-        Some(format!("{}{}", prefix, track))
+        let formatted = format!("{}{}", stylize::stylize(prefix, options.style), track);
+        Some(self.with_seekbar(formatted, options))
+    }
+
+    // Adds the seekbar on a line of its own when progress is shown
+    fn with_seekbar(&self, mut text: String, options: &MediaLinkOptions) -> String {
+        if options.show_progress {
+            let seekbar = self
+                .get_duration()
+                .and_then(|duration| render_seekbar(options, self.get_position().unwrap_or(0.0), duration));
+            if let Some(seekbar) = seekbar {
+                text.push('\n');
+                text.push_str(&seekbar);
+            }
+        }
+        text
+    }
+
+    // (status, artist, title) of the current player, or None when nothing is playing
+    pub fn get_track_info(&self) -> Option<(String, String, String)> {
+        let output = Command::new("playerctl")
+            .arg("metadata")
+            .arg("--format")
+            .arg("{{status}}\t{{artist}}\t{{title}}")
+            .output()
+            .ok()?;
+        let info = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        let mut fields = info.splitn(3, '\t').map(|s| s.trim().to_string());
+        let status = fields.next().filter(|s| !s.is_empty())?;
+        Some((status, fields.next().unwrap_or_default(), fields.next().unwrap_or_default()))
     }
 
     pub fn is_playing(&self) -> bool {
@@ -193,4 +357,110 @@ impl MediaLinkModule {
         let dur_str = binding.trim();
         dur_str.parse::<f32>().ok().map(|d| d / 1_000_000.0)
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_times() {
+        assert_eq!(format_time(0.0), "0:00");
+        assert_eq!(format_time(83.9), "1:23");
+        assert_eq!(format_time(3600.0), "1:00:00");
+        assert_eq!(format_time(3725.0), "1:02:05");
+        assert_eq!(format_time(-5.0), "0:00");
+        assert_eq!(format_time(f32::NAN), "0:00");
+    }
+
+    #[test]
+    fn places_the_knob_at_the_ends() {
+        assert_eq!(render_bar(0.0, 200.0, 5, "━", "─", "●"), "●────");
+        assert_eq!(render_bar(200.0, 200.0, 5, "━", "─", "●"), "━━━━●");
+        assert_eq!(render_bar(100.0, 200.0, 5, "━", "─", "●"), "━━●──");
+    }
+
+    #[test]
+    fn clamps_positions_outside_the_track() {
+        assert_eq!(render_bar(250.0, 200.0, 5, "━", "─", "●"), "━━━━●");
+        assert_eq!(render_bar(-10.0, 200.0, 5, "━", "─", "●"), "●────");
+        assert_eq!(render_bar(f32::NAN, 200.0, 5, "━", "─", "●"), "●────");
+    }
+
+    #[test]
+    fn shows_an_empty_bar_without_a_duration() {
+        assert_eq!(render_bar(30.0, 0.0, 5, "━", "─", "●"), "●────");
+        assert_eq!(render_bar(30.0, 0.0, 4, "▓", "░", ""), "░░░░");
+        assert_eq!(render_bar(30.0, -1.0, 4, "▓", "░", ""), "░░░░");
+    }
+
+    #[test]
+    fn renders_the_narrowest_bars() {
+        assert_eq!(render_bar(0.0, 10.0, 1, "━", "─", "●"), "●");
+        assert_eq!(render_bar(10.0, 10.0, 1, "━", "─", "●"), "●");
+        assert_eq!(render_bar(0.0, 10.0, 1, "▓", "░", ""), "░");
+        assert_eq!(render_bar(10.0, 10.0, 1, "▓", "░", ""), "▓");
+        assert_eq!(render_bar(0.0, 10.0, 2, "━", "─", "●"), "●─");
+        assert_eq!(render_bar(10.0, 10.0, 2, "━", "─", "●"), "━●");
+        assert_eq!(render_bar(10.0, 10.0, 2, "▓", "░", ""), "▓▓");
+        // A width of 0 is treated as 1
+        assert_eq!(render_bar(0.0, 10.0, 0, "━", "─", "●"), "●");
+    }
+
+    #[test]
+    fn fills_without_a_knob() {
+        assert_eq!(render_bar(0.0, 100.0, 4, "▓", "░", ""), "░░░░");
+        assert_eq!(render_bar(50.0, 100.0, 4, "▓", "░", ""), "▓▓░░");
+        assert_eq!(render_bar(100.0, 100.0, 4, "▓", "░", ""), "▓▓▓▓");
+    }
+
+    #[test]
+    fn repeats_multi_character_pieces_per_cell() {
+        assert_eq!(render_bar(50.0, 100.0, 4, "=>", "..", ""), "=>=>....");
+        assert_eq!(render_bar(50.0, 100.0, 5, "==", "  ", "<>"), "====<>    ");
+    }
+
+    #[test]
+    fn renders_small_numbers() {
+        assert_eq!(render_small_numbers(83.0, 225.0), "¹:²³ / ³:⁴⁵");
+        assert_eq!(render_small_numbers(300.0, 225.0), "³:⁴⁵ / ³:⁴⁵");
+        assert_eq!(render_small_numbers(-3.0, 225.0), "⁰:⁰⁰ / ³:⁴⁵");
+        assert_eq!(render_small_numbers(12.0, 0.0), "⁰:⁰⁰ / ⁰:⁰⁰");
+    }
+
+    #[test]
+    fn picks_the_configured_seekbar() {
+        let mut options = MediaLinkOptions {
+            seekbar_width: 4,
+            ..MediaLinkOptions::default()
+        };
+        options.seekbar_style = SeekbarStyle::None;
+        assert_eq!(render_seekbar(&options, 50.0, 100.0), None);
+        options.seekbar_style = SeekbarStyle::Bar;
+        assert_eq!(render_seekbar(&options, 50.0, 100.0).as_deref(), Some("━━●─"));
+        options.seekbar_style = SeekbarStyle::Custom;
+        assert_eq!(render_seekbar(&options, 50.0, 100.0).as_deref(), Some("▓▓░░"));
+    }
+
+    fn info(status: &str, artist: &str, title: &str) -> Option<(String, String, String)> {
+        Some((status.to_string(), artist.to_string(), title.to_string()))
+    }
+
+    #[test]
+    fn formats_track_info() {
+        let media = MediaLinkModule::new();
+        let options = MediaLinkOptions::default();
+        assert_eq!(media.format_track(info("Playing", "Artist", "Song"), &options, None).as_deref(), Some("Listening to: Artist - Song"));
+        assert_eq!(media.format_track(info("Playing", "", "Song"), &options, None).as_deref(), Some("Listening to: Song"));
+        assert_eq!(media.format_track(info("Playing", "Artist", " "), &options, None).as_deref(), Some("Listening to: Artist"));
+        assert_eq!(media.format_track(info("Playing", "", ""), &options, None), None);
+        assert_eq!(media.format_track(info("Playing", " ", ""), &options, None), None);
+        assert_eq!(media.format_track(None, &options, None), None);
+        let options = MediaLinkOptions {
+            use_music_note_prefix: true,
+            show_pause_emoji: true,
+            ..MediaLinkOptions::default()
+        };
+        assert_eq!(media.format_track(info("Stopped", "Artist", "Song"), &options, None).as_deref(), Some("🎵 Artist - Song"));
+        assert_eq!(media.format_track(info("Paused", "Artist", "Song"), &options, None).as_deref(), Some("⏸"));
+    }
+}
@@ -1,5 +1,6 @@
 use network_interface::{NetworkInterface, NetworkInterfaceConfig};
 use serde::{Deserialize, Serialize};
+use crate::stylize::{self, TextStyle};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NetworkOptions {
@@ -11,7 +12,8 @@ pub struct NetworkOptions {
     pub show_total_download: bool,
     pub show_total_upload: bool,
     pub show_utilization: bool,
-    pub stylized_chars: bool,
+    #[serde(default, alias = "stylized_chars", deserialize_with = "stylize::deserialize_style")]
+    pub style: TextStyle,
 }
 
 impl Default for NetworkOptions {
@@ -25,7 +27,7 @@ impl Default for NetworkOptions {
             show_total_download: false,
             show_total_upload: false,
             show_utilization: false,
-            stylized_chars: false,
+            style: TextStyle::None,
         }
     }
 }
@@ -67,7 +69,10 @@ impl NetworkStatsOptions {
         response |= ui.checkbox(&mut self.config.show_total_download, "Show total download");
         response |= ui.checkbox(&mut self.config.show_total_upload, "Show total upload");
         response |= ui.checkbox(&mut self.config.show_utilization, "Show network utilization");
-        response |= ui.checkbox(&mut self.config.stylized_chars, "Stylized characters");
+        ui.horizontal(|ui| {
+            ui.label("Text style: ");
+            response |= stylize::style_combo(ui, "network_style", &mut self.config.style);
+        });
         response
     }
 }
@@ -115,43 +120,43 @@ impl NetworkStats {
         if options.show_download_speed {
             if let Some(speed) = Self::get_download_speed(interface_name) {
                 let text = format!("Download: {:.2} MB/s", speed);
-                parts.push(if options.stylized_chars { text.to_uppercase() } else { text });
+                parts.push(stylize::stylize(&text, options.style));
             }
         }
         if options.show_upload_speed {
             if let Some(speed) = Self::get_upload_speed(interface_name) {
                 let text = format!("Upload: {:.2} MB/s", speed);
-                parts.push(if options.stylized_chars { text.to_uppercase() } else { text });
+                parts.push(stylize::stylize(&text, options.style));
             }
         }
         if options.show_max_download {
             if let Some(speed) = Self::get_max_download_speed(interface_name) {
                 let text = format!("Max Download: {:.2} MB/s", speed);
-                parts.push(if options.stylized_chars { text.to_uppercase() } else { text });
+                parts.push(stylize::stylize(&text, options.style));
             }
         }
         if options.show_max_upload {
             if let Some(speed) = Self::get_max_upload_speed(interface_name) {
                 let text = format!("Max Upload: {:.2} MB/s", speed);
-                parts.push(if options.stylized_chars { text.to_uppercase() } else { text });
+                parts.push(stylize::stylize(&text, options.style));
             }
         }
         if options.show_total_download {
             if let Some(total) = Self::get_total_download(interface_name) {
                 let text = format!("Total Download: {} MB", total / 1_048_576);
-                parts.push(if options.stylized_chars { text.to_uppercase() } else { text });
+                parts.push(stylize::stylize(&text, options.style));
             }
         }
         if options.show_total_upload {
             if let Some(total) = Self::get_total_upload(interface_name) {
                 let text = format!("Total Upload: {} MB", total / 1_048_576);
-                parts.push(if options.stylized_chars { text.to_uppercase() } else { text });
+                parts.push(stylize::stylize(&text, options.style));
             }
         }
         if options.show_utilization {
             if let Some(util) = Self::get_utilization(interface_name) {
                 let text = format!("Utilization: {}%", util);
-                parts.push(if options.stylized_chars { text.to_uppercase() } else { text });
+                parts.push(stylize::stylize(&text, options.style));
             }
         }
         if parts.is_empty() {
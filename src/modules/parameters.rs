@@ -0,0 +1,339 @@
+use std::collections::HashMap;
+use std::time::Instant;
+use serde::{Serialize, Deserialize};
+use eframe::egui;
+use rosc::OscType;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum ParameterSource {
+    TimeHour,
+    TimeMinute,
+    CpuUsage,
+    GpuUsage,
+    MemoryUsage,
+    MediaPlaying,
+}
+
+impl ParameterSource {
+    pub const ALL: [ParameterSource; 6] = [
+        ParameterSource::TimeHour,
+        ParameterSource::TimeMinute,
+        ParameterSource::CpuUsage,
+        ParameterSource::GpuUsage,
+        ParameterSource::MemoryUsage,
+        ParameterSource::MediaPlaying,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ParameterSource::TimeHour => "Hour (0-23)",
+            ParameterSource::TimeMinute => "Minute (0-59)",
+            ParameterSource::CpuUsage => "CPU usage (%)",
+            ParameterSource::GpuUsage => "GPU usage (%)",
+            ParameterSource::MemoryUsage => "Memory usage (%)",
+            ParameterSource::MediaPlaying => "Media playing (0/1)",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum ParameterKind {
+    Float,
+    Int,
+    Bool,
+}
+
+impl ParameterKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ParameterKind::Float => "Float",
+            ParameterKind::Int => "Int",
+            ParameterKind::Bool => "Bool",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ParameterBinding {
+    pub enabled: bool,
+    // Name under /avatar/parameters/
+    pub parameter: String,
+    pub source: ParameterSource,
+    pub kind: ParameterKind,
+    // The source value is multiplied by this, e.g. 0.01 maps a percentage onto a 0-1 float
+    pub scale: f32,
+    pub update_rate: f32,
+}
+
+impl Default for ParameterBinding {
+    fn default() -> Self {
+        ParameterBinding {
+            enabled: true,
+            parameter: String::new(),
+            source: ParameterSource::CpuUsage,
+            kind: ParameterKind::Float,
+            scale: 0.01,
+            update_rate: 2.0,
+        }
+    }
+}
+
+impl ParameterBinding {
+    pub fn to_osc(&self, value: f32) -> OscType {
+        let value = value * self.scale;
+        match self.kind {
+            ParameterKind::Float => OscType::Float(value.clamp(-1.0, 1.0)),
+            // VRChat ints are 0-255
+            ParameterKind::Int => OscType::Int(value.round().clamp(0.0, 255.0) as i32),
+            ParameterKind::Bool => OscType::Bool(value >= 0.5),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ParameterOptions {
+    pub enabled: bool,
+    pub bindings: Vec<ParameterBinding>,
+}
+
+impl ParameterOptions {
+    pub fn show_parameter_options(&mut self, ui: &mut egui::Ui) -> egui::Response {
+        let mut response = ui.interact(
+            egui::Rect::EVERYTHING,
+            ui.id().with("parameter_options"),
+            egui::Sense::hover(),
+        );
+        ui.label("Send integration values to /avatar/parameters/<name>.");
+        let mut remove_index = None;
+        for (index, binding) in self.bindings.iter_mut().enumerate() {
+            ui.push_id(index, |ui| {
+                ui.horizontal(|ui| {
+                    response |= ui.checkbox(&mut binding.enabled, "");
+                    response |= ui.add(egui::TextEdit::singleline(&mut binding.parameter).desired_width(120.0).hint_text("Parameter"));
+                    let source_response = egui::ComboBox::from_id_source("source")
+                        .selected_text(binding.source.label())
+                        .show_ui(ui, |ui| {
+                            for source in ParameterSource::ALL {
+                                if ui.selectable_value(&mut binding.source, source, source.label()).changed() {
+                                    response.mark_changed();
+                                }
+                            }
+                        });
+                    response |= source_response.response;
+                    let kind_response = egui::ComboBox::from_id_source("kind")
+                        .selected_text(binding.kind.label())
+                        .show_ui(ui, |ui| {
+                            for kind in [ParameterKind::Float, ParameterKind::Int, ParameterKind::Bool] {
+                                if ui.selectable_value(&mut binding.kind, kind, kind.label()).changed() {
+                                    response.mark_changed();
+                                }
+                            }
+                        });
+                    response |= kind_response.response;
+                    if ui.button("X").clicked() {
+                        remove_index = Some(index);
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Scale: ");
+                    response |= ui.add(egui::DragValue::new(&mut binding.scale).speed(0.01));
+                    ui.label("Every: ");
+                    response |= ui.add(
+                        egui::Slider::new(&mut binding.update_rate, 0.1..=60.0)
+                            .step_by(0.1)
+                            .text("seconds"),
+                    );
+                });
+            });
+        }
+        if let Some(index) = remove_index {
+            self.bindings.remove(index);
+            response.mark_changed();
+        }
+        if ui.button("Add Parameter").clicked() {
+            self.bindings.push(ParameterBinding::default());
+            response.mark_changed();
+        }
+        response
+    }
+}
+
+pub struct ParameterModule {
+    last_sent: HashMap<String, (Instant, OscType)>,
+}
+
+impl ParameterModule {
+    pub fn new() -> Self {
+        Self {
+            last_sent: HashMap::new(),
+        }
+    }
+
+    // Returns the parameters that are due, reading each source at most once per call
+    pub fn due_updates(
+        &mut self,
+        options: &ParameterOptions,
+        mut read_source: impl FnMut(ParameterSource) -> Option<f32>,
+    ) -> Vec<(String, OscType)> {
+        let mut values = HashMap::new();
+        let mut updates = Vec::new();
+        for binding in options.bindings.iter().filter(|b| b.enabled && !b.parameter.trim().is_empty()) {
+            let name = binding.parameter.trim();
+            if self
+                .last_sent
+                .get(name)
+                .is_some_and(|(at, _)| at.elapsed().as_secs_f32() < binding.update_rate)
+            {
+                continue;
+            }
+            let Some(value) = *values.entry(binding.source).or_insert_with(|| read_source(binding.source)) else {
+                continue;
+            };
+            let value = binding.to_osc(value);
+            let unchanged = self.last_sent.get(name).is_some_and(|(_, last)| *last == value);
+            self.last_sent.insert(name.to_string(), (Instant::now(), value.clone()));
+            // Re-sending an unchanged value only costs bandwidth
+            if !unchanged {
+                updates.push((name.to_string(), value));
+            }
+        }
+        updates
+    }
+
+    // Forget what was sent, e.g. after an avatar change resets the parameters
+    pub fn reset(&mut self) {
+        self.last_sent.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn binding(parameter: &str, source: ParameterSource, kind: ParameterKind, scale: f32) -> ParameterBinding {
+        ParameterBinding {
+            parameter: parameter.to_string(),
+            source,
+            kind,
+            scale,
+            ..ParameterBinding::default()
+        }
+    }
+
+    fn options(bindings: Vec<ParameterBinding>) -> ParameterOptions {
+        ParameterOptions {
+            enabled: true,
+            bindings,
+        }
+    }
+
+    // Backdates every send so each binding is due again
+    fn wait_out(module: &mut ParameterModule, duration: Duration) {
+        for (at, _) in module.last_sent.values_mut() {
+            *at = at.checked_sub(duration).unwrap();
+        }
+    }
+
+    #[test]
+    fn clamps_floats_to_plus_minus_one() {
+        let float = binding("Cpu", ParameterSource::CpuUsage, ParameterKind::Float, 0.01);
+        assert_eq!(float.to_osc(42.0), OscType::Float(0.42));
+        assert_eq!(float.to_osc(250.0), OscType::Float(1.0));
+        assert_eq!(float.to_osc(-250.0), OscType::Float(-1.0));
+    }
+
+    #[test]
+    fn rounds_and_clamps_ints_to_a_byte() {
+        let int = binding("Hour", ParameterSource::TimeHour, ParameterKind::Int, 1.0);
+        assert_eq!(int.to_osc(13.4), OscType::Int(13));
+        assert_eq!(int.to_osc(13.5), OscType::Int(14));
+        assert_eq!(int.to_osc(-3.0), OscType::Int(0));
+        assert_eq!(int.to_osc(300.0), OscType::Int(255));
+    }
+
+    #[test]
+    fn turns_bools_on_from_one_half() {
+        let bool = binding("Playing", ParameterSource::MediaPlaying, ParameterKind::Bool, 1.0);
+        assert_eq!(bool.to_osc(0.0), OscType::Bool(false));
+        assert_eq!(bool.to_osc(0.49), OscType::Bool(false));
+        assert_eq!(bool.to_osc(0.5), OscType::Bool(true));
+        assert_eq!(bool.to_osc(1.0), OscType::Bool(true));
+    }
+
+    #[test]
+    fn sends_each_binding_at_its_own_rate() {
+        let mut module = ParameterModule::new();
+        let options = options(vec![
+            ParameterBinding {
+                update_rate: 1.0,
+                ..binding("Fast", ParameterSource::TimeMinute, ParameterKind::Int, 1.0)
+            },
+            ParameterBinding {
+                update_rate: 10.0,
+                ..binding("Slow", ParameterSource::TimeHour, ParameterKind::Int, 1.0)
+            },
+        ]);
+        let mut minute = 0.0;
+        let mut read = |source| Some(if source == ParameterSource::TimeMinute { minute } else { 12.0 });
+        let names = |updates: Vec<(String, OscType)>| updates.into_iter().map(|(name, _)| name).collect::<Vec<_>>();
+        assert_eq!(names(module.due_updates(&options, &mut read)), ["Fast", "Slow"]);
+        assert_eq!(module.due_updates(&options, &mut read), []);
+
+        wait_out(&mut module, Duration::from_secs(2));
+        minute = 1.0;
+        let mut read = |source| Some(if source == ParameterSource::TimeMinute { minute } else { 13.0 });
+        assert_eq!(module.due_updates(&options, &mut read), [("Fast".to_string(), OscType::Int(1))]);
+    }
+
+    #[test]
+    fn does_not_resend_an_unchanged_value() {
+        let mut module = ParameterModule::new();
+        let options = options(vec![binding("Cpu", ParameterSource::CpuUsage, ParameterKind::Float, 0.01)]);
+        assert_eq!(module.due_updates(&options, |_| Some(50.0)), [("Cpu".to_string(), OscType::Float(0.5))]);
+        wait_out(&mut module, Duration::from_secs(5));
+        assert_eq!(module.due_updates(&options, |_| Some(50.0)), []);
+        // Checking still restarts the wait
+        assert_eq!(module.due_updates(&options, |_| Some(25.0)), []);
+        wait_out(&mut module, Duration::from_secs(5));
+        assert_eq!(module.due_updates(&options, |_| Some(25.0)), [("Cpu".to_string(), OscType::Float(0.25))]);
+    }
+
+    #[test]
+    fn reads_each_source_once_per_call() {
+        let mut module = ParameterModule::new();
+        let options = options(vec![
+            binding("CpuFloat", ParameterSource::CpuUsage, ParameterKind::Float, 0.01),
+            binding("CpuInt", ParameterSource::CpuUsage, ParameterKind::Int, 1.0),
+            binding("Memory", ParameterSource::MemoryUsage, ParameterKind::Float, 0.01),
+            binding("", ParameterSource::GpuUsage, ParameterKind::Float, 0.01),
+            ParameterBinding {
+                enabled: false,
+                ..binding("Off", ParameterSource::TimeHour, ParameterKind::Int, 1.0)
+            },
+        ]);
+        let mut reads = Vec::new();
+        let updates = module.due_updates(&options, |source| {
+            reads.push(source);
+            (source != ParameterSource::MemoryUsage).then_some(75.0)
+        });
+        assert_eq!(reads, [ParameterSource::CpuUsage, ParameterSource::MemoryUsage]);
+        // A source with nothing to report is skipped
+        assert_eq!(
+            updates,
+            [("CpuFloat".to_string(), OscType::Float(0.75)), ("CpuInt".to_string(), OscType::Int(75))]
+        );
+    }
+
+    #[test]
+    fn sends_everything_again_after_a_reset() {
+        let mut module = ParameterModule::new();
+        let options = options(vec![binding(" Cpu ", ParameterSource::CpuUsage, ParameterKind::Float, 0.01)]);
+        assert_eq!(module.due_updates(&options, |_| Some(50.0)).len(), 1);
+        assert_eq!(module.due_updates(&options, |_| Some(50.0)), []);
+        module.reset();
+        assert_eq!(module.due_updates(&options, |_| Some(50.0)), [("Cpu".to_string(), OscType::Float(0.5))]);
+    }
+}
@@ -0,0 +1,99 @@
+use serde::{Serialize, Deserialize};
+use eframe::egui;
+use crate::modules::app::OscTarget;
+
+#[derive(Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct OscRoute {
+    pub name: String,
+    pub enabled: bool,
+    pub ip: String,
+    pub port: u16,
+    // Comma separated address prefixes to forward; empty forwards everything
+    pub prefixes: String,
+}
+
+impl Default for OscRoute {
+    fn default() -> Self {
+        OscRoute {
+            name: "Route".to_string(),
+            enabled: true,
+            ip: "127.0.0.1".to_string(),
+            port: 9002,
+            prefixes: String::new(),
+        }
+    }
+}
+
+impl OscRoute {
+    pub fn target(&self) -> OscTarget {
+        OscTarget {
+            name: self.name.clone(),
+            enabled: self.enabled,
+            ip: self.ip.clone(),
+            port: self.port,
+            follow_oscquery: false,
+        }
+    }
+
+    pub fn prefix_list(&self) -> Vec<String> {
+        self.prefixes
+            .split(',')
+            .map(|prefix| prefix.trim().to_string())
+            .filter(|prefix| !prefix.is_empty())
+            .collect()
+    }
+}
+
+#[derive(Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct RouterOptions {
+    pub enabled: bool,
+    pub routes: Vec<OscRoute>,
+}
+
+impl RouterOptions {
+    pub fn show_router_options(&mut self, ui: &mut egui::Ui) -> egui::Response {
+        let mut response = ui.interact(
+            egui::Rect::EVERYTHING,
+            ui.id().with("router_options"),
+            egui::Sense::hover(),
+        );
+        response |= ui.checkbox(&mut self.enabled, "Forward received OSC to other apps");
+        let mut remove_index = None;
+        for (index, route) in self.routes.iter_mut().enumerate() {
+            ui.push_id(index, |ui| {
+                ui.group(|ui| {
+                    ui.horizontal(|ui| {
+                        response |= ui.checkbox(&mut route.enabled, "");
+                        response |= ui.add(egui::TextEdit::singleline(&mut route.name).desired_width(100.0).hint_text("Name"));
+                        response |= ui.add(egui::TextEdit::singleline(&mut route.ip).desired_width(110.0).hint_text("IP"));
+                        response |= ui.add(egui::DragValue::new(&mut route.port).speed(1.0));
+                        if ui.button("X").clicked() {
+                            remove_index = Some(index);
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Prefixes: ");
+                        response |= ui.add(
+                            egui::TextEdit::singleline(&mut route.prefixes)
+                                .hint_text("All messages, or e.g. /avatar/parameters/, /tracking/"),
+                        );
+                    });
+                });
+            });
+        }
+        if let Some(index) = remove_index {
+            self.routes.remove(index);
+            response.mark_changed();
+        }
+        if ui.button("Add Route").clicked() {
+            self.routes.push(OscRoute {
+                name: format!("Route {}", self.routes.len() + 1),
+                ..OscRoute::default()
+            });
+            response.mark_changed();
+        }
+        response
+    }
+}
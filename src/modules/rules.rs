@@ -0,0 +1,667 @@
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use chrono::NaiveTime;
+use serde::{Serialize, Deserialize};
+use eframe::egui;
+use crate::modules::media::MediaLinkModule;
+use crate::modules::integration::Integration;
+
+// How often rules are checked, and how often the sampler runs playerctl and friends
+const EVALUATE_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum MediaState {
+    Playing,
+    Paused,
+    Stopped,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum Condition {
+    VrActive(bool),
+    // Case insensitive match anywhere in the focused window title
+    AppFocused(String),
+    MediaState(MediaState),
+    MediaPausedFor { minutes: u32 },
+    // "HH:MM" in local time; an end before the start wraps past midnight
+    TimeBetween { start: String, end: String },
+    IdleFor { minutes: u32 },
+}
+
+impl Condition {
+    pub const KINDS: [&'static str; 6] = ["VR active", "App focused", "Media state", "Media paused for", "Time between", "Idle for"];
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Condition::VrActive(_) => Self::KINDS[0],
+            Condition::AppFocused(_) => Self::KINDS[1],
+            Condition::MediaState(_) => Self::KINDS[2],
+            Condition::MediaPausedFor { .. } => Self::KINDS[3],
+            Condition::TimeBetween { .. } => Self::KINDS[4],
+            Condition::IdleFor { .. } => Self::KINDS[5],
+        }
+    }
+
+    fn from_kind(kind: &str) -> Self {
+        match kind {
+            "App focused" => Condition::AppFocused(String::new()),
+            "Media state" => Condition::MediaState(MediaState::Playing),
+            "Media paused for" => Condition::MediaPausedFor { minutes: 5 },
+            "Time between" => Condition::TimeBetween {
+                start: "22:00".to_string(),
+                end: "06:00".to_string(),
+            },
+            "Idle for" => Condition::IdleFor { minutes: 10 },
+            _ => Condition::VrActive(true),
+        }
+    }
+
+    fn matches(&self, context: &RuleContext, paused_for: Option<Duration>) -> bool {
+        match self {
+            Condition::VrActive(active) => context.vr_active == *active,
+            Condition::AppFocused(app) => {
+                !app.trim().is_empty() && context.focused_app.to_lowercase().contains(&app.trim().to_lowercase())
+            }
+            Condition::MediaState(state) => {
+                let current = match context.media_status.as_deref() {
+                    Some("Playing") => MediaState::Playing,
+                    Some("Paused") => MediaState::Paused,
+                    _ => MediaState::Stopped,
+                };
+                current == *state
+            }
+            Condition::MediaPausedFor { minutes } => {
+                paused_for.is_some_and(|paused| paused >= Duration::from_secs(*minutes as u64 * 60))
+            }
+            Condition::TimeBetween { start, end } => match (parse_time(start), parse_time(end)) {
+                (Some(start), Some(end)) if start <= end => context.time >= start && context.time < end,
+                (Some(start), Some(end)) => context.time >= start || context.time < end,
+                _ => false,
+            },
+            Condition::IdleFor { minutes } => {
+                context.idle.is_some_and(|idle| idle >= Duration::from_secs(*minutes as u64 * 60))
+            }
+        }
+    }
+}
+
+fn parse_time(text: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(text.trim(), "%H:%M").ok()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum RuleAction {
+    SetIntegration { integration: Integration, enabled: bool },
+    // Shows this status message, if it's in the list
+    SelectStatus(String),
+    // Switches to this layout template by name
+    SelectTemplate(String),
+}
+
+impl RuleAction {
+    pub const KINDS: [&'static str; 3] = ["Set integration", "Select status", "Select template"];
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            RuleAction::SetIntegration { .. } => Self::KINDS[0],
+            RuleAction::SelectStatus(_) => Self::KINDS[1],
+            RuleAction::SelectTemplate(_) => Self::KINDS[2],
+        }
+    }
+
+    fn from_kind(kind: &str) -> Self {
+        match kind {
+            "Select status" => RuleAction::SelectStatus(String::new()),
+            "Select template" => RuleAction::SelectTemplate(String::new()),
+            _ => RuleAction::SetIntegration {
+                integration: Integration::Components,
+                enabled: false,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct Rule {
+    pub name: String,
+    pub enabled: bool,
+    // All of these have to match
+    pub conditions: Vec<Condition>,
+    pub actions: Vec<RuleAction>,
+}
+
+impl Default for Rule {
+    fn default() -> Self {
+        Rule {
+            name: "Rule".to_string(),
+            enabled: true,
+            conditions: vec![Condition::VrActive(false)],
+            actions: vec![RuleAction::SetIntegration {
+                integration: Integration::Components,
+                enabled: false,
+            }],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct RuleOptions {
+    pub enabled: bool,
+    pub rules: Vec<Rule>,
+}
+
+// What the app looked like when rules were last checked
+pub struct RuleContext {
+    pub vr_active: bool,
+    pub focused_app: String,
+    pub media_status: Option<String>,
+    pub time: NaiveTime,
+    pub idle: Option<Duration>,
+}
+
+// Overrides from every matching rule; later rules win over earlier ones
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RuleEffects {
+    pub integrations: Vec<(Integration, bool)>,
+    pub status: Option<String>,
+    pub template: Option<String>,
+    pub matched: Vec<String>,
+}
+
+impl RuleEffects {
+    pub fn integration(&self, integration: Integration) -> Option<bool> {
+        self.integrations
+            .iter()
+            .rev()
+            .find(|(i, _)| *i == integration)
+            .map(|(_, enabled)| *enabled)
+    }
+}
+
+impl RuleOptions {
+    pub fn uses_idle(&self) -> bool {
+        self.rules
+            .iter()
+            .filter(|rule| rule.enabled)
+            .flat_map(|rule| &rule.conditions)
+            .any(|condition| matches!(condition, Condition::IdleFor { .. }))
+    }
+
+    pub fn show_rule_options(&mut self, ui: &mut egui::Ui, status_messages: &[String], templates: &[String]) -> egui::Response {
+        let mut response = ui.interact(
+            egui::Rect::EVERYTHING,
+            ui.id().with("rule_options"),
+            egui::Sense::hover(),
+        );
+        response |= ui.checkbox(&mut self.enabled, "Apply rules");
+        let mut remove_index = None;
+        for (index, rule) in self.rules.iter_mut().enumerate() {
+            ui.push_id(index, |ui| {
+                ui.group(|ui| {
+                    ui.horizontal(|ui| {
+                        response |= ui.checkbox(&mut rule.enabled, "");
+                        response |= ui.add(egui::TextEdit::singleline(&mut rule.name).desired_width(160.0).hint_text("Name"));
+                        if ui.button("X").clicked() {
+                            remove_index = Some(index);
+                        }
+                    });
+                    ui.label("When all of these match:");
+                    response |= show_conditions(ui, &mut rule.conditions);
+                    ui.label("Then:");
+                    response |= show_actions(ui, &mut rule.actions, status_messages, templates);
+                });
+            });
+        }
+        if let Some(index) = remove_index {
+            self.rules.remove(index);
+            response.mark_changed();
+        }
+        if ui.button("Add Rule").clicked() {
+            self.rules.push(Rule {
+                name: format!("Rule {}", self.rules.len() + 1),
+                ..Rule::default()
+            });
+            response.mark_changed();
+        }
+        response
+    }
+}
+
+fn kind_combo(ui: &mut egui::Ui, id: &str, kinds: &[&'static str], current: &'static str) -> Option<&'static str> {
+    let mut selected = None;
+    egui::ComboBox::from_id_source(ui.id().with(id))
+        .selected_text(current)
+        .show_ui(ui, |ui| {
+            for kind in kinds {
+                if ui.selectable_label(*kind == current, *kind).clicked() && *kind != current {
+                    selected = Some(*kind);
+                }
+            }
+        });
+    selected
+}
+
+fn choice_combo(ui: &mut egui::Ui, id: &str, selected: &mut String, options: &[String]) -> bool {
+    let mut changed = false;
+    egui::ComboBox::from_id_source(ui.id().with(id))
+        .selected_text(selected.as_str())
+        .width(200.0)
+        .show_ui(ui, |ui| {
+            for option in options {
+                changed |= ui.selectable_value(selected, option.clone(), option).changed();
+            }
+        });
+    changed
+}
+
+fn show_conditions(ui: &mut egui::Ui, conditions: &mut Vec<Condition>) -> egui::Response {
+    let mut response = ui.interact(egui::Rect::NOTHING, ui.id().with("conditions"), egui::Sense::hover());
+    let mut remove_index = None;
+    for (index, condition) in conditions.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            if let Some(kind) = kind_combo(ui, &format!("condition_{}", index), &Condition::KINDS, condition.kind()) {
+                *condition = Condition::from_kind(kind);
+                response.mark_changed();
+            }
+            match condition {
+                Condition::VrActive(active) => {
+                    response |= ui.selectable_value(active, true, "In VR");
+                    response |= ui.selectable_value(active, false, "On desktop");
+                }
+                Condition::AppFocused(app) => {
+                    response |= ui.add(egui::TextEdit::singleline(app).desired_width(160.0).hint_text("Part of the window title"));
+                }
+                Condition::MediaState(state) => {
+                    for (value, label) in [(MediaState::Playing, "Playing"), (MediaState::Paused, "Paused"), (MediaState::Stopped, "Stopped")] {
+                        response |= ui.selectable_value(state, value, label);
+                    }
+                }
+                Condition::MediaPausedFor { minutes } | Condition::IdleFor { minutes } => {
+                    response |= ui.add(egui::DragValue::new(minutes).range(1..=1440));
+                    ui.label("minutes");
+                }
+                Condition::TimeBetween { start, end } => {
+                    response |= ui.add(egui::TextEdit::singleline(start).desired_width(50.0).hint_text("HH:MM"));
+                    ui.label("and");
+                    response |= ui.add(egui::TextEdit::singleline(end).desired_width(50.0).hint_text("HH:MM"));
+                    if parse_time(start).is_none() || parse_time(end).is_none() {
+                        ui.colored_label(egui::Color32::RED, "Use HH:MM");
+                    }
+                }
+            }
+            if ui.button("-").clicked() {
+                remove_index = Some(index);
+            }
+        });
+    }
+    if let Some(index) = remove_index {
+        conditions.remove(index);
+        response.mark_changed();
+    }
+    if ui.button("Add Condition").clicked() {
+        conditions.push(Condition::VrActive(true));
+        response.mark_changed();
+    }
+    response
+}
+
+fn show_actions(ui: &mut egui::Ui, actions: &mut Vec<RuleAction>, status_messages: &[String], templates: &[String]) -> egui::Response {
+    let mut response = ui.interact(egui::Rect::NOTHING, ui.id().with("actions"), egui::Sense::hover());
+    let mut remove_index = None;
+    for (index, action) in actions.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            if let Some(kind) = kind_combo(ui, &format!("action_{}", index), &RuleAction::KINDS, action.kind()) {
+                *action = RuleAction::from_kind(kind);
+                response.mark_changed();
+            }
+            match action {
+                RuleAction::SetIntegration { integration, enabled } => {
+                    egui::ComboBox::from_id_source(ui.id().with(("action_integration", index)))
+                        .selected_text(integration.name())
+                        .show_ui(ui, |ui| {
+                            for option in Integration::ALL {
+                                if ui.selectable_value(integration, option, option.name()).changed() {
+                                    response.mark_changed();
+                                }
+                            }
+                        });
+                    response |= ui.selectable_value(enabled, true, "On");
+                    response |= ui.selectable_value(enabled, false, "Off");
+                }
+                RuleAction::SelectStatus(selected) => {
+                    if choice_combo(ui, &format!("action_status_{}", index), selected, status_messages) {
+                        response.mark_changed();
+                    }
+                }
+                RuleAction::SelectTemplate(selected) => {
+                    if choice_combo(ui, &format!("action_template_{}", index), selected, templates) {
+                        response.mark_changed();
+                    }
+                }
+            }
+            if ui.button("-").clicked() {
+                remove_index = Some(index);
+            }
+        });
+    }
+    if let Some(index) = remove_index {
+        actions.remove(index);
+        response.mark_changed();
+    }
+    if ui.button("Add Action").clicked() {
+        actions.push(RuleAction::SelectStatus(String::new()));
+        response.mark_changed();
+    }
+    response
+}
+
+// Seconds since the last keyboard or mouse input, from whichever source the desktop offers
+pub fn idle_time() -> Option<Duration> {
+    let xprintidle = Command::new("xprintidle")
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8_lossy(&output.stdout).trim().parse::<u64>().ok());
+    if let Some(ms) = xprintidle {
+        return Some(Duration::from_millis(ms));
+    }
+    let output = Command::new("dbus-send")
+        .args([
+            "--print-reply",
+            "--dest=org.gnome.Mutter.IdleMonitor",
+            "/org/gnome/Mutter/IdleMonitor/Core",
+            "org.gnome.Mutter.IdleMonitor.GetIdletime",
+        ])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .last()
+        .and_then(|ms| ms.parse::<u64>().ok())
+        .map(Duration::from_millis)
+}
+
+#[derive(Default)]
+struct Sample {
+    media_status: Option<String>,
+    idle: Option<Duration>,
+}
+
+// Samples the conditions that shell out on a thread of its own, so checking rules never
+// waits on playerctl or xprintidle. Stops when dropped.
+pub struct RuleSampler {
+    sample: Arc<Mutex<Sample>>,
+    wants_idle: Arc<AtomicBool>,
+    running: Arc<AtomicBool>,
+}
+
+impl RuleSampler {
+    pub fn start(wants_idle: bool) -> Self {
+        let sampler = Self {
+            sample: Arc::new(Mutex::new(Sample::default())),
+            wants_idle: Arc::new(AtomicBool::new(wants_idle)),
+            running: Arc::new(AtomicBool::new(true)),
+        };
+        let sample = Arc::clone(&sampler.sample);
+        let wants_idle = Arc::clone(&sampler.wants_idle);
+        let running = Arc::clone(&sampler.running);
+        std::thread::spawn(move || {
+            let media = MediaLinkModule::new();
+            while running.load(Ordering::SeqCst) {
+                let media_status = media.get_track_info().map(|(status, _, _)| status);
+                let idle = if wants_idle.load(Ordering::SeqCst) { idle_time() } else { None };
+                *sample.lock().unwrap() = Sample { media_status, idle };
+                std::thread::sleep(EVALUATE_INTERVAL);
+            }
+        });
+        sampler
+    }
+
+    // Idle time is only looked up while a rule needs it
+    pub fn set_wants_idle(&self, wants_idle: bool) {
+        self.wants_idle.store(wants_idle, Ordering::SeqCst);
+    }
+
+    pub fn media_status(&self) -> Option<String> {
+        self.sample.lock().unwrap().media_status.clone()
+    }
+
+    pub fn idle(&self) -> Option<Duration> {
+        self.sample.lock().unwrap().idle
+    }
+}
+
+impl Drop for RuleSampler {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+    }
+}
+
+pub struct RuleEngine {
+    paused_since: Option<Instant>,
+    last_evaluated: Option<Instant>,
+    effects: RuleEffects,
+}
+
+impl RuleEngine {
+    pub fn new() -> Self {
+        Self {
+            paused_since: None,
+            last_evaluated: None,
+            effects: RuleEffects::default(),
+        }
+    }
+
+    pub fn due(&self) -> bool {
+        self.last_evaluated.is_none_or(|last| last.elapsed() >= EVALUATE_INTERVAL)
+    }
+
+    pub fn effects(&self) -> &RuleEffects {
+        &self.effects
+    }
+
+    pub fn clear(&mut self) {
+        self.effects = RuleEffects::default();
+        self.paused_since = None;
+    }
+
+    pub fn evaluate(&mut self, options: &RuleOptions, context: &RuleContext) -> &RuleEffects {
+        self.last_evaluated = Some(Instant::now());
+        if context.media_status.as_deref() == Some("Paused") {
+            self.paused_since.get_or_insert_with(Instant::now);
+        } else {
+            self.paused_since = None;
+        }
+        let paused_for = self.paused_since.map(|since| since.elapsed());
+
+        let mut effects = RuleEffects::default();
+        for rule in options.rules.iter().filter(|rule| rule.enabled) {
+            if !rule.conditions.iter().all(|condition| condition.matches(context, paused_for)) {
+                continue;
+            }
+            effects.matched.push(rule.name.clone());
+            for action in &rule.actions {
+                match action {
+                    RuleAction::SetIntegration { integration, enabled } => effects.integrations.push((*integration, *enabled)),
+                    RuleAction::SelectStatus(status) if !status.is_empty() => effects.status = Some(status.clone()),
+                    RuleAction::SelectTemplate(name) if !name.is_empty() => effects.template = Some(name.clone()),
+                    _ => {}
+                }
+            }
+        }
+        self.effects = effects;
+        &self.effects
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> RuleContext {
+        RuleContext {
+            vr_active: false,
+            focused_app: "Blender - scene.blend".to_string(),
+            media_status: None,
+            time: NaiveTime::from_hms_opt(12, 0, 0).unwrap(),
+            idle: None,
+        }
+    }
+
+    fn at(hour: u32, minute: u32) -> RuleContext {
+        RuleContext {
+            time: NaiveTime::from_hms_opt(hour, minute, 0).unwrap(),
+            ..context()
+        }
+    }
+
+    fn between(start: &str, end: &str) -> Condition {
+        Condition::TimeBetween {
+            start: start.to_string(),
+            end: end.to_string(),
+        }
+    }
+
+    fn rule(name: &str, conditions: Vec<Condition>, actions: Vec<RuleAction>) -> Rule {
+        Rule {
+            name: name.to_string(),
+            enabled: true,
+            conditions,
+            actions,
+        }
+    }
+
+    #[test]
+    fn time_between_wraps_past_midnight() {
+        let night = between("22:00", "06:00");
+        assert!(night.matches(&at(22, 0), None));
+        assert!(night.matches(&at(23, 59), None));
+        assert!(night.matches(&at(0, 0), None));
+        assert!(night.matches(&at(5, 59), None));
+        assert!(!night.matches(&at(6, 0), None));
+        assert!(!night.matches(&at(21, 59), None));
+        assert!(!night.matches(&at(12, 0), None));
+    }
+
+    #[test]
+    fn time_between_within_a_day() {
+        let work = between("09:00", "17:30");
+        assert!(work.matches(&at(9, 0), None));
+        assert!(work.matches(&at(17, 29), None));
+        assert!(!work.matches(&at(17, 30), None));
+        assert!(!work.matches(&at(8, 59), None));
+        // An empty range never matches
+        assert!(!between("10:00", "10:00").matches(&at(10, 0), None));
+        assert!(!between("25:00", "06:00").matches(&at(1, 0), None));
+        assert!(!between("", "06:00").matches(&at(1, 0), None));
+    }
+
+    #[test]
+    fn matches_focus_media_and_idle() {
+        let context = RuleContext {
+            media_status: Some("Paused".to_string()),
+            idle: Some(Duration::from_secs(600)),
+            ..context()
+        };
+        assert!(Condition::AppFocused(" blender ".to_string()).matches(&context, None));
+        assert!(!Condition::AppFocused("Firefox".to_string()).matches(&context, None));
+        assert!(!Condition::AppFocused(" ".to_string()).matches(&context, None));
+        assert!(Condition::MediaState(MediaState::Paused).matches(&context, None));
+        assert!(Condition::MediaState(MediaState::Stopped).matches(&RuleContext { media_status: None, ..at(1, 0) }, None));
+        assert!(Condition::IdleFor { minutes: 10 }.matches(&context, None));
+        assert!(!Condition::IdleFor { minutes: 11 }.matches(&context, None));
+        assert!(!Condition::IdleFor { minutes: 1 }.matches(&RuleContext { idle: None, ..at(1, 0) }, None));
+        assert!(Condition::VrActive(false).matches(&context, None));
+    }
+
+    #[test]
+    fn media_paused_for_counts_from_the_first_paused_check() {
+        let options = RuleOptions {
+            enabled: true,
+            rules: vec![rule(
+                "Away",
+                vec![Condition::MediaPausedFor { minutes: 5 }],
+                vec![RuleAction::SelectStatus("Away".to_string())],
+            )],
+        };
+        let paused = RuleContext {
+            media_status: Some("Paused".to_string()),
+            ..context()
+        };
+        let mut engine = RuleEngine::new();
+        assert_eq!(engine.evaluate(&options, &paused).status, None);
+        engine.paused_since = Instant::now().checked_sub(Duration::from_secs(5 * 60));
+        assert_eq!(engine.evaluate(&options, &paused).status.as_deref(), Some("Away"));
+        // Playing again starts the count over
+        let playing = RuleContext {
+            media_status: Some("Playing".to_string()),
+            ..context()
+        };
+        assert_eq!(engine.evaluate(&options, &playing).status, None);
+        assert!(engine.paused_since.is_none());
+        assert!(!Condition::MediaPausedFor { minutes: 5 }.matches(&paused, Some(Duration::from_secs(299))));
+    }
+
+    #[test]
+    fn later_matching_rules_win() {
+        let options = RuleOptions {
+            enabled: true,
+            rules: vec![
+                rule(
+                    "Desktop",
+                    vec![Condition::VrActive(false)],
+                    vec![
+                        RuleAction::SetIntegration { integration: Integration::Media, enabled: false },
+                        RuleAction::SelectStatus("At my desk".to_string()),
+                        RuleAction::SelectTemplate("Compact".to_string()),
+                    ],
+                ),
+                rule(
+                    "Blender at noon",
+                    vec![Condition::AppFocused("blender".to_string()), between("11:00", "13:00")],
+                    vec![
+                        RuleAction::SetIntegration { integration: Integration::Media, enabled: true },
+                        RuleAction::SelectStatus("Modelling".to_string()),
+                        RuleAction::SelectTemplate(String::new()),
+                    ],
+                ),
+                rule("In VR", vec![Condition::VrActive(true)], vec![RuleAction::SelectStatus("In VR".to_string())]),
+                Rule {
+                    enabled: false,
+                    ..rule("Off", vec![], vec![RuleAction::SelectStatus("Disabled".to_string())])
+                },
+            ],
+        };
+        let mut engine = RuleEngine::new();
+        let effects = engine.evaluate(&options, &context()).clone();
+        assert_eq!(effects.matched, ["Desktop", "Blender at noon"]);
+        assert_eq!(effects.status.as_deref(), Some("Modelling"));
+        // An empty template choice leaves the earlier one in place
+        assert_eq!(effects.template.as_deref(), Some("Compact"));
+        assert_eq!(effects.integration(Integration::Media), Some(true));
+        assert_eq!(effects.integration(Integration::Time), None);
+
+        // Only the first rule once the time condition fails
+        let effects = engine.evaluate(&options, &at(18, 0)).clone();
+        assert_eq!(effects.matched, ["Desktop"]);
+        assert_eq!(effects.integration(Integration::Media), Some(false));
+        engine.clear();
+        assert_eq!(engine.effects(), &RuleEffects::default());
+    }
+
+    #[test]
+    fn only_samples_idle_time_when_a_rule_needs_it() {
+        let mut options = RuleOptions {
+            enabled: true,
+            rules: vec![rule("Idle", vec![Condition::IdleFor { minutes: 5 }], vec![])],
+        };
+        assert!(options.uses_idle());
+        options.rules[0].enabled = false;
+        assert!(!options.uses_idle());
+    }
+}
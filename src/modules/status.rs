@@ -1,6 +1,7 @@
 use rand::seq::SliceRandom;
 use serde::{Deserialize, Serialize};
 use eframe::egui;
+use crate::stylize::{self, TextStyle};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StatusOptions {
@@ -11,6 +12,8 @@ pub struct StatusOptions {
     pub enable_custom_prefix_shuffle: bool,
     pub custom_prefixes: String,
     pub add_speech_bubble: bool,
+    #[serde(default)]
+    pub style: TextStyle,
 }
 
 impl Default for StatusOptions {
@@ -23,6 +26,7 @@ impl Default for StatusOptions {
             enable_custom_prefix_shuffle: false,
             custom_prefixes: "".to_string(),
             add_speech_bubble: false,
+            style: TextStyle::None,
         }
     }
 }
@@ -49,6 +53,10 @@ impl StatusOptions {
             response |= ui.text_edit_singleline(&mut self.custom_prefixes);
         }
         response |= ui.checkbox(&mut self.add_speech_bubble, "Add 🗨 as prefix");
+        ui.horizontal(|ui| {
+            ui.label("Text style: ");
+            response |= stylize::style_combo(ui, "status_style", &mut self.style);
+        });
         response
     }
 }
@@ -57,6 +65,8 @@ pub struct StatusModule {
     pub messages: Vec<String>,
     current_index: usize,
     last_cycle: std::time::Instant,
+    // What was showing before a rule picked a status, put back once no rule does
+    before_override: Option<String>,
 }
 
 impl StatusModule {
@@ -65,6 +75,7 @@ impl StatusModule {
             messages: vec![],
             current_index: 0,
             last_cycle: std::time::Instant::now(),
+            before_override: None,
         }
     }
 
@@ -72,6 +83,30 @@ impl StatusModule {
         self.messages.push(message);
     }
 
+    // Shows message next if it's one of ours
+    pub fn select_message(&mut self, message: &str) {
+        if let Some(index) = self.messages.iter().position(|m| m == message) {
+            self.current_index = index;
+        }
+    }
+
+    // Shows message while a rule asks for it, or the status it replaced once message is None
+    pub fn set_override(&mut self, message: Option<&str>) {
+        match message {
+            Some(message) => {
+                if self.before_override.is_none() {
+                    self.before_override = self.messages.get(self.current_index).cloned();
+                }
+                self.select_message(message);
+            }
+            None => {
+                if let Some(previous) = self.before_override.take() {
+                    self.select_message(&previous);
+                }
+            }
+        }
+    }
+
     pub fn remove_message(&mut self, index: usize) {
         if index < self.messages.len() {
             self.messages.remove(index);
@@ -94,6 +129,7 @@ impl StatusModule {
                 }
             }
         }
+        message = stylize::stylize(&message, options.style);
         if options.add_speech_bubble {
             message = format!("🗨 {}", message);
         }
@@ -113,4 +149,59 @@ impl StatusModule {
             self.last_cycle = std::time::Instant::now();
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn module(messages: &[&str]) -> StatusModule {
+        let mut module = StatusModule::new();
+        for message in messages {
+            module.add_message(message.to_string());
+        }
+        module
+    }
+
+    fn current(module: &StatusModule) -> Option<String> {
+        module.get_current_message(&StatusOptions::default())
+    }
+
+    #[test]
+    fn restores_the_status_a_rule_replaced() {
+        let mut module = module(&["Chilling", "AFK", "Working"]);
+        module.select_message("Working");
+        module.set_override(Some("AFK"));
+        assert_eq!(current(&module).as_deref(), Some("AFK"));
+        // Staying matched, or switching rules, keeps the original to go back to
+        module.set_override(Some("AFK"));
+        module.set_override(Some("Chilling"));
+        assert_eq!(current(&module).as_deref(), Some("Chilling"));
+        module.set_override(None);
+        assert_eq!(current(&module).as_deref(), Some("Working"));
+        // Nothing to restore without an override
+        module.select_message("AFK");
+        module.set_override(None);
+        assert_eq!(current(&module).as_deref(), Some("AFK"));
+    }
+
+    #[test]
+    fn restores_by_message_after_a_removal() {
+        let mut module = module(&["Chilling", "AFK", "Working"]);
+        module.select_message("Working");
+        module.set_override(Some("AFK"));
+        module.remove_message(0);
+        module.set_override(None);
+        assert_eq!(current(&module).as_deref(), Some("Working"));
+    }
+
+    #[test]
+    fn ignores_a_status_it_doesnt_have() {
+        let mut module = module(&["Chilling"]);
+        module.set_override(Some("Gone"));
+        assert_eq!(current(&module).as_deref(), Some("Chilling"));
+        module.set_override(None);
+        assert_eq!(current(&module).as_deref(), Some("Chilling"));
+        assert_eq!(current(&StatusModule::new()), None);
+    }
+}
@@ -1,8 +1,9 @@
-use chrono::Local;
+use chrono::{Local, Timelike};
 use chrono_tz::Tz;
 use log::error;
 use serde::{Deserialize, Serialize};
 use eframe::egui;
+use crate::stylize::{self, TextStyle};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TimeConfig {
@@ -12,6 +13,8 @@ pub struct TimeConfig {
     pub use_system_culture: bool,
     pub auto_dst: bool,
     pub custom_timezone: Option<String>,
+    #[serde(default)]
+    pub style: TextStyle,
 }
 
 impl Default for TimeConfig {
@@ -23,6 +26,7 @@ impl Default for TimeConfig {
             use_system_culture: true,
             auto_dst: true,
             custom_timezone: None,
+            style: TextStyle::None,
         }
     }
 }
@@ -51,6 +55,10 @@ impl TimeOptions {
         response |= ui.checkbox(&mut self.config.use_24_hour, "24-Hour time format");
         response |= ui.checkbox(&mut self.config.use_system_culture, "Use current system culture for formatting time");
         response |= ui.checkbox(&mut self.config.auto_dst, "Auto daylight savings time");
+        ui.horizontal(|ui| {
+            ui.label("Text style: ");
+            response |= stylize::style_combo(ui, "time_style", &mut self.config.style);
+        });
         let mut use_custom_tz = self.config.custom_timezone.is_some();
         response |= ui.checkbox(&mut use_custom_tz, "Custom time zone");
         if use_custom_tz {
@@ -105,10 +113,29 @@ impl TimeModule {
                 now.format(format_str).to_string()
             }
         };
-        if options.config.show_my_time_prefix {
+        let time_str = if options.config.show_my_time_prefix {
             format!("My time: {}", time_str)
         } else {
             time_str
+        };
+        stylize::stylize(&time_str, options.config.style)
+    }
+
+    // The formatted time without the "My time:" prefix
+    pub fn get_clock(options: &TimeOptions) -> String {
+        let mut options = options.clone();
+        options.config.show_my_time_prefix = false;
+        Self::get_local_time(&options)
+    }
+
+    pub fn get_hour_minute(options: &TimeOptions) -> (u32, u32) {
+        let now = Local::now();
+        match options.config.custom_timezone.as_ref().and_then(|tz| tz.parse::<Tz>().ok()) {
+            Some(tz) => {
+                let time_in_tz = now.with_timezone(&tz);
+                (time_in_tz.hour(), time_in_tz.minute())
+            }
+            None => (now.hour(), now.minute()),
         }
     }
 }
\ No newline at end of file
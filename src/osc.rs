@@ -1,33 +1,448 @@
-use std::net::UdpSocket;
+use std::collections::HashMap;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::{Duration, Instant};
 use anyhow::Result;
+use log::{debug, error, info};
 use rosc::{OscMessage, OscPacket, OscType};
+use crate::modules::app::{OscOptions, OscTarget};
+use capture::{CaptureFormat, CaptureMode, CaptureWriter};
+
+pub mod capture;
+mod query;
+mod router;
+mod server;
+pub use query::{DiscoveredService, OscQueryService};
+pub use router::OscRouter;
+pub use server::{OscEvent, OscServer};
+
+// Minimum gap before re-raising the typing indicator, so flapping focus or
+// clearing and retyping doesn't send a packet per keystroke
+const TYPING_DEBOUNCE: Duration = Duration::from_millis(500);
+// A typing indicator that failed to send is tried again after this, not on every frame
+const TYPING_RETRY: Duration = Duration::from_secs(2);
+
+// VRChat's anti-spam drops chatbox input that arrives faster than this
+pub const CHATBOX_RATE_WINDOW: Duration = Duration::from_millis(1600);
+
+#[derive(Debug, Clone)]
+pub struct ChatboxMessage {
+    pub text: String,
+    // Send straight to the chatbox instead of opening the keyboard with it
+    pub immediate: bool,
+    // Play the notification sound for other players
+    pub notify: bool,
+    pub slim: bool,
+    // Hold the message until the rate window opens instead of dropping it
+    pub defer: bool,
+}
+
+impl ChatboxMessage {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            immediate: true,
+            notify: false,
+            slim: false,
+            defer: false,
+        }
+    }
+
+    // Nothing opens the keyboard with a message yet, but VRChat supports it
+    #[allow(dead_code)]
+    pub fn immediate(mut self, immediate: bool) -> Self {
+        self.immediate = immediate;
+        self
+    }
+
+    pub fn notify(mut self, notify: bool) -> Self {
+        self.notify = notify;
+        self
+    }
+
+    pub fn slim(mut self, slim: bool) -> Self {
+        self.slim = slim;
+        self
+    }
+
+    pub fn deferred(mut self) -> Self {
+        self.defer = true;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SendOutcome {
+    Sent,
+    Deferred,
+    Dropped,
+}
+
+struct RateLimiter {
+    window: Duration,
+    last_send: Option<Instant>,
+}
+
+impl RateLimiter {
+    fn new(window: Duration) -> Self {
+        Self {
+            window,
+            last_send: None,
+        }
+    }
+
+    fn is_ready(&self) -> bool {
+        self.last_send.is_none_or(|last| last.elapsed() >= self.window)
+    }
+
+    fn record_send(&mut self) {
+        self.last_send = Some(Instant::now());
+    }
+}
+
+struct Destination {
+    name: String,
+    addr: SocketAddr,
+    follow_oscquery: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct SendReport {
+    pub at: Instant,
+    pub error: Option<String>,
+}
 
 pub struct OscClient {
     socket: UdpSocket,
-    addr: String,
+    destinations: Vec<Destination>,
+    discovered_addr: Option<SocketAddr>,
+    target_errors: HashMap<String, String>,
+    last_send: Option<SendReport>,
+    capture: Option<CaptureWriter>,
+    dry_run: bool,
+    typing: bool,
+    last_typing_send: Option<Instant>,
+    typing_failed_at: Option<Instant>,
+    rate_limiter: RateLimiter,
+    pending: Option<ChatboxMessage>,
 }
 
 impl OscClient {
-    pub fn new(ip: &str, port: u16) -> Result<Self> {
+    pub fn new(targets: &[OscTarget]) -> Result<Self> {
         let socket = UdpSocket::bind("0.0.0.0:0")?;
-        let addr = format!("{}:{}", ip, port);
-        Ok(Self { socket, addr })
+        let mut client = Self {
+            socket,
+            destinations: Vec::new(),
+            discovered_addr: None,
+            target_errors: HashMap::new(),
+            last_send: None,
+            capture: None,
+            dry_run: false,
+            typing: false,
+            last_typing_send: None,
+            typing_failed_at: None,
+            rate_limiter: RateLimiter::new(CHATBOX_RATE_WINDOW),
+            pending: None,
+        };
+        client.set_targets(targets);
+        Ok(client)
+    }
+
+    // Targets that don't resolve are skipped and reported through target_errors
+    pub fn set_targets(&mut self, targets: &[OscTarget]) {
+        self.destinations.clear();
+        self.target_errors.clear();
+        for target in targets.iter().filter(|target| target.enabled) {
+            match resolve_target(target) {
+                Ok(addr) => {
+                    info!("Sending OSC to {} at {}", target.name, addr);
+                    self.destinations.push(Destination {
+                        name: target.name.clone(),
+                        addr,
+                        follow_oscquery: target.follow_oscquery,
+                    });
+                }
+                Err(e) => {
+                    error!("Invalid OSC target {} ({}): {}", target.name, target.addr(), e);
+                    self.target_errors.insert(target.name.clone(), e.to_string());
+                }
+            }
+        }
+    }
+
+    // Points targets that follow OSCQuery at a discovered client, or back at their own address
+    pub fn set_discovered_addr(&mut self, addr: Option<SocketAddr>) {
+        self.discovered_addr = addr;
     }
 
-    pub fn send_chatbox_message(&self, message: &str, _fx_sound: bool, slim_mode: bool) -> Result<()> {
-        let mut msg = message.to_string();
-        if slim_mode {
-            msg.push_str("\u{0003}\u{001f}");
+    // Starts a new capture file for every outgoing packet, or stops capturing when mode is Off
+    pub fn set_capture(&mut self, mode: CaptureMode, format: CaptureFormat) -> Result<()> {
+        if let Some(previous) = self.capture.take() {
+            info!("Stopped OSC capture {} after {} packets", previous.path().display(), previous.packets());
         }
-        let msg_buf = rosc::encoder::encode(&OscPacket::Message(OscMessage {
+        self.dry_run = false;
+        if mode != CaptureMode::Off {
+            self.capture = Some(CaptureWriter::create(&capture::capture_dir(), format)?);
+            self.dry_run = mode == CaptureMode::DryRun;
+        }
+        Ok(())
+    }
+
+    pub fn capture(&self) -> Option<&CaptureWriter> {
+        self.capture.as_ref()
+    }
+
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    pub fn target_errors(&self) -> &HashMap<String, String> {
+        &self.target_errors
+    }
+
+    pub fn last_send(&self) -> Option<&SendReport> {
+        self.last_send.as_ref()
+    }
+
+    fn send_packet(&mut self, packet: &OscPacket) -> Result<()> {
+        let msg_buf = rosc::encoder::encode(packet)?;
+        if let Some(capture) = &mut self.capture {
+            capture.write(packet, &msg_buf);
+        }
+        // Nothing went out, so there's no send to report on
+        if self.dry_run {
+            return Ok(());
+        }
+        let mut failed = Vec::new();
+        for destination in &self.destinations {
+            let addr = match (destination.follow_oscquery, self.discovered_addr) {
+                (true, Some(addr)) => addr,
+                _ => destination.addr,
+            };
+            match self.socket.send_to(&msg_buf, addr) {
+                Ok(_) => {
+                    self.target_errors.remove(&destination.name);
+                }
+                Err(e) => {
+                    error!("Failed to send OSC to {} ({}): {}", destination.name, addr, e);
+                    self.target_errors.insert(destination.name.clone(), e.to_string());
+                    failed.push(destination.name.as_str());
+                }
+            }
+        }
+        let error = if failed.is_empty() {
+            None
+        } else {
+            Some(format!("Failed to send to {}", failed.join(", ")))
+        };
+        self.last_send = Some(SendReport {
+            at: Instant::now(),
+            error: error.clone(),
+        });
+        match error {
+            Some(error) => Err(anyhow::anyhow!(error)),
+            None => Ok(()),
+        }
+    }
+
+    pub fn set_typing(&mut self, typing: bool) -> Result<()> {
+        if typing == self.typing {
+            return Ok(());
+        }
+        if typing && self.last_typing_send.is_some_and(|last| last.elapsed() < TYPING_DEBOUNCE) {
+            // Callers poll every frame, so this is picked up once the debounce passes
+            return Ok(());
+        }
+        if self.typing_failed_at.is_some_and(|at| at.elapsed() < TYPING_RETRY) {
+            return Ok(());
+        }
+        if let Err(e) = self.send_packet(&OscPacket::Message(OscMessage {
+            addr: "/chatbox/typing".to_string(),
+            args: vec![OscType::Bool(typing)],
+        })) {
+            self.typing_failed_at = Some(Instant::now());
+            return Err(e);
+        }
+        self.typing = typing;
+        self.typing_failed_at = None;
+        self.last_typing_send = Some(Instant::now());
+        Ok(())
+    }
+
+    pub fn forward(&mut self, packet: &OscPacket) -> Result<()> {
+        self.send_packet(packet)
+    }
+
+    pub fn send_parameter(&mut self, name: &str, value: OscType) -> Result<()> {
+        self.send_packet(&OscPacket::Message(OscMessage {
+            addr: format!("/avatar/parameters/{}", name),
+            args: vec![value],
+        }))
+    }
+
+    pub fn send_chatbox(&mut self, message: ChatboxMessage) -> Result<SendOutcome> {
+        if message.defer {
+            if let Some(replaced) = self.pending.take() {
+                debug!("Replacing deferred chatbox message: {}", replaced.text);
+            }
+        } else if self.pending.is_some() {
+            // A deferred message is something the user asked for; don't let a refresh overwrite it
+            debug!("Deferred chatbox message pending, dropping: {}", message.text);
+            return Ok(SendOutcome::Dropped);
+        }
+        if !self.rate_limiter.is_ready() {
+            if message.defer {
+                self.pending = Some(message);
+                return Ok(SendOutcome::Deferred);
+            }
+            debug!("Rate limited, dropping chatbox message: {}", message.text);
+            return Ok(SendOutcome::Dropped);
+        }
+        self.write_chatbox(&message)?;
+        Ok(SendOutcome::Sent)
+    }
+
+    // Sends the deferred message once the rate window opens, returning it if it went out
+    pub fn flush_pending(&mut self) -> Result<Option<ChatboxMessage>> {
+        if self.pending.is_none() || !self.rate_limiter.is_ready() {
+            return Ok(None);
+        }
+        let message = self.pending.take().unwrap();
+        self.write_chatbox(&message)?;
+        info!("Sent deferred chatbox message: {}", message.text);
+        Ok(Some(message))
+    }
+
+    fn write_chatbox(&mut self, message: &ChatboxMessage) -> Result<()> {
+        let mut text = message.text.clone();
+        if message.slim {
+            text.push_str("\u{0003}\u{001f}");
+        }
+        // Count the attempt even if it fails so a broken target can't be hammered
+        self.rate_limiter.record_send();
+        self.send_packet(&OscPacket::Message(OscMessage {
             addr: "/chatbox/input".to_string(),
             args: vec![
-                OscType::String(msg),
-                OscType::Bool(true),
-                OscType::Bool(false),
+                OscType::String(text),
+                OscType::Bool(message.immediate),
+                OscType::Bool(message.notify),
             ],
-        }))?;
-        self.socket.send_to(&msg_buf, &self.addr)?;
-        Ok(())
+        }))
+    }
+}
+
+fn resolve_target(target: &OscTarget) -> Result<SocketAddr> {
+    if target.port == 0 {
+        anyhow::bail!("Port must not be 0");
+    }
+    (target.ip.trim(), target.port)
+        .to_socket_addrs()?
+        .find(SocketAddr::is_ipv4)
+        .ok_or_else(|| anyhow::anyhow!("No IPv4 address for {}", target.ip))
+}
+
+// Starts the OSC server and, if enabled, the OSCQuery advertisement pointing at it
+pub fn start_receiver(options: &OscOptions) -> Result<(OscServer, Option<OscQueryService>)> {
+    // With OSCQuery VRChat finds us on any port, so leave 9001 to other apps
+    let receive_port = if options.oscquery_enabled { 0 } else { options.receive_port };
+    let server = OscServer::start("0.0.0.0", receive_port)
+        .map_err(|e| anyhow::anyhow!("Failed to start OSC server on port {}: {}", receive_port, e))?;
+    let oscquery = if options.oscquery_enabled {
+        match OscQueryService::start(server.port()) {
+            Ok(service) => Some(service),
+            Err(e) => {
+                error!("Failed to start OSCQuery service: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+    Ok((server, oscquery))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::OscListener;
+
+    #[test]
+    fn sends_chatbox_input_straight_to_the_chatbox() {
+        let listener = OscListener::new();
+        let mut client = OscClient::new(&[listener.target("VRChat")]).unwrap();
+        let message = ChatboxMessage::new("hi").notify(true).slim(true);
+        assert_eq!(client.send_chatbox(message).unwrap(), SendOutcome::Sent);
+        let packet = listener.recv_addr("/chatbox/input").unwrap();
+        assert_eq!(
+            packet.args,
+            [
+                OscType::String("hi\u{0003}\u{001f}".to_string()),
+                OscType::Bool(true),
+                OscType::Bool(true),
+            ]
+        );
+
+        // Each flag goes out as its own argument
+        client.rate_limiter.last_send = None;
+        let message = ChatboxMessage::new("hi").immediate(false);
+        assert_eq!(client.send_chatbox(message).unwrap(), SendOutcome::Sent);
+        let packet = listener.recv_addr("/chatbox/input").unwrap();
+        assert_eq!(
+            packet.args,
+            [OscType::String("hi".to_string()), OscType::Bool(false), OscType::Bool(false)]
+        );
+    }
+
+    #[test]
+    fn holds_chatbox_input_to_the_rate_window() {
+        let listener = OscListener::new();
+        let mut client = OscClient::new(&[listener.target("VRChat")]).unwrap();
+        client.rate_limiter.window = Duration::from_millis(100);
+        assert_eq!(client.send_chatbox(ChatboxMessage::new("one")).unwrap(), SendOutcome::Sent);
+        assert_eq!(client.send_chatbox(ChatboxMessage::new("two")).unwrap(), SendOutcome::Dropped);
+        assert_eq!(client.send_chatbox(ChatboxMessage::new("three").deferred()).unwrap(), SendOutcome::Deferred);
+        // Refreshes don't replace a deferred message
+        assert_eq!(client.send_chatbox(ChatboxMessage::new("four")).unwrap(), SendOutcome::Dropped);
+        assert!(client.flush_pending().unwrap().is_none());
+        std::thread::sleep(Duration::from_millis(120));
+        assert_eq!(client.flush_pending().unwrap().map(|m| m.text).as_deref(), Some("three"));
+        let texts: Vec<_> = std::iter::from_fn(|| listener.recv_addr("/chatbox/input"))
+            .map(|m| m.args[0].clone())
+            .collect();
+        assert_eq!(texts, [OscType::String("one".to_string()), OscType::String("three".to_string())]);
+    }
+
+    #[test]
+    fn dry_run_sends_and_reports_nothing() {
+        let listener = OscListener::new();
+        let mut client = OscClient::new(&[listener.target("VRChat")]).unwrap();
+        client.dry_run = true;
+        client.send_parameter("Test", OscType::Bool(true)).unwrap();
+        assert!(listener.recv().is_none());
+        assert!(client.last_send().is_none());
+        client.dry_run = false;
+        client.send_parameter("Test", OscType::Bool(true)).unwrap();
+        assert!(listener.recv().is_some());
+        assert!(client.last_send().is_some_and(|report| report.error.is_none()));
+    }
+
+    #[test]
+    fn backs_off_after_a_failed_typing_indicator() {
+        // Broadcasting without SO_BROADCAST fails on every send
+        let target = OscTarget {
+            ip: "255.255.255.255".to_string(),
+            ..OscListener::new().target("Broadcast")
+        };
+        let mut client = OscClient::new(&[target]).unwrap();
+        assert!(client.set_typing(true).is_err());
+        let failed_at = client.last_send().unwrap().at;
+        for _ in 0..10 {
+            assert!(client.set_typing(true).is_ok());
+        }
+        assert_eq!(client.last_send().unwrap().at, failed_at);
+        assert!(!client.typing);
+
+        client.typing_failed_at = Instant::now().checked_sub(TYPING_RETRY);
+        assert!(client.set_typing(true).is_err());
+        assert!(client.last_send().unwrap().at > failed_at);
     }
-}
\ No newline at end of file
+}
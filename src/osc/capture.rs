@@ -0,0 +1,407 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use anyhow::Result;
+use log::{error, info};
+use rosc::{OscBundle, OscMessage, OscPacket, OscTime, OscType};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{json, Value};
+use crate::modules::app::OscTarget;
+use super::OscClient;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum CaptureMode {
+    Off,
+    // Capture and send as usual
+    Record,
+    // Capture only, nothing goes out on the network
+    DryRun,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum CaptureFormat {
+    JsonLines,
+    Raw,
+}
+
+// A dry run only lasts the session; saved, it would quietly stop sending on every later launch
+pub fn save_mode<S: Serializer>(mode: &CaptureMode, serializer: S) -> Result<S::Ok, S::Error> {
+    match mode {
+        CaptureMode::DryRun => CaptureMode::Off,
+        mode => *mode,
+    }
+    .serialize(serializer)
+}
+
+// Configs saved before dry runs were kept out of them may still have one
+pub fn load_mode<'de, D: Deserializer<'de>>(deserializer: D) -> Result<CaptureMode, D::Error> {
+    Ok(match CaptureMode::deserialize(deserializer)? {
+        CaptureMode::DryRun => CaptureMode::Off,
+        mode => mode,
+    })
+}
+
+impl CaptureFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            CaptureFormat::JsonLines => "jsonl",
+            CaptureFormat::Raw => "osc",
+        }
+    }
+}
+
+pub fn capture_dir() -> PathBuf {
+    crate::config::data_dir().join("captures")
+}
+
+pub struct CaptureWriter {
+    path: PathBuf,
+    format: CaptureFormat,
+    file: BufWriter<File>,
+    started: Instant,
+    packets: u64,
+}
+
+impl CaptureWriter {
+    pub fn create(dir: &Path, format: CaptureFormat) -> Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        let timestamp = chrono::Local::now().format("%Y-%m-%d_%H-%M-%S");
+        // A restart within the same second gets a numbered file instead of truncating the last one
+        let mut number = 1;
+        let (path, file) = loop {
+            let suffix = if number == 1 { String::new() } else { format!("_{}", number) };
+            let path = dir.join(format!("capture_{}{}.{}", timestamp, suffix, format.extension()));
+            match File::options().write(true).create_new(true).open(&path) {
+                Ok(file) => break (path, BufWriter::new(file)),
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => number += 1,
+                Err(e) => return Err(e.into()),
+            }
+        };
+        info!("Capturing OSC packets to {}", path.display());
+        Ok(Self {
+            path,
+            format,
+            file,
+            started: Instant::now(),
+            packets: 0,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn packets(&self) -> u64 {
+        self.packets
+    }
+
+    pub fn write(&mut self, packet: &OscPacket, encoded: &[u8]) {
+        let offset_ms = self.started.elapsed().as_millis() as u64;
+        let result = match self.format {
+            CaptureFormat::JsonLines => {
+                let line = json!({
+                    "offset_ms": offset_ms,
+                    "time": chrono::Local::now().to_rfc3339(),
+                    "packet": packet_to_json(packet),
+                });
+                writeln!(self.file, "{}", line)
+            }
+            // Each record is the offset as a u64, the length as a u32 (both little endian) and the packet
+            CaptureFormat::Raw => self
+                .file
+                .write_all(&offset_ms.to_le_bytes())
+                .and_then(|_| self.file.write_all(&(encoded.len() as u32).to_le_bytes()))
+                .and_then(|_| self.file.write_all(encoded)),
+        };
+        // Flush per packet so a crash still leaves a usable capture
+        match result.and_then(|_| self.file.flush()) {
+            Ok(()) => self.packets += 1,
+            Err(e) => error!("Failed to write OSC capture {}: {}", self.path.display(), e),
+        }
+    }
+}
+
+fn packet_to_json(packet: &OscPacket) -> Value {
+    match packet {
+        OscPacket::Message(message) => json!({
+            "addr": message.addr,
+            "args": message.args.iter().map(arg_to_json).collect::<Vec<_>>(),
+        }),
+        OscPacket::Bundle(bundle) => json!({
+            "timetag": [bundle.timetag.seconds, bundle.timetag.fractional],
+            "content": bundle.content.iter().map(packet_to_json).collect::<Vec<_>>(),
+        }),
+    }
+}
+
+fn arg_to_json(arg: &OscType) -> Value {
+    match arg {
+        OscType::Int(i) => json!({ "i": i }),
+        OscType::Float(f) => json!({ "f": f }),
+        OscType::String(s) => json!({ "s": s }),
+        OscType::Bool(b) => json!({ "b": b }),
+        OscType::Long(h) => json!({ "h": h }),
+        OscType::Double(d) => json!({ "d": d }),
+        OscType::Blob(blob) => json!({ "blob": blob }),
+        OscType::Nil => json!({ "nil": null }),
+        other => json!({ "unsupported": format!("{:?}", other) }),
+    }
+}
+
+fn packet_from_json(value: &Value) -> Result<OscPacket> {
+    if let Some(addr) = value.get("addr").and_then(Value::as_str) {
+        let args = value
+            .get("args")
+            .and_then(Value::as_array)
+            .map(|args| args.iter().map(arg_from_json).collect::<Result<Vec<_>>>())
+            .transpose()?
+            .unwrap_or_default();
+        return Ok(OscPacket::Message(OscMessage {
+            addr: addr.to_string(),
+            args,
+        }));
+    }
+    let timetag = value
+        .get("timetag")
+        .and_then(Value::as_array)
+        .and_then(|t| {
+            Some(OscTime {
+                seconds: t.first()?.as_u64()? as u32,
+                fractional: t.get(1)?.as_u64()? as u32,
+            })
+        })
+        .ok_or_else(|| anyhow::anyhow!("Packet is neither a message nor a bundle: {}", value))?;
+    let content = value
+        .get("content")
+        .and_then(Value::as_array)
+        .map(|content| content.iter().map(packet_from_json).collect::<Result<Vec<_>>>())
+        .transpose()?
+        .unwrap_or_default();
+    Ok(OscPacket::Bundle(OscBundle {
+        timetag,
+        content,
+    }))
+}
+
+fn arg_from_json(value: &Value) -> Result<OscType> {
+    let (kind, inner) = value
+        .as_object()
+        .and_then(|object| object.iter().next())
+        .ok_or_else(|| anyhow::anyhow!("Malformed OSC argument: {}", value))?;
+    let invalid = || anyhow::anyhow!("Malformed OSC argument: {}", value);
+    Ok(match kind.as_str() {
+        "i" => OscType::Int(inner.as_i64().ok_or_else(invalid)? as i32),
+        "f" => OscType::Float(inner.as_f64().ok_or_else(invalid)? as f32),
+        "s" => OscType::String(inner.as_str().ok_or_else(invalid)?.to_string()),
+        "b" => OscType::Bool(inner.as_bool().ok_or_else(invalid)?),
+        "h" => OscType::Long(inner.as_i64().ok_or_else(invalid)?),
+        "d" => OscType::Double(inner.as_f64().ok_or_else(invalid)?),
+        "blob" => OscType::Blob(serde_json::from_value(inner.clone())?),
+        "nil" => OscType::Nil,
+        _ => anyhow::bail!("Unsupported OSC argument in capture: {}", value),
+    })
+}
+
+// Reads a capture written by CaptureWriter, picking the format from the extension
+pub fn read_capture(path: &Path) -> Result<Vec<(Duration, OscPacket)>> {
+    let mut packets = Vec::new();
+    if path.extension().and_then(|e| e.to_str()) == Some(CaptureFormat::Raw.extension()) {
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut header = [0u8; 12];
+        loop {
+            match reader.read_exact(&mut header) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e.into()),
+            }
+            let offset_ms = u64::from_le_bytes(header[..8].try_into()?);
+            let len = u32::from_le_bytes(header[8..].try_into()?) as usize;
+            let mut buf = vec![0u8; len];
+            reader.read_exact(&mut buf)?;
+            let (_, packet) = rosc::decoder::decode_udp(&buf).map_err(|e| anyhow::anyhow!("{:?}", e))?;
+            packets.push((Duration::from_millis(offset_ms), packet));
+        }
+    } else {
+        for (index, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record: Value = serde_json::from_str(&line)
+                .map_err(|e| anyhow::anyhow!("Line {}: {}", index + 1, e))?;
+            let offset_ms = record.get("offset_ms").and_then(Value::as_u64).unwrap_or(0);
+            let packet = record
+                .get("packet")
+                .ok_or_else(|| anyhow::anyhow!("Line {}: missing packet", index + 1))
+                .and_then(packet_from_json)?;
+            packets.push((Duration::from_millis(offset_ms), packet));
+        }
+    }
+    Ok(packets)
+}
+
+// Re-sends a capture to addr, keeping the original spacing unless fast is set
+pub fn replay(path: &Path, ip: &str, port: u16, fast: bool) -> Result<usize> {
+    let packets = read_capture(path)?;
+    let target = OscTarget {
+        name: "Replay".to_string(),
+        ip: ip.to_string(),
+        port,
+        follow_oscquery: false,
+        ..OscTarget::default()
+    };
+    let mut client = OscClient::new(&[target])?;
+    if let Some(e) = client.target_errors().values().next() {
+        anyhow::bail!("Invalid replay target {}:{}: {}", ip, port, e);
+    }
+    info!("Replaying {} packets from {} to {}:{}", packets.len(), path.display(), ip, port);
+    let started = Instant::now();
+    for (offset, packet) in &packets {
+        if !fast {
+            if let Some(wait) = offset.checked_sub(started.elapsed()) {
+                std::thread::sleep(wait);
+            }
+        }
+        client.forward(packet)?;
+    }
+    Ok(packets.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::app::OscOptions;
+    use crate::testing::{temp_dir, OscListener};
+
+    fn message(addr: &str, args: Vec<OscType>) -> OscPacket {
+        OscPacket::Message(OscMessage {
+            addr: addr.to_string(),
+            args,
+        })
+    }
+
+    // A bit of everything a capture has to keep: each argument type, an empty message and a nested bundle
+    fn sample_packets() -> Vec<OscPacket> {
+        vec![
+            message("/chatbox/typing", vec![OscType::Bool(true)]),
+            message(
+                "/chatbox/input",
+                vec![OscType::String("héllo 👋".to_string()), OscType::Bool(true), OscType::Bool(false)],
+            ),
+            message(
+                "/avatar/parameters/Mixed",
+                vec![
+                    OscType::Int(-7),
+                    OscType::Float(0.25),
+                    OscType::Long(1 << 40),
+                    OscType::Double(-1.5),
+                    OscType::Blob(vec![0, 1, 255]),
+                    OscType::Nil,
+                ],
+            ),
+            message("/ping", Vec::new()),
+            OscPacket::Bundle(OscBundle {
+                timetag: OscTime { seconds: 3, fractional: 4 },
+                content: vec![
+                    message("/avatar/parameters/A", vec![OscType::Int(1)]),
+                    OscPacket::Bundle(OscBundle {
+                        timetag: OscTime { seconds: 0, fractional: 1 },
+                        content: vec![message("/avatar/parameters/B", vec![OscType::Float(1.0)])],
+                    }),
+                ],
+            }),
+        ]
+    }
+
+    // Records packets a few milliseconds apart, like a short session would
+    fn record(name: &str, format: CaptureFormat, packets: &[OscPacket]) -> PathBuf {
+        let mut writer = CaptureWriter::create(&temp_dir(name), format).unwrap();
+        for packet in packets {
+            writer.write(packet, &rosc::encoder::encode(packet).unwrap());
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        assert_eq!(writer.packets(), packets.len() as u64);
+        writer.path().to_path_buf()
+    }
+
+    #[test]
+    fn never_reuses_a_capture_file() {
+        let dir = temp_dir("capture-names");
+        let packet = OscPacket::Message(OscMessage {
+            addr: "/chatbox/typing".to_string(),
+            args: vec![OscType::Bool(true)],
+        });
+        let mut first = CaptureWriter::create(&dir, CaptureFormat::JsonLines).unwrap();
+        first.write(&packet, &rosc::encoder::encode(&packet).unwrap());
+        let paths: Vec<_> = (0..3)
+            .map(|_| CaptureWriter::create(&dir, CaptureFormat::JsonLines).unwrap().path().to_path_buf())
+            .collect();
+        assert!(!paths.contains(&first.path().to_path_buf()));
+        assert_ne!(paths[0], paths[1]);
+        assert_ne!(paths[1], paths[2]);
+        assert_eq!(read_capture(first.path()).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn keeps_dry_runs_out_of_the_config() {
+        let options = OscOptions {
+            capture_mode: CaptureMode::DryRun,
+            ..OscOptions::default()
+        };
+        let saved = serde_json::to_value(&options).unwrap();
+        assert_eq!(saved["capture_mode"], "Off");
+        let options = OscOptions {
+            capture_mode: CaptureMode::Record,
+            ..OscOptions::default()
+        };
+        let saved = serde_json::to_value(&options).unwrap();
+        assert_eq!(saved["capture_mode"], "Record");
+        let loaded: OscOptions = serde_json::from_value(json!({ "capture_mode": "DryRun" })).unwrap();
+        assert_eq!(loaded.capture_mode, CaptureMode::Off);
+    }
+
+    #[test]
+    fn round_trips_packets_in_both_formats() {
+        let packets = sample_packets();
+        for format in [CaptureFormat::JsonLines, CaptureFormat::Raw] {
+            let path = record(&format!("capture-round-trip-{}", format.extension()), format, &packets);
+            assert_eq!(path.extension().unwrap(), format.extension());
+            let read = read_capture(&path).unwrap();
+            let (offsets, read): (Vec<Duration>, Vec<OscPacket>) = read.into_iter().unzip();
+            assert_eq!(read, packets, "{:?}", format);
+            assert!(offsets.windows(2).all(|pair| pair[0] < pair[1]), "{:?} {:?}", format, offsets);
+        }
+    }
+
+    #[test]
+    fn reports_a_malformed_line() {
+        let path = temp_dir("capture-malformed").join("capture.jsonl");
+        std::fs::write(&path, "{\"offset_ms\":0,\"packet\":{\"addr\":\"/a\"}}\n\nnot json\n").unwrap();
+        let error = read_capture(&path).unwrap_err().to_string();
+        assert!(error.starts_with("Line 3:"), "{}", error);
+    }
+
+    #[test]
+    fn replays_a_capture_in_order() {
+        let packets = sample_packets();
+        for (format, fast) in [(CaptureFormat::JsonLines, false), (CaptureFormat::Raw, true)] {
+            let path = record(&format!("capture-replay-{}", format.extension()), format, &packets);
+            let recorded = read_capture(&path).unwrap().last().unwrap().0;
+            let listener = OscListener::new();
+            let started = Instant::now();
+            assert_eq!(replay(&path, "127.0.0.1", listener.port(), fast).unwrap(), packets.len());
+            // Without --fast the original spacing is kept
+            assert_eq!(started.elapsed() >= recorded, !fast, "{:?}", format);
+            let received: Vec<_> = std::iter::from_fn(|| listener.recv_packet()).collect();
+            assert_eq!(received, packets, "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn refuses_an_unusable_replay_target() {
+        let path = record("capture-replay-target", CaptureFormat::JsonLines, &sample_packets()[..1]);
+        let error = replay(&path, "127.0.0.1", 0, true).unwrap_err().to_string();
+        assert!(error.starts_with("Invalid replay target 127.0.0.1:0"), "{}", error);
+    }
+}
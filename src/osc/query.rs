@@ -0,0 +1,458 @@
+use std::io::{Read, Write};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use anyhow::Result;
+use log::{debug, error, info};
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+use network_interface::{NetworkInterface, NetworkInterfaceConfig};
+use serde_json::{json, Value};
+
+pub const OSCJSON_SERVICE: &str = "_oscjson._tcp.local.";
+pub const OSC_SERVICE: &str = "_osc._udp.local.";
+const VRCHAT_CLIENT_PREFIX: &str = "VRChat-Client-";
+const HTTP_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiscoveredService {
+    pub name: String,
+    pub ip: IpAddr,
+    pub osc_port: u16,
+}
+
+impl DiscoveredService {
+    pub fn is_vrchat(&self) -> bool {
+        self.name.starts_with(VRCHAT_CLIENT_PREFIX)
+    }
+}
+
+pub struct OscQueryService {
+    name: String,
+    http_port: u16,
+    daemon: ServiceDaemon,
+    running: Arc<AtomicBool>,
+    discovered: Arc<Mutex<Vec<DiscoveredService>>>,
+    http_worker: Option<std::thread::JoinHandle<()>>,
+    browse_worker: Option<std::thread::JoinHandle<()>>,
+}
+
+impl Drop for OscQueryService {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Err(e) = self.daemon.shutdown() {
+            error!("Failed to shut down mDNS daemon: {}", e);
+        }
+        for worker in [self.http_worker.take(), self.browse_worker.take()].into_iter().flatten() {
+            if worker.join().is_err() {
+                error!("OSCQuery worker thread panicked");
+            }
+        }
+    }
+}
+
+impl OscQueryService {
+    pub fn start(osc_port: u16) -> Result<Self> {
+        let name = format!("RustyChatBox-{:04X}", rand::random::<u16>());
+        let listener = TcpListener::bind("0.0.0.0:0")?;
+        listener.set_nonblocking(true)?;
+        let http_port = listener.local_addr()?.port();
+        let running = Arc::new(AtomicBool::new(true));
+
+        let http_worker = {
+            let running = Arc::clone(&running);
+            let name = name.clone();
+            std::thread::spawn(move || serve_http(listener, &name, osc_port, &running))
+        };
+
+        let daemon = ServiceDaemon::new()?;
+        let host_name = format!("{}.local.", name.to_lowercase());
+        for (service_type, port) in [(OSCJSON_SERVICE, http_port), (OSC_SERVICE, osc_port)] {
+            let service = ServiceInfo::new(service_type, &name, &host_name, "", port, None)?.enable_addr_auto();
+            daemon.register(service)?;
+        }
+        info!("Advertising OSCQuery service {} (HTTP {}, OSC {})", name, http_port, osc_port);
+
+        let discovered: Arc<Mutex<Vec<DiscoveredService>>> = Arc::new(Mutex::new(Vec::new()));
+        let browse_worker = {
+            let receiver = daemon.browse(OSCJSON_SERVICE)?;
+            let running = Arc::clone(&running);
+            let discovered = Arc::clone(&discovered);
+            let own_name = name.clone();
+            std::thread::spawn(move || {
+                while running.load(Ordering::Relaxed) {
+                    let event = match receiver.recv_timeout(Duration::from_millis(250)) {
+                        Ok(event) => event,
+                        Err(_) if receiver.is_disconnected() => break,
+                        Err(_) => continue,
+                    };
+                    record_event(&own_name, &discovered, event);
+                }
+            })
+        };
+
+        Ok(Self {
+            name,
+            http_port,
+            daemon,
+            running,
+            discovered,
+            http_worker: Some(http_worker),
+            browse_worker: Some(browse_worker),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn http_port(&self) -> u16 {
+        self.http_port
+    }
+
+    pub fn discovered(&self) -> Vec<DiscoveredService> {
+        self.discovered.lock().unwrap().clone()
+    }
+
+    // Handles an mDNS event as if the browser had received it, so tests don't need multicast
+    #[cfg(test)]
+    pub(crate) fn announce(&self, event: ServiceEvent) {
+        record_event(&self.name, &self.discovered, event);
+    }
+
+    // Prefer a client on this machine over a standalone headset on the LAN
+    pub fn vrchat_client(&self) -> Option<DiscoveredService> {
+        pick_vrchat_client(&self.discovered.lock().unwrap())
+    }
+}
+
+fn pick_vrchat_client(discovered: &[DiscoveredService]) -> Option<DiscoveredService> {
+    let clients: Vec<&DiscoveredService> = discovered.iter().filter(|s| s.is_vrchat()).collect();
+    clients
+        .iter()
+        .find(|s| s.ip.is_loopback())
+        .or(clients.first())
+        .map(|s| (*s).clone())
+}
+
+// Keeps the discovered list in step with what the mDNS browser sees
+fn record_event(own_name: &str, discovered: &Mutex<Vec<DiscoveredService>>, event: ServiceEvent) {
+    match event {
+        ServiceEvent::ServiceResolved(info) => {
+            let instance = instance_name(info.get_fullname());
+            if instance == own_name {
+                return;
+            }
+            let Some(ip) = info.get_addresses_v4().into_iter().next().map(|ip| IpAddr::V4(*ip)) else {
+                return;
+            };
+            match fetch_host_info(SocketAddr::new(ip, info.get_port())) {
+                Ok(host_info) => {
+                    if let Some(service) = service_from_host_info(&instance, ip, &host_info) {
+                        info!("Discovered OSCQuery service {} at {}:{}", service.name, service.ip, service.osc_port);
+                        let mut discovered = discovered.lock().unwrap();
+                        discovered.retain(|s| s.name != service.name);
+                        discovered.push(service);
+                    }
+                }
+                Err(e) => debug!("Failed to query host info of {}: {}", instance, e),
+            }
+        }
+        ServiceEvent::ServiceRemoved(_, fullname) => {
+            let instance = instance_name(&fullname);
+            debug!("OSCQuery service {} went away", instance);
+            discovered.lock().unwrap().retain(|s| s.name != instance);
+        }
+        _ => {}
+    }
+}
+
+fn instance_name(fullname: &str) -> String {
+    fullname
+        .strip_suffix(OSCJSON_SERVICE)
+        .unwrap_or(fullname)
+        .trim_end_matches('.')
+        .to_string()
+}
+
+fn service_from_host_info(name: &str, ip: IpAddr, host_info: &Value) -> Option<DiscoveredService> {
+    let osc_port = host_info.get("OSC_PORT")?.as_u64()? as u16;
+    // Services usually report a wildcard or loopback OSC_IP, so only trust it
+    // for services running on this machine and use the mDNS address otherwise
+    let ip = if is_local_address(ip) {
+        IpAddr::V4(Ipv4Addr::LOCALHOST)
+    } else {
+        host_info
+            .get("OSC_IP")
+            .and_then(Value::as_str)
+            .and_then(|ip| ip.parse::<IpAddr>().ok())
+            .filter(|reported| !reported.is_unspecified() && !reported.is_loopback())
+            .unwrap_or(ip)
+    };
+    Some(DiscoveredService {
+        name: name.to_string(),
+        ip,
+        osc_port,
+    })
+}
+
+fn is_local_address(ip: IpAddr) -> bool {
+    ip.is_loopback()
+        || NetworkInterface::show()
+            .map(|interfaces| interfaces.iter().any(|iface| iface.addr.iter().any(|addr| addr.ip() == ip)))
+            .unwrap_or(false)
+}
+
+pub fn fetch_host_info(addr: SocketAddr) -> Result<Value> {
+    let mut stream = TcpStream::connect_timeout(&addr, HTTP_TIMEOUT)?;
+    stream.set_read_timeout(Some(HTTP_TIMEOUT))?;
+    // One write, so a server that reads the request in a single call sees all of it
+    let request = format!("GET /?HOST_INFO HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n", addr);
+    stream.write_all(request.as_bytes())?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    let (head, body) = response
+        .split_once("\r\n\r\n")
+        .ok_or_else(|| anyhow::anyhow!("Malformed HTTP response from {}", addr))?;
+    if !head.starts_with("HTTP/1.1 200") && !head.starts_with("HTTP/1.0 200") {
+        anyhow::bail!("Unexpected HTTP status from {}: {}", addr, head.lines().next().unwrap_or_default());
+    }
+    Ok(serde_json::from_str(body)?)
+}
+
+fn serve_http(listener: TcpListener, name: &str, osc_port: u16, running: &AtomicBool) {
+    while running.load(Ordering::Relaxed) {
+        match listener.accept() {
+            Ok((stream, peer)) => {
+                if let Err(e) = handle_http(stream, name, osc_port) {
+                    debug!("OSCQuery HTTP request from {} failed: {}", peer, e);
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                std::thread::sleep(Duration::from_millis(100));
+            }
+            Err(e) => {
+                error!("OSCQuery HTTP accept error: {}", e);
+                std::thread::sleep(Duration::from_millis(100));
+            }
+        }
+    }
+}
+
+fn handle_http(mut stream: TcpStream, name: &str, osc_port: u16) -> Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(HTTP_TIMEOUT))?;
+    let mut buf = [0u8; 2048];
+    let size = stream.read(&mut buf)?;
+    let request = String::from_utf8_lossy(&buf[..size]);
+    let target = request
+        .lines()
+        .next()
+        .and_then(|line| line.strip_prefix("GET "))
+        .and_then(|rest| rest.split_whitespace().next())
+        .unwrap_or_default();
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+
+    let body = if query == "HOST_INFO" {
+        Some(host_info(name, osc_port))
+    } else {
+        find_node(&oscquery_tree(), if path.is_empty() { "/" } else { path })
+    };
+    let (status, body) = match body {
+        Some(body) => ("200 OK", body.to_string()),
+        None => ("404 Not Found", "{}".to_string()),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    Ok(())
+}
+
+fn host_info(name: &str, osc_port: u16) -> Value {
+    json!({
+        "NAME": name,
+        "EXTENSIONS": {
+            "ACCESS": true,
+            "CLIPMODE": false,
+            "RANGE": true,
+            "TYPE": true,
+            "VALUE": true,
+        },
+        "OSC_IP": Ipv4Addr::LOCALHOST.to_string(),
+        "OSC_PORT": osc_port,
+        "OSC_TRANSPORT": "UDP",
+    })
+}
+
+// Advertising /avatar/change is what makes VRChat forward avatar data to us
+fn oscquery_tree() -> Value {
+    json!({
+        "DESCRIPTION": "root node",
+        "FULL_PATH": "/",
+        "ACCESS": 0,
+        "CONTENTS": {
+            "avatar": {
+                "FULL_PATH": "/avatar",
+                "ACCESS": 0,
+                "CONTENTS": {
+                    "change": {
+                        "FULL_PATH": "/avatar/change",
+                        "ACCESS": 3,
+                        "TYPE": "s",
+                    },
+                    "parameters": {
+                        "FULL_PATH": "/avatar/parameters",
+                        "ACCESS": 0,
+                    },
+                },
+            },
+        },
+    })
+}
+
+fn find_node(root: &Value, path: &str) -> Option<Value> {
+    let mut node = root;
+    for segment in path.split('/').filter(|s| !s.is_empty()) {
+        node = node.get("CONTENTS")?.get(segment)?;
+    }
+    Some(node.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{fake_http_server, fake_vrchat, vrchat_announcement};
+
+    const OWN_NAME: &str = "RustyChatBox-TEST";
+
+    // Our HTTP handler on a port of its own, without the mDNS daemon
+    fn serve(osc_port: u16) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let _ = handle_http(stream, OWN_NAME, osc_port);
+            }
+        });
+        port
+    }
+
+    // Status line and body of a GET against our own server
+    fn get(port: u16, target: &str) -> (String, Value) {
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        let request = format!("GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n", target);
+        stream.write_all(request.as_bytes()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        (head.lines().next().unwrap().to_string(), serde_json::from_str(body).unwrap())
+    }
+
+    fn discover(events: impl IntoIterator<Item = ServiceEvent>) -> Vec<DiscoveredService> {
+        let discovered = Mutex::new(Vec::new());
+        for event in events {
+            record_event(OWN_NAME, &discovered, event);
+        }
+        discovered.into_inner().unwrap()
+    }
+
+    fn service(name: &str, ip: Ipv4Addr) -> DiscoveredService {
+        DiscoveredService {
+            name: name.to_string(),
+            ip: IpAddr::V4(ip),
+            osc_port: 9000,
+        }
+    }
+
+    #[test]
+    fn fetches_host_info() {
+        let addr = fake_http_server(json!({ "NAME": "VRChat-Client-1A2B3C", "OSC_IP": "127.0.0.1", "OSC_PORT": 9000 }));
+        let host_info = fetch_host_info(addr).unwrap();
+        assert_eq!(host_info["OSC_PORT"], 9000);
+        let service = service_from_host_info("VRChat-Client-1A2B3C", addr.ip(), &host_info).unwrap();
+        assert!(service.is_vrchat());
+        assert_eq!(service.ip, IpAddr::V4(Ipv4Addr::LOCALHOST));
+        assert_eq!(service.osc_port, 9000);
+    }
+
+    #[test]
+    fn ignores_host_info_without_an_osc_port() {
+        let addr = fake_http_server(json!({ "NAME": "Something" }));
+        let host_info = fetch_host_info(addr).unwrap();
+        assert_eq!(service_from_host_info("Something", addr.ip(), &host_info), None);
+    }
+
+    #[test]
+    fn serves_host_info_and_the_tree() {
+        let port = serve(9123);
+        let host_info = fetch_host_info(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port)).unwrap();
+        assert_eq!(host_info["NAME"], OWN_NAME);
+        assert_eq!(host_info["OSC_PORT"], 9123);
+        assert_eq!(host_info["OSC_TRANSPORT"], "UDP");
+
+        let (status, root) = get(port, "/");
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        assert_eq!(root["FULL_PATH"], "/");
+        let (_, change) = get(port, "/avatar/change");
+        assert_eq!(change["FULL_PATH"], "/avatar/change");
+        assert_eq!(change["TYPE"], "s");
+        let (status, _) = get(port, "/avatar/missing");
+        assert_eq!(status, "HTTP/1.1 404 Not Found");
+    }
+
+    #[test]
+    fn discovers_an_announced_vrchat_client() {
+        let discovered = discover([fake_vrchat("VRChat-Client-ABCDEF", 9555)]);
+        let client = pick_vrchat_client(&discovered).expect("the announced client is discovered");
+        assert_eq!(client.name, "VRChat-Client-ABCDEF");
+        assert_eq!(client.ip, IpAddr::V4(Ipv4Addr::LOCALHOST));
+        assert_eq!(client.osc_port, 9555);
+
+        // Re-announcing replaces the entry instead of adding another
+        let discovered = discover([fake_vrchat("VRChat-Client-ABCDEF", 9555), fake_vrchat("VRChat-Client-ABCDEF", 9556)]);
+        assert_eq!(discovered.len(), 1);
+        assert_eq!(discovered[0].osc_port, 9556);
+
+        let fullname = format!("VRChat-Client-ABCDEF.{}", OSCJSON_SERVICE);
+        let removed = ServiceEvent::ServiceRemoved(OSCJSON_SERVICE.to_string(), fullname);
+        assert_eq!(discover([fake_vrchat("VRChat-Client-ABCDEF", 9555), removed]), []);
+    }
+
+    #[test]
+    fn skips_itself_and_other_apps() {
+        let own = fake_http_server(json!({ "NAME": OWN_NAME, "OSC_PORT": 9125 }));
+        let discovered = discover([vrchat_announcement(OWN_NAME, own), fake_vrchat("VRCFaceTracking", 9400)]);
+        assert_eq!(discovered.len(), 1);
+        assert_eq!(pick_vrchat_client(&discovered), None);
+    }
+
+    #[test]
+    fn ignores_clients_that_dont_answer() {
+        let closed = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        assert_eq!(discover([vrchat_announcement("VRChat-Client-000000", closed)]), []);
+    }
+
+    #[test]
+    fn prefers_a_client_on_this_machine() {
+        let headset = service("VRChat-Client-111111", Ipv4Addr::new(192, 168, 1, 50));
+        let desktop = service("VRChat-Client-222222", Ipv4Addr::LOCALHOST);
+        let other = service("VRCFaceTracking", Ipv4Addr::LOCALHOST);
+        assert_eq!(pick_vrchat_client(&[headset.clone(), other.clone(), desktop.clone()]), Some(desktop));
+        assert_eq!(pick_vrchat_client(&[other, headset.clone()]), Some(headset));
+    }
+
+    #[test]
+    #[ignore = "registers with mDNS, which needs multicast"]
+    fn advertises_and_serves_over_mdns() {
+        let service = OscQueryService::start(9124).unwrap();
+        let host_info = fetch_host_info(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), service.http_port())).unwrap();
+        assert_eq!(host_info["NAME"], service.name());
+        assert_eq!(service.vrchat_client(), None);
+        service.announce(fake_vrchat("VRChat-Client-ABCDEF", 9555));
+        assert_eq!(service.vrchat_client().unwrap().osc_port, 9555);
+    }
+}
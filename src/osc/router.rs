@@ -0,0 +1,316 @@
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::sync::Arc;
+use std::time::Duration;
+use log::{debug, error, info};
+use rosc::{OscBundle, OscPacket};
+use crate::modules::router::RouterOptions;
+use super::{OscClient, OscServer};
+
+#[derive(Default)]
+struct RouteCounters {
+    forwarded: AtomicU64,
+    filtered: AtomicU64,
+    errors: AtomicU64,
+}
+
+#[derive(Debug, Clone)]
+pub struct RouteStats {
+    pub name: String,
+    pub forwarded: u64,
+    pub filtered: u64,
+    pub errors: u64,
+    pub error: Option<String>,
+}
+
+struct Route {
+    name: String,
+    prefixes: Vec<String>,
+    client: OscClient,
+    counters: Arc<RouteCounters>,
+}
+
+pub struct OscRouter {
+    routes: Vec<(String, Arc<RouteCounters>)>,
+    route_errors: Vec<(String, String)>,
+    running: Arc<AtomicBool>,
+    worker: Option<std::thread::JoinHandle<()>>,
+}
+
+impl Drop for OscRouter {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(worker) = self.worker.take() {
+            if worker.join().is_err() {
+                error!("OSC router thread panicked");
+            }
+        }
+    }
+}
+
+impl OscRouter {
+    pub fn start(server: &OscServer, options: &RouterOptions) -> Self {
+        let mut routes = Vec::new();
+        let mut route_errors = Vec::new();
+        for route in options.routes.iter().filter(|route| route.enabled) {
+            // Forwarding to ourselves would bounce every packet forever
+            if route.port == server.port() && is_loopback(&route.ip) {
+                route_errors.push((route.name.clone(), "Route points back at RustyChatBox".to_string()));
+                continue;
+            }
+            match OscClient::new(&[route.target()]) {
+                Ok(client) => {
+                    if let Some(e) = client.target_errors().get(&route.name) {
+                        route_errors.push((route.name.clone(), e.clone()));
+                        continue;
+                    }
+                    routes.push(Route {
+                        name: route.name.clone(),
+                        prefixes: route.prefix_list(),
+                        client,
+                        counters: Arc::new(RouteCounters::default()),
+                    });
+                }
+                Err(e) => route_errors.push((route.name.clone(), e.to_string())),
+            }
+        }
+        for (name, e) in &route_errors {
+            error!("Skipping OSC route {}: {}", name, e);
+        }
+
+        let counters = routes.iter().map(|route| (route.name.clone(), Arc::clone(&route.counters))).collect();
+        let running = Arc::new(AtomicBool::new(true));
+        let packets = server.subscribe_raw();
+        let worker = {
+            let running = Arc::clone(&running);
+            std::thread::spawn(move || route_packets(packets, routes, &running))
+        };
+        info!("OSC router forwarding from port {}", server.port());
+
+        Self {
+            routes: counters,
+            route_errors,
+            running,
+            worker: Some(worker),
+        }
+    }
+
+    pub fn stats(&self) -> Vec<RouteStats> {
+        let mut stats: Vec<RouteStats> = self
+            .routes
+            .iter()
+            .map(|(name, counters)| RouteStats {
+                name: name.clone(),
+                forwarded: counters.forwarded.load(Ordering::Relaxed),
+                filtered: counters.filtered.load(Ordering::Relaxed),
+                errors: counters.errors.load(Ordering::Relaxed),
+                error: None,
+            })
+            .collect();
+        stats.extend(self.route_errors.iter().map(|(name, e)| RouteStats {
+            name: name.clone(),
+            forwarded: 0,
+            filtered: 0,
+            errors: 0,
+            error: Some(e.clone()),
+        }));
+        stats
+    }
+}
+
+fn route_packets(packets: Receiver<OscPacket>, mut routes: Vec<Route>, running: &AtomicBool) {
+    while running.load(Ordering::Relaxed) {
+        let packet = match packets.recv_timeout(Duration::from_millis(250)) {
+            Ok(packet) => packet,
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+        for route in &mut routes {
+            let Some(packet) = filter_packet(&packet, &route.prefixes) else {
+                route.counters.filtered.fetch_add(1, Ordering::Relaxed);
+                continue;
+            };
+            match route.client.forward(&packet) {
+                Ok(()) => {
+                    route.counters.forwarded.fetch_add(1, Ordering::Relaxed);
+                }
+                Err(e) => {
+                    route.counters.errors.fetch_add(1, Ordering::Relaxed);
+                    debug!("Failed to forward OSC to {}: {}", route.name, e);
+                }
+            }
+        }
+    }
+    debug!("OSC router stopped");
+}
+
+// Keeps only the messages a route asked for, dropping bundles that end up empty
+fn filter_packet(packet: &OscPacket, prefixes: &[String]) -> Option<OscPacket> {
+    if prefixes.is_empty() {
+        return Some(packet.clone());
+    }
+    match packet {
+        OscPacket::Message(message) => prefixes
+            .iter()
+            .any(|prefix| message.addr.starts_with(prefix.as_str()))
+            .then(|| packet.clone()),
+        OscPacket::Bundle(bundle) => {
+            let content: Vec<OscPacket> = bundle
+                .content
+                .iter()
+                .filter_map(|packet| filter_packet(packet, prefixes))
+                .collect();
+            (!content.is_empty()).then_some(OscPacket::Bundle(OscBundle {
+                timetag: bundle.timetag,
+                content,
+            }))
+        }
+    }
+}
+
+fn is_loopback(ip: &str) -> bool {
+    let ip = ip.trim();
+    ip == "localhost" || ip.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback() || ip.is_unspecified())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rosc::{OscMessage, OscTime, OscType};
+    use std::net::UdpSocket;
+    use std::time::Instant;
+    use crate::modules::router::OscRoute;
+    use crate::testing::OscListener;
+
+    const TIMETAG: OscTime = OscTime { seconds: 1, fractional: 2 };
+
+    fn message(addr: &str) -> OscPacket {
+        OscPacket::Message(OscMessage {
+            addr: addr.to_string(),
+            args: vec![OscType::Int(1)],
+        })
+    }
+
+    fn bundle(content: Vec<OscPacket>) -> OscPacket {
+        OscPacket::Bundle(OscBundle {
+            timetag: TIMETAG,
+            content,
+        })
+    }
+
+    fn prefixes(prefixes: &[&str]) -> Vec<String> {
+        prefixes.iter().map(|prefix| prefix.to_string()).collect()
+    }
+
+    #[test]
+    fn forwards_everything_without_prefixes() {
+        let packet = bundle(vec![message("/tracking/head"), message("/avatar/change")]);
+        assert_eq!(filter_packet(&packet, &[]), Some(packet));
+    }
+
+    #[test]
+    fn keeps_messages_matching_a_prefix() {
+        let prefixes = prefixes(&["/avatar/parameters/", "/tracking/"]);
+        assert_eq!(filter_packet(&message("/avatar/parameters/Muted"), &prefixes), Some(message("/avatar/parameters/Muted")));
+        assert_eq!(filter_packet(&message("/tracking/head"), &prefixes), Some(message("/tracking/head")));
+        assert_eq!(filter_packet(&message("/avatar/change"), &prefixes), None);
+    }
+
+    #[test]
+    fn rebuilds_nested_bundles() {
+        let packet = bundle(vec![
+            message("/avatar/parameters/Speed"),
+            message("/avatar/change"),
+            bundle(vec![message("/chatbox/input"), message("/avatar/parameters/Count")]),
+            bundle(vec![message("/chatbox/typing")]),
+        ]);
+        let expected = bundle(vec![
+            message("/avatar/parameters/Speed"),
+            bundle(vec![message("/avatar/parameters/Count")]),
+        ]);
+        assert_eq!(filter_packet(&packet, &prefixes(&["/avatar/parameters/"])), Some(expected));
+    }
+
+    #[test]
+    fn drops_bundles_left_empty() {
+        let packet = bundle(vec![message("/avatar/change"), bundle(vec![message("/chatbox/input")])]);
+        assert_eq!(filter_packet(&packet, &prefixes(&["/avatar/parameters/"])), None);
+        assert_eq!(filter_packet(&bundle(Vec::new()), &prefixes(&["/"])), None);
+    }
+
+    #[test]
+    fn recognises_addresses_of_this_machine() {
+        for ip in ["127.0.0.1", " localhost ", "0.0.0.0", "::1", "127.0.0.2"] {
+            assert!(is_loopback(ip), "{}", ip);
+        }
+        for ip in ["192.168.1.20", "example.com", ""] {
+            assert!(!is_loopback(ip), "{}", ip);
+        }
+    }
+
+    #[test]
+    fn forwards_received_packets_per_route() {
+        let server = OscServer::start("127.0.0.1", 0).unwrap();
+        let face = OscListener::new();
+        let everything = OscListener::new();
+        let route = |name: &str, listener: &OscListener, prefixes: &str| OscRoute {
+            name: name.to_string(),
+            port: listener.port(),
+            prefixes: prefixes.to_string(),
+            ..OscRoute::default()
+        };
+        let options = RouterOptions {
+            enabled: true,
+            routes: vec![
+                route("Face", &face, "/avatar/parameters/"),
+                route("Everything", &everything, ""),
+                OscRoute {
+                    name: "Loop".to_string(),
+                    port: server.port(),
+                    ..OscRoute::default()
+                },
+                OscRoute {
+                    enabled: false,
+                    ..route("Off", &face, "")
+                },
+            ],
+        };
+        let router = OscRouter::start(&server, &options);
+
+        let vrchat = UdpSocket::bind("127.0.0.1:0").unwrap();
+        for addr in ["/avatar/parameters/Muted", "/tracking/head"] {
+            let buf = rosc::encoder::encode(&message(addr)).unwrap();
+            vrchat.send_to(&buf, ("127.0.0.1", server.port())).unwrap();
+        }
+
+        assert_eq!(face.recv().map(|m| m.addr).as_deref(), Some("/avatar/parameters/Muted"));
+        assert!(face.recv().is_none());
+        assert_eq!(everything.recv().map(|m| m.addr).as_deref(), Some("/avatar/parameters/Muted"));
+        assert_eq!(everything.recv().map(|m| m.addr).as_deref(), Some("/tracking/head"));
+
+        // Counters are bumped right after the send, so give the router a moment to catch up
+        let started = Instant::now();
+        let counts = loop {
+            let counts: Vec<_> = router
+                .stats()
+                .into_iter()
+                .map(|stats| (stats.name, stats.forwarded, stats.filtered, stats.error.is_some()))
+                .collect();
+            if counts[..2].iter().all(|(_, forwarded, filtered, _)| forwarded + filtered == 2)
+                || started.elapsed() > Duration::from_secs(2)
+            {
+                break counts;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        };
+        assert_eq!(
+            counts,
+            [
+                ("Face".to_string(), 1, 1, false),
+                ("Everything".to_string(), 2, 0, false),
+                ("Loop".to_string(), 0, 0, true),
+            ]
+        );
+    }
+}
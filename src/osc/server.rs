@@ -0,0 +1,266 @@
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use anyhow::Result;
+use log::{debug, error, info};
+use rosc::{OscMessage, OscPacket, OscType};
+
+const AVATAR_CHANGE: &str = "/avatar/change";
+const AVATAR_PARAMETERS_PREFIX: &str = "/avatar/parameters/";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OscEvent {
+    AvatarChange(String),
+    AvatarParameter { name: String, value: OscType },
+}
+
+pub struct OscServer {
+    port: u16,
+    subscribers: Arc<Mutex<Vec<Sender<OscEvent>>>>,
+    raw_subscribers: Arc<Mutex<Vec<Sender<OscPacket>>>>,
+    last_received: Arc<Mutex<Option<(Instant, SocketAddr)>>>,
+    running: Arc<AtomicBool>,
+    worker: Option<std::thread::JoinHandle<()>>,
+}
+
+impl Drop for OscServer {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(worker) = self.worker.take() {
+            debug!("Joining OSC server thread");
+            if worker.join().is_err() {
+                error!("OSC server thread panicked");
+            }
+        }
+    }
+}
+
+impl OscServer {
+    pub fn start(ip: &str, port: u16) -> Result<Self> {
+        let socket = UdpSocket::bind(format!("{}:{}", ip, port))?;
+        // Wake up periodically so the thread notices when the server is dropped
+        socket.set_read_timeout(Some(Duration::from_millis(250)))?;
+        let port = socket.local_addr()?.port();
+        let subscribers: Arc<Mutex<Vec<Sender<OscEvent>>>> = Arc::new(Mutex::new(Vec::new()));
+        let raw_subscribers: Arc<Mutex<Vec<Sender<OscPacket>>>> = Arc::new(Mutex::new(Vec::new()));
+        let last_received = Arc::new(Mutex::new(None));
+        let running = Arc::new(AtomicBool::new(true));
+
+        let worker = {
+            let subscribers = Arc::clone(&subscribers);
+            let raw_subscribers = Arc::clone(&raw_subscribers);
+            let last_received = Arc::clone(&last_received);
+            let running = Arc::clone(&running);
+            std::thread::spawn(move || {
+                info!("OSC server listening on port {}", port);
+                let mut buf = [0u8; rosc::decoder::MTU];
+                while running.load(Ordering::Relaxed) {
+                    let size = match socket.recv_from(&mut buf) {
+                        Ok((size, peer)) => {
+                            *last_received.lock().unwrap() = Some((Instant::now(), peer));
+                            size
+                        }
+                        // Interrupted covers the stop signal in headless mode landing on this thread
+                        Err(e) if matches!(
+                            e.kind(),
+                            std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut | std::io::ErrorKind::Interrupted
+                        ) => continue,
+                        Err(e) => {
+                            error!("OSC server receive error: {}", e);
+                            continue;
+                        }
+                    };
+                    match rosc::decoder::decode_udp(&buf[..size]) {
+                        Ok((_, packet)) => {
+                            {
+                                let mut raw_subscribers = raw_subscribers.lock().unwrap();
+                                if !raw_subscribers.is_empty() {
+                                    raw_subscribers.retain(|s| s.send(packet.clone()).is_ok());
+                                }
+                            }
+                            let mut events = Vec::new();
+                            collect_events(packet, &mut events);
+                            if !events.is_empty() {
+                                let mut subscribers = subscribers.lock().unwrap();
+                                for event in events {
+                                    subscribers.retain(|s| s.send(event.clone()).is_ok());
+                                }
+                            }
+                        }
+                        Err(e) => debug!("Ignoring undecodable OSC packet: {:?}", e),
+                    }
+                }
+                info!("OSC server on port {} stopped", port);
+            })
+        };
+
+        Ok(Self {
+            port,
+            subscribers,
+            raw_subscribers,
+            last_received,
+            running,
+            worker: Some(worker),
+        })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    pub fn last_received(&self) -> Option<(Instant, SocketAddr)> {
+        *self.last_received.lock().unwrap()
+    }
+
+    pub fn subscribe(&self) -> Receiver<OscEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribers.lock().unwrap().push(sender);
+        receiver
+    }
+
+    // Every decoded packet as received, for consumers that care about more than avatar events
+    pub fn subscribe_raw(&self) -> Receiver<OscPacket> {
+        let (sender, receiver) = mpsc::channel();
+        self.raw_subscribers.lock().unwrap().push(sender);
+        receiver
+    }
+}
+
+fn collect_events(packet: OscPacket, events: &mut Vec<OscEvent>) {
+    match packet {
+        OscPacket::Message(message) => {
+            if let Some(event) = message_to_event(message) {
+                events.push(event);
+            }
+        }
+        OscPacket::Bundle(bundle) => {
+            for packet in bundle.content {
+                collect_events(packet, events);
+            }
+        }
+    }
+}
+
+fn message_to_event(message: OscMessage) -> Option<OscEvent> {
+    let value = message.args.into_iter().next()?;
+    if message.addr == AVATAR_CHANGE {
+        match value {
+            OscType::String(id) => Some(OscEvent::AvatarChange(id)),
+            _ => None,
+        }
+    } else {
+        message
+            .addr
+            .strip_prefix(AVATAR_PARAMETERS_PREFIX)
+            .map(|name| OscEvent::AvatarParameter {
+                name: name.to_string(),
+                value,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rosc::{OscBundle, OscTime};
+    use std::sync::mpsc::RecvTimeoutError;
+
+    const TIMEOUT: Duration = Duration::from_secs(2);
+
+    fn message(addr: &str, value: OscType) -> OscPacket {
+        OscPacket::Message(OscMessage {
+            addr: addr.to_string(),
+            args: vec![value],
+        })
+    }
+
+    // Sends packet to the server the way VRChat would, from a socket of its own
+    fn send(server: &OscServer, packet: &OscPacket) -> UdpSocket {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.send_to(&rosc::encoder::encode(packet).unwrap(), ("127.0.0.1", server.port())).unwrap();
+        socket
+    }
+
+    #[test]
+    fn delivers_avatar_events() {
+        let server = OscServer::start("127.0.0.1", 0).unwrap();
+        let events = server.subscribe();
+        assert!(server.last_received().is_none());
+
+        let vrchat = send(&server, &message("/avatar/change", OscType::String("avtr_1234".to_string())));
+        assert_eq!(events.recv_timeout(TIMEOUT).unwrap(), OscEvent::AvatarChange("avtr_1234".to_string()));
+        assert_eq!(server.last_received().map(|(_, peer)| peer), Some(vrchat.local_addr().unwrap()));
+
+        send(&server, &message("/avatar/parameters/Muted", OscType::Bool(true)));
+        assert_eq!(
+            events.recv_timeout(TIMEOUT).unwrap(),
+            OscEvent::AvatarParameter {
+                name: "Muted".to_string(),
+                value: OscType::Bool(true),
+            }
+        );
+    }
+
+    #[test]
+    fn unpacks_bundles_and_skips_other_addresses() {
+        let server = OscServer::start("127.0.0.1", 0).unwrap();
+        let events = server.subscribe();
+        let raw = server.subscribe_raw();
+        let bundle = OscPacket::Bundle(OscBundle {
+            timetag: OscTime { seconds: 0, fractional: 1 },
+            content: vec![
+                message("/avatar/parameters/Speed", OscType::Float(0.5)),
+                message("/tracking/vrsystem/head/pose", OscType::Float(1.0)),
+                OscPacket::Bundle(OscBundle {
+                    timetag: OscTime { seconds: 0, fractional: 1 },
+                    content: vec![message("/avatar/parameters/Count", OscType::Int(3))],
+                }),
+            ],
+        });
+        send(&server, &bundle);
+
+        assert_eq!(raw.recv_timeout(TIMEOUT).unwrap(), bundle);
+        let received: Vec<_> = std::iter::from_fn(|| events.recv_timeout(Duration::from_millis(200)).ok()).collect();
+        assert_eq!(
+            received,
+            [
+                OscEvent::AvatarParameter {
+                    name: "Speed".to_string(),
+                    value: OscType::Float(0.5),
+                },
+                OscEvent::AvatarParameter {
+                    name: "Count".to_string(),
+                    value: OscType::Int(3),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn delivers_to_every_subscriber() {
+        let server = OscServer::start("127.0.0.1", 0).unwrap();
+        let first = server.subscribe();
+        let second = server.subscribe();
+        // A subscriber that went away doesn't stop the others
+        drop(server.subscribe());
+        send(&server, &message("/avatar/change", OscType::String("avtr_5678".to_string())));
+        for events in [first, second] {
+            assert_eq!(events.recv_timeout(TIMEOUT).unwrap(), OscEvent::AvatarChange("avtr_5678".to_string()));
+        }
+    }
+
+    #[test]
+    fn stops_on_drop() {
+        let server = OscServer::start("127.0.0.1", 0).unwrap();
+        let port = server.port();
+        let events = server.subscribe();
+        let raw = server.subscribe_raw();
+        drop(server);
+        assert_eq!(events.recv_timeout(TIMEOUT), Err(RecvTimeoutError::Disconnected));
+        assert_eq!(raw.recv_timeout(TIMEOUT), Err(RecvTimeoutError::Disconnected));
+        // The socket is closed, so the port can be bound again
+        UdpSocket::bind(("127.0.0.1", port)).unwrap();
+    }
+}
@@ -0,0 +1,278 @@
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+use log::{debug, error, warn};
+use serde::Serialize;
+
+// Changes are written once editing has paused for SAVE_DELAY, or after MAX_SAVE_DELAY
+// at the latest so a setting that keeps changing still reaches the disk
+const SAVE_DELAY: Duration = Duration::from_secs(1);
+const MAX_SAVE_DELAY: Duration = Duration::from_secs(5);
+// A save that failed isn't tried again before this, so a full disk isn't hit every frame
+const SAVE_RETRY: Duration = Duration::from_secs(10);
+// How often the file is checked for edits made outside the app
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+// Modification time and size, enough to tell an outside edit from the last known state
+type FileStamp = (SystemTime, u64);
+
+fn file_stamp(path: &Path) -> Option<FileStamp> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+// Writes the config to disk, batching bursts of changes into one write, and notices
+// when something else changed the file
+pub struct Persister {
+    path: PathBuf,
+    first_change: Option<Instant>,
+    last_change: Option<Instant>,
+    retry_at: Option<Instant>,
+    // The file as last loaded or written by us
+    stamp: Option<FileStamp>,
+    last_poll: Instant,
+    // Set while the file on disk can't be loaded, so our settings don't overwrite it
+    held: bool,
+}
+
+impl Persister {
+    pub fn new(path: PathBuf) -> Self {
+        Persister {
+            stamp: file_stamp(&path),
+            path,
+            first_change: None,
+            last_change: None,
+            retry_at: None,
+            last_poll: Instant::now(),
+            held: false,
+        }
+    }
+
+    // Call right before loading from the new path, so the loaded state is the known one
+    pub fn set_path(&mut self, path: PathBuf) {
+        self.stamp = file_stamp(&path);
+        self.path = path;
+        self.held = false;
+    }
+
+    pub fn mark_changed(&mut self) {
+        let now = Instant::now();
+        self.first_change.get_or_insert(now);
+        self.last_change = Some(now);
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.first_change.is_some()
+    }
+
+    pub fn discard_pending(&mut self) {
+        self.first_change = None;
+        self.last_change = None;
+        self.retry_at = None;
+    }
+
+    pub fn set_held(&mut self, held: bool) {
+        self.held = held;
+    }
+
+    // True once per outside change to the file. Our own writes update the stamp, so they
+    // never show up here.
+    pub fn poll_external_change(&mut self) -> bool {
+        if self.last_poll.elapsed() < WATCH_INTERVAL {
+            return false;
+        }
+        self.last_poll = Instant::now();
+        let stamp = file_stamp(&self.path);
+        // A deleted file is left alone; the next save recreates it
+        if stamp.is_none() || stamp == self.stamp {
+            return false;
+        }
+        self.stamp = stamp;
+        true
+    }
+
+    pub fn is_due(&self) -> bool {
+        if self.held || self.retry_at.is_some_and(|at| Instant::now() < at) {
+            return false;
+        }
+        match (self.first_change, self.last_change) {
+            (Some(first), Some(last)) => last.elapsed() >= SAVE_DELAY || first.elapsed() >= MAX_SAVE_DELAY,
+            _ => false,
+        }
+    }
+
+    pub fn save<T: Serialize>(&mut self, value: &T) {
+        if self.held {
+            warn!("Not saving to {} until it loads again", self.path.display());
+            return;
+        }
+        debug!("Saving configuration to {}", self.path.display());
+        match write_atomic(&self.path, value) {
+            Ok(()) => {
+                self.discard_pending();
+                self.stamp = file_stamp(&self.path);
+                debug!("Configuration saved");
+            }
+            // Stays pending, so the first due check after SAVE_RETRY tries again
+            Err(e) => {
+                error!(
+                    "Failed to save config to {}, retrying in {}s: {}",
+                    self.path.display(),
+                    SAVE_RETRY.as_secs(),
+                    e
+                );
+                self.retry_at = Some(Instant::now() + SAVE_RETRY);
+            }
+        }
+    }
+}
+
+// The file is replaced in one rename, so a crash leaves either the old or the new
+// contents behind, never a truncated mix
+pub fn write_atomic<T: Serialize>(path: &Path, value: &T) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(value).map_err(|e| format!("Failed to serialize: {}", e))?;
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+    let result = File::create(&tmp_path)
+        .and_then(|mut file| {
+            file.write_all(json.as_bytes())?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&tmp_path, path));
+    if let Err(e) = result {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(e.to_string());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::temp_dir;
+    use serde_json::json;
+
+    fn ago(duration: Duration) -> Option<Instant> {
+        Instant::now().checked_sub(duration)
+    }
+
+    #[test]
+    fn waits_for_edits_to_settle() {
+        let mut persister = Persister::new(temp_dir("persist-debounce").join("config.json"));
+        assert!(!persister.is_due());
+        persister.mark_changed();
+        assert!(persister.is_pending());
+        assert!(!persister.is_due());
+        persister.last_change = ago(SAVE_DELAY);
+        assert!(persister.is_due());
+    }
+
+    #[test]
+    fn saves_a_burst_of_edits_by_the_max_delay() {
+        let mut persister = Persister::new(temp_dir("persist-max-delay").join("config.json"));
+        persister.mark_changed();
+        persister.first_change = ago(MAX_SAVE_DELAY - Duration::from_millis(100));
+        persister.mark_changed();
+        assert!(!persister.is_due());
+        // Further edits don't push the first one back
+        persister.first_change = ago(MAX_SAVE_DELAY);
+        persister.mark_changed();
+        assert!(persister.is_due());
+    }
+
+    #[test]
+    fn saves_and_clears_the_pending_change() {
+        let path = temp_dir("persist-save").join("config.json");
+        let mut persister = Persister::new(path.clone());
+        persister.mark_changed();
+        persister.save(&json!({ "version": 2 }));
+        assert!(!persister.is_pending());
+        let saved: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(saved, json!({ "version": 2 }));
+        assert!(!path.with_file_name("config.json.tmp").exists());
+    }
+
+    #[test]
+    fn does_not_save_while_held() {
+        let path = temp_dir("persist-held").join("config.json");
+        fs::write(&path, "{ broken").unwrap();
+        let mut persister = Persister::new(path.clone());
+        persister.set_held(true);
+        persister.mark_changed();
+        persister.last_change = ago(SAVE_DELAY);
+        assert!(!persister.is_due());
+        persister.save(&json!({}));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{ broken");
+        assert!(persister.is_pending());
+
+        persister.set_held(false);
+        assert!(persister.is_due());
+    }
+
+    #[test]
+    fn backs_off_after_a_failed_save() {
+        // A file where the directory should be can't be written into, even as root
+        let dir = temp_dir("persist-unwritable");
+        fs::write(dir.join("profiles"), "").unwrap();
+        let mut persister = Persister::new(dir.join("profiles").join("config.json"));
+        persister.mark_changed();
+        persister.first_change = ago(MAX_SAVE_DELAY);
+        assert!(persister.is_due());
+        persister.save(&json!({}));
+        assert!(persister.is_pending());
+        assert!(!persister.is_due());
+
+        persister.retry_at = ago(Duration::ZERO);
+        assert!(persister.is_due());
+    }
+
+    #[test]
+    fn keeps_the_old_file_when_a_write_fails() {
+        let dir = temp_dir("persist-atomic");
+        let path = dir.join("config.json");
+        fs::write(&path, "old").unwrap();
+        // The temporary file can't be created over a directory
+        fs::create_dir(dir.join("config.json.tmp")).unwrap();
+        assert!(write_atomic(&path, &json!({ "new": true })).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "old");
+    }
+
+    #[test]
+    fn reports_an_outside_edit_once() {
+        let path = temp_dir("persist-external").join("config.json");
+        fs::write(&path, "{}").unwrap();
+        let mut persister = Persister::new(path.clone());
+        persister.last_poll = ago(WATCH_INTERVAL).unwrap();
+        assert!(!persister.poll_external_change());
+
+        fs::write(&path, "{ \"edited\": true }").unwrap();
+        assert!(!persister.poll_external_change(), "polled before the watch interval");
+        persister.last_poll = ago(WATCH_INTERVAL).unwrap();
+        assert!(persister.poll_external_change());
+        persister.last_poll = ago(WATCH_INTERVAL).unwrap();
+        assert!(!persister.poll_external_change());
+    }
+
+    #[test]
+    fn does_not_report_its_own_saves() {
+        let path = temp_dir("persist-own-save").join("config.json");
+        fs::write(&path, "{}").unwrap();
+        let mut persister = Persister::new(path.clone());
+        persister.mark_changed();
+        persister.save(&json!({ "saved": "by the app" }));
+        persister.last_poll = ago(WATCH_INTERVAL).unwrap();
+        assert!(!persister.poll_external_change());
+    }
+
+    #[test]
+    fn ignores_a_deleted_file() {
+        let path = temp_dir("persist-deleted").join("config.json");
+        fs::write(&path, "{}").unwrap();
+        let mut persister = Persister::new(path.clone());
+        fs::remove_file(&path).unwrap();
+        persister.last_poll = ago(WATCH_INTERVAL).unwrap();
+        assert!(!persister.poll_external_change());
+    }
+}
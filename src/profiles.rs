@@ -0,0 +1,216 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+
+// Lives in config.json, so setups from before profiles keep working
+pub const DEFAULT_PROFILE: &str = "Default";
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct ProfileState {
+    active: String,
+}
+
+// Named configs: the default one in config.json (or the file given with --config), every
+// other one in profiles/<name>.json next to it
+pub struct Profiles {
+    default_path: PathBuf,
+    active: String,
+}
+
+impl Profiles {
+    // Picks up the last active profile, unless one is asked for by name
+    pub fn load(default_path: &Path, requested: Option<&str>) -> Self {
+        let mut profiles = Profiles {
+            default_path: default_path.to_path_buf(),
+            active: DEFAULT_PROFILE.to_string(),
+        };
+        let remembered = fs::read_to_string(profiles.state_path())
+            .ok()
+            .and_then(|contents| serde_json::from_str::<ProfileState>(&contents).ok())
+            .map(|state| state.active);
+        // A bad name would clobber the state file or end up outside the profiles folder
+        let requested = requested.filter(|name| match check_name(name) {
+            Ok(()) => true,
+            Err(e) => {
+                error!("{}, using the last active profile", e);
+                false
+            }
+        });
+        let remembered = remembered.filter(|name| check_name(name).is_ok());
+        match requested.map(str::to_string).or(remembered) {
+            Some(name) if profiles.exists(&name) => profiles.active = name,
+            Some(name) if requested.is_some() => {
+                // Asked for at launch, so start it from defaults rather than ignoring it
+                info!("Creating profile {}", name);
+                profiles.active = name;
+            }
+            Some(name) => error!("Profile {} no longer exists, using {}", name, DEFAULT_PROFILE),
+            None => {}
+        }
+        profiles.save_state();
+        profiles
+    }
+
+    fn profiles_dir(&self) -> PathBuf {
+        self.default_path.parent().unwrap_or(Path::new(".")).join("profiles")
+    }
+
+    fn state_path(&self) -> PathBuf {
+        self.profiles_dir().join("profiles.json")
+    }
+
+    fn save_state(&self) {
+        let state = ProfileState {
+            active: self.active.clone(),
+        };
+        let result = fs::create_dir_all(self.profiles_dir())
+            .map_err(|e| e.to_string())
+            .and_then(|_| serde_json::to_string_pretty(&state).map_err(|e| e.to_string()))
+            .and_then(|json| fs::write(self.state_path(), json).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            error!("Failed to save active profile: {}", e);
+        }
+    }
+
+    pub fn path(&self, name: &str) -> PathBuf {
+        if name == DEFAULT_PROFILE {
+            self.default_path.clone()
+        } else {
+            self.profiles_dir().join(format!("{}.json", name))
+        }
+    }
+
+    pub fn active(&self) -> &str {
+        &self.active
+    }
+
+    pub fn active_path(&self) -> PathBuf {
+        self.path(&self.active)
+    }
+
+    pub fn exists(&self, name: &str) -> bool {
+        name == DEFAULT_PROFILE || self.path(name).exists()
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(self.profiles_dir())
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().and_then(|e| e.to_str()) == Some("json") && *path != self.state_path())
+            .filter_map(|path| path.file_stem().and_then(|s| s.to_str()).map(str::to_string))
+            .filter(|name| name != DEFAULT_PROFILE)
+            .collect();
+        names.sort();
+        names.insert(0, DEFAULT_PROFILE.to_string());
+        if !names.contains(&self.active) {
+            names.push(self.active.clone());
+        }
+        names
+    }
+
+    fn check_new_name(&self, name: &str) -> Result<(), String> {
+        check_name(name)?;
+        if self.exists(name) {
+            return Err(format!("Profile {} already exists", name));
+        }
+        Ok(())
+    }
+
+    pub fn set_active(&mut self, name: &str) {
+        self.active = name.to_string();
+        self.save_state();
+    }
+
+    // The copy starts from the profile as last saved
+    pub fn duplicate(&self, from: &str, to: &str) -> Result<(), String> {
+        self.check_new_name(to)?;
+        fs::create_dir_all(self.profiles_dir()).map_err(|e| e.to_string())?;
+        fs::copy(self.path(from), self.path(to)).map_err(|e| format!("Failed to copy profile {}: {}", from, e))?;
+        info!("Duplicated profile {} as {}", from, to);
+        Ok(())
+    }
+
+    pub fn rename(&mut self, from: &str, to: &str) -> Result<(), String> {
+        if from == DEFAULT_PROFILE {
+            return Err(format!("The {} profile can't be renamed", DEFAULT_PROFILE));
+        }
+        self.check_new_name(to)?;
+        fs::rename(self.path(from), self.path(to)).map_err(|e| format!("Failed to rename profile {}: {}", from, e))?;
+        if self.active == from {
+            self.set_active(to);
+        }
+        info!("Renamed profile {} to {}", from, to);
+        Ok(())
+    }
+
+    pub fn delete(&mut self, name: &str) -> Result<(), String> {
+        if name == DEFAULT_PROFILE {
+            return Err(format!("The {} profile can't be deleted", DEFAULT_PROFILE));
+        }
+        fs::remove_file(self.path(name)).map_err(|e| format!("Failed to delete profile {}: {}", name, e))?;
+        if self.active == name {
+            self.set_active(DEFAULT_PROFILE);
+        }
+        info!("Deleted profile {}", name);
+        Ok(())
+    }
+}
+
+fn check_name(name: &str) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("Profile name can't be empty".to_string());
+    }
+    if name.contains(['/', '\\']) || name.starts_with('.') || name == "profiles" {
+        return Err(format!("{} can't be used as a profile name", name));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::temp_dir;
+
+    #[test]
+    fn creates_a_requested_profile() {
+        let dir = temp_dir("profiles-requested");
+        let profiles = Profiles::load(&dir.join("config.json"), Some("Streaming"));
+        assert_eq!(profiles.active(), "Streaming");
+        assert_eq!(profiles.active_path(), dir.join("profiles").join("Streaming.json"));
+        // Remembered for the next launch, once there's a file for it
+        fs::write(profiles.active_path(), "{}").unwrap();
+        assert_eq!(Profiles::load(&dir.join("config.json"), None).active(), "Streaming");
+    }
+
+    #[test]
+    fn ignores_requested_names_that_arent_usable() {
+        let dir = temp_dir("profiles-invalid");
+        let config = dir.join("config.json");
+        let profiles = Profiles::load(&config, Some("Streaming"));
+        fs::write(profiles.active_path(), "{}").unwrap();
+        for name in ["profiles", "a/b", "..\\up", ".hidden", " "] {
+            let profiles = Profiles::load(&config, Some(name));
+            assert_eq!(profiles.active(), "Streaming", "{}", name);
+        }
+        let state = fs::read_to_string(dir.join("profiles").join("profiles.json")).unwrap();
+        assert!(state.contains("Streaming"));
+        assert!(!dir.join("profiles").join("a").exists());
+    }
+
+    #[test]
+    fn duplicates_and_renames_profiles() {
+        let dir = temp_dir("profiles-copy");
+        let mut profiles = Profiles::load(&dir.join("config.json"), Some("Work"));
+        fs::write(profiles.active_path(), "{}").unwrap();
+        profiles.duplicate("Work", "Play").unwrap();
+        assert!(profiles.duplicate("Work", "Play").is_err());
+        profiles.rename("Work", "Office").unwrap();
+        assert_eq!(profiles.active(), "Office");
+        assert_eq!(profiles.names(), ["Default", "Office", "Play"]);
+        assert!(profiles.rename(DEFAULT_PROFILE, "Other").is_err());
+    }
+}
@@ -0,0 +1,244 @@
+// Unicode text styles for chatbox output. Most styles are built from lookalike
+// characters in other Unicode blocks, so each one covers a different set of
+// characters. Anything a style has no mapping for is passed through unchanged,
+// except where a closer lookalike exists (noted per style).
+
+use eframe::egui;
+use serde::{Deserialize, Deserializer, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub enum TextStyle {
+    #[default]
+    None,
+    Uppercase,
+    SmallCaps,
+    Bold,
+    Italic,
+    BoldItalic,
+    Monospace,
+    Fullwidth,
+    Superscript,
+    Circled,
+}
+
+impl TextStyle {
+    pub const ALL: [TextStyle; 10] = [
+        TextStyle::None,
+        TextStyle::Uppercase,
+        TextStyle::SmallCaps,
+        TextStyle::Bold,
+        TextStyle::Italic,
+        TextStyle::BoldItalic,
+        TextStyle::Monospace,
+        TextStyle::Fullwidth,
+        TextStyle::Superscript,
+        TextStyle::Circled,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TextStyle::None => "None",
+            TextStyle::Uppercase => "Uppercase",
+            TextStyle::SmallCaps => "Small caps",
+            TextStyle::Bold => "Bold",
+            TextStyle::Italic => "Italic",
+            TextStyle::BoldItalic => "Bold italic",
+            TextStyle::Monospace => "Monospace",
+            TextStyle::Fullwidth => "Fullwidth",
+            TextStyle::Superscript => "Superscript",
+            TextStyle::Circled => "Circled",
+        }
+    }
+}
+
+// Older configs stored a bool that meant uppercase
+pub fn deserialize_style<'de, D: Deserializer<'de>>(deserializer: D) -> Result<TextStyle, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StyleOrBool {
+        Style(TextStyle),
+        Bool(bool),
+    }
+    Ok(match StyleOrBool::deserialize(deserializer)? {
+        StyleOrBool::Style(style) => style,
+        StyleOrBool::Bool(true) => TextStyle::Uppercase,
+        StyleOrBool::Bool(false) => TextStyle::None,
+    })
+}
+
+// q and x have no small capital, so they stay lowercase
+const SMALL_CAPS: [char; 26] = [
+    'ᴀ', 'ʙ', 'ᴄ', 'ᴅ', 'ᴇ', 'ꜰ', 'ɢ', 'ʜ', 'ɪ', 'ᴊ', 'ᴋ', 'ʟ', 'ᴍ',
+    'ɴ', 'ᴏ', 'ᴘ', 'ǫ', 'ʀ', 'ꜱ', 'ᴛ', 'ᴜ', 'ᴠ', 'ᴡ', 'x', 'ʏ', 'ᴢ',
+];
+
+// No superscript q exists, so it stays as is
+const SUPERSCRIPT_LOWER: [char; 26] = [
+    'ᵃ', 'ᵇ', 'ᶜ', 'ᵈ', 'ᵉ', 'ᶠ', 'ᵍ', 'ʰ', 'ⁱ', 'ʲ', 'ᵏ', 'ˡ', 'ᵐ',
+    'ⁿ', 'ᵒ', 'ᵖ', 'q', 'ʳ', 'ˢ', 'ᵗ', 'ᵘ', 'ᵛ', 'ʷ', 'ˣ', 'ʸ', 'ᶻ',
+];
+
+// Capitals without a superscript form use the lowercase one
+const SUPERSCRIPT_UPPER: [char; 26] = [
+    'ᴬ', 'ᴮ', 'ᶜ', 'ᴰ', 'ᴱ', 'ᶠ', 'ᴳ', 'ᴴ', 'ᴵ', 'ᴶ', 'ᴷ', 'ᴸ', 'ᴹ',
+    'ᴺ', 'ᴼ', 'ᴾ', 'q', 'ᴿ', 'ˢ', 'ᵀ', 'ᵁ', 'ⱽ', 'ᵂ', 'ˣ', 'ʸ', 'ᶻ',
+];
+
+const SUPERSCRIPT_DIGITS: [char; 10] = ['⁰', '¹', '²', '³', '⁴', '⁵', '⁶', '⁷', '⁸', '⁹'];
+
+// First code point of a style's capitals, small letters and digits in the
+// Mathematical Alphanumeric Symbols block
+struct MathAlphabet {
+    upper: u32,
+    lower: u32,
+    digits: Option<u32>,
+}
+
+const MATH_BOLD: MathAlphabet = MathAlphabet { upper: 0x1D400, lower: 0x1D41A, digits: Some(0x1D7CE) };
+// Italic has no digits of its own
+const MATH_ITALIC: MathAlphabet = MathAlphabet { upper: 0x1D434, lower: 0x1D44E, digits: None };
+// Bold italic borrows the bold digits
+const MATH_BOLD_ITALIC: MathAlphabet = MathAlphabet { upper: 0x1D468, lower: 0x1D482, digits: Some(0x1D7CE) };
+const MATH_MONOSPACE: MathAlphabet = MathAlphabet { upper: 0x1D670, lower: 0x1D68A, digits: Some(0x1D7F6) };
+
+fn offset(c: char, base: char) -> usize {
+    c as usize - base as usize
+}
+
+fn from_base(base: u32, index: usize) -> Option<char> {
+    char::from_u32(base + index as u32)
+}
+
+fn math_char(c: char, alphabet: &MathAlphabet) -> Option<char> {
+    match c {
+        // Italic small h was already encoded as the Planck constant, leaving a hole in the block
+        'h' if alphabet.lower == MATH_ITALIC.lower => Some('ℎ'),
+        'A'..='Z' => from_base(alphabet.upper, offset(c, 'A')),
+        'a'..='z' => from_base(alphabet.lower, offset(c, 'a')),
+        '0'..='9' => alphabet.digits.and_then(|base| from_base(base, offset(c, '0'))),
+        _ => None,
+    }
+}
+
+fn style_char(c: char, style: TextStyle) -> Option<char> {
+    match style {
+        TextStyle::None | TextStyle::Uppercase => None,
+        // Capitals keep their size so words still start with a capital
+        TextStyle::SmallCaps => match c {
+            'a'..='z' => Some(SMALL_CAPS[offset(c, 'a')]),
+            _ => None,
+        },
+        TextStyle::Bold => math_char(c, &MATH_BOLD),
+        TextStyle::Italic => math_char(c, &MATH_ITALIC),
+        TextStyle::BoldItalic => math_char(c, &MATH_BOLD_ITALIC),
+        TextStyle::Monospace => math_char(c, &MATH_MONOSPACE),
+        TextStyle::Fullwidth => match c {
+            ' ' => Some('\u{3000}'),
+            '!'..='~' => char::from_u32(c as u32 + 0xFEE0),
+            _ => None,
+        },
+        TextStyle::Superscript => match c {
+            'a'..='z' => Some(SUPERSCRIPT_LOWER[offset(c, 'a')]),
+            'A'..='Z' => Some(SUPERSCRIPT_UPPER[offset(c, 'A')]),
+            '0'..='9' => Some(SUPERSCRIPT_DIGITS[offset(c, '0')]),
+            '+' => Some('⁺'),
+            '-' => Some('⁻'),
+            '=' => Some('⁼'),
+            '(' => Some('⁽'),
+            ')' => Some('⁾'),
+            _ => None,
+        },
+        TextStyle::Circled => match c {
+            'A'..='Z' => from_base(0x24B6, offset(c, 'A')),
+            'a'..='z' => from_base(0x24D0, offset(c, 'a')),
+            '0' => Some('⓪'),
+            '1'..='9' => from_base(0x2460, offset(c, '1')),
+            _ => None,
+        },
+    }
+}
+
+pub fn stylize(text: &str, style: TextStyle) -> String {
+    match style {
+        TextStyle::None => text.to_string(),
+        TextStyle::Uppercase => text.to_uppercase(),
+        _ => text.chars().map(|c| style_char(c, style).unwrap_or(c)).collect(),
+    }
+}
+
+// Style picker whose entries are shown in their own style
+pub fn style_combo(ui: &mut egui::Ui, id: &str, style: &mut TextStyle) -> egui::Response {
+    let mut changed = false;
+    let mut response = egui::ComboBox::from_id_source(id)
+        .selected_text(stylize(style.name(), *style))
+        .show_ui(ui, |ui| {
+            for option in TextStyle::ALL {
+                changed |= ui.selectable_value(style, option, stylize(option.name(), option)).changed();
+            }
+        })
+        .response;
+    if changed {
+        response.mark_changed();
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn small_caps_keep_q_and_x_lookalikes() {
+        assert_eq!(stylize("quix", TextStyle::SmallCaps), "ǫᴜɪx");
+        assert_eq!(stylize("Hello 42", TextStyle::SmallCaps), "Hᴇʟʟᴏ 42");
+    }
+
+    #[test]
+    fn superscript_has_no_q() {
+        assert_eq!(stylize("q", TextStyle::Superscript), "q");
+        assert_eq!(stylize("Q", TextStyle::Superscript), "q");
+        assert_eq!(stylize("x2+(y-1)=0", TextStyle::Superscript), "ˣ²⁺⁽ʸ⁻¹⁾⁼⁰");
+        assert_eq!(stylize("CV", TextStyle::Superscript), "ᶜⱽ");
+    }
+
+    #[test]
+    fn italic_fills_the_h_hole() {
+        assert_eq!(stylize("h", TextStyle::Italic), "ℎ");
+        assert_eq!(stylize("ghi", TextStyle::Italic), "𝑔ℎ𝑖");
+        assert_eq!(stylize("H", TextStyle::Italic), "𝐻");
+        // No italic digits, and bold italic's h isn't missing
+        assert_eq!(stylize("h1", TextStyle::Italic), "ℎ1");
+        assert_eq!(stylize("h1", TextStyle::BoldItalic), "𝒉𝟏");
+    }
+
+    #[test]
+    fn circled_zero_is_separate() {
+        assert_eq!(stylize("0", TextStyle::Circled), "⓪");
+        assert_eq!(stylize("19", TextStyle::Circled), "①⑨");
+        assert_eq!(stylize("Az", TextStyle::Circled), "Ⓐⓩ");
+    }
+
+    #[test]
+    fn maps_the_math_alphabets_at_their_ends() {
+        assert_eq!(stylize("AZaz09", TextStyle::Bold), "𝐀𝐙𝐚𝐳𝟎𝟗");
+        assert_eq!(stylize("AZaz09", TextStyle::Monospace), "𝙰𝚉𝚊𝚣𝟶𝟿");
+        assert_eq!(stylize("Hi 1!", TextStyle::Fullwidth), "Ｈｉ\u{3000}１！");
+    }
+
+    #[test]
+    fn keeps_one_character_per_character() {
+        let text = "The quick brown fox, 0123456789 (+-=) é 漢 👍";
+        for style in TextStyle::ALL.into_iter().filter(|style| *style != TextStyle::Uppercase) {
+            assert_eq!(stylize(text, style).chars().count(), text.chars().count(), "{}", style.name());
+        }
+        assert_eq!(stylize("straße", TextStyle::Uppercase), "STRASSE");
+    }
+
+    #[test]
+    fn reads_old_bool_styles() {
+        let style = |json: &str| deserialize_style(&mut serde_json::Deserializer::from_str(json)).unwrap();
+        assert_eq!(style("true"), TextStyle::Uppercase);
+        assert_eq!(style("false"), TextStyle::None);
+        assert_eq!(style("\"Circled\""), TextStyle::Circled);
+    }
+}
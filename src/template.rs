@@ -0,0 +1,231 @@
+// Chatbox layout templates.
+//
+//   {status}              value of a field, empty if the field is missing
+//   {?media.paused:⏸}     text shown only when the field is set (not empty or "false")
+//   {!media.paused:▶}     text shown only when the field is not set
+//   {{ and }}             literal braces
+//
+// Conditional text may contain fields and other conditionals. Templates are
+// rendered line by line: a line with placeholders that all came out empty is
+// dropped, so a missing integration doesn't leave a stray label behind.
+
+use std::collections::BTreeMap;
+use std::iter::Peekable;
+use std::str::Chars;
+
+pub type Fields = BTreeMap<String, String>;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Text(String),
+    Field(String),
+    Conditional { field: String, negate: bool, body: Vec<Node> },
+}
+
+fn is_set(fields: &Fields, name: &str) -> bool {
+    fields.get(name).is_some_and(|value| !value.is_empty() && value != "false")
+}
+
+fn parse_nodes(chars: &mut Peekable<Chars>, nested: bool) -> Result<Vec<Node>, String> {
+    let mut nodes = Vec::new();
+    let mut text = String::new();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            }
+            '}' if nested => {
+                if !text.is_empty() {
+                    nodes.push(Node::Text(text));
+                }
+                return Ok(nodes);
+            }
+            '}' => return Err("Unexpected '}', use '}}' for a literal brace".to_string()),
+            '{' => {
+                if !text.is_empty() {
+                    nodes.push(Node::Text(std::mem::take(&mut text)));
+                }
+                nodes.push(parse_placeholder(chars)?);
+            }
+            _ => text.push(c),
+        }
+    }
+    if nested {
+        return Err("Missing '}' after conditional text".to_string());
+    }
+    if !text.is_empty() {
+        nodes.push(Node::Text(text));
+    }
+    Ok(nodes)
+}
+
+fn parse_placeholder(chars: &mut Peekable<Chars>) -> Result<Node, String> {
+    let negate = match chars.peek() {
+        Some('?') => Some(false),
+        Some('!') => Some(true),
+        _ => None,
+    };
+    if negate.is_some() {
+        chars.next();
+    }
+    let mut name = String::new();
+    loop {
+        match chars.next() {
+            Some('}') if negate.is_none() => break,
+            Some(':') if negate.is_some() => break,
+            Some(c) if c.is_alphanumeric() || c == '.' || c == '_' => name.push(c),
+            Some(c) => return Err(format!("Unexpected '{}' in placeholder {{{}", c, name)),
+            None => return Err(format!("Missing '}}' after {{{}", name)),
+        }
+    }
+    if name.is_empty() {
+        return Err("Empty placeholder".to_string());
+    }
+    Ok(match negate {
+        None => Node::Field(name),
+        Some(negate) => Node::Conditional {
+            field: name,
+            negate,
+            body: parse_nodes(chars, true)?,
+        },
+    })
+}
+
+// Renders nodes into out, returning (placeholders seen, placeholders that produced text)
+fn render_nodes(nodes: &[Node], fields: &Fields, out: &mut String) -> (usize, usize) {
+    let mut seen = 0;
+    let mut filled = 0;
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Field(name) => {
+                seen += 1;
+                if let Some(value) = fields.get(name).filter(|value| !value.is_empty()) {
+                    out.push_str(value);
+                    filled += 1;
+                }
+            }
+            Node::Conditional { field, negate, body } => {
+                seen += 1;
+                if is_set(fields, field) != *negate {
+                    let start = out.len();
+                    render_nodes(body, fields, out);
+                    if out.len() > start {
+                        filled += 1;
+                    }
+                }
+            }
+        }
+    }
+    (seen, filled)
+}
+
+// Checks a template without rendering it
+pub fn validate(template: &str) -> Result<(), String> {
+    for (index, line) in template.lines().enumerate() {
+        parse_nodes(&mut line.chars().peekable(), false).map_err(|e| format!("Line {}: {}", index + 1, e))?;
+    }
+    Ok(())
+}
+
+pub fn render(template: &str, fields: &Fields) -> Result<String, String> {
+    let mut lines = Vec::new();
+    for (index, line) in template.lines().enumerate() {
+        let nodes = parse_nodes(&mut line.chars().peekable(), false).map_err(|e| format!("Line {}: {}", index + 1, e))?;
+        let mut rendered = String::new();
+        let (seen, filled) = render_nodes(&nodes, fields, &mut rendered);
+        if seen > 0 && filled == 0 {
+            continue;
+        }
+        let rendered = rendered.trim();
+        if !rendered.is_empty() {
+            lines.push(rendered.to_string());
+        }
+    }
+    Ok(lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(pairs: &[(&str, &str)]) -> Fields {
+        pairs.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+    }
+
+    fn parse(template: &str) -> Result<Vec<Node>, String> {
+        parse_nodes(&mut template.chars().peekable(), false)
+    }
+
+    #[test]
+    fn parses_fields_and_conditionals() {
+        assert_eq!(
+            parse("🎵 {media}{?media.paused: (⏸ {time})}").unwrap(),
+            [
+                Node::Text("🎵 ".to_string()),
+                Node::Field("media".to_string()),
+                Node::Conditional {
+                    field: "media.paused".to_string(),
+                    negate: false,
+                    body: vec![Node::Text(" (⏸ ".to_string()), Node::Field("time".to_string()), Node::Text(")".to_string())],
+                },
+            ]
+        );
+        assert_eq!(
+            parse("{!activity.vr:desktop}").unwrap(),
+            [Node::Conditional {
+                field: "activity.vr".to_string(),
+                negate: true,
+                body: vec![Node::Text("desktop".to_string())],
+            }]
+        );
+    }
+
+    #[test]
+    fn escapes_braces() {
+        assert_eq!(parse("{{status}}").unwrap(), [Node::Text("{status}".to_string())]);
+        assert_eq!(render("{{ {status} }}", &fields(&[("status", "hi")])).unwrap(), "{ hi }");
+        assert_eq!(render("{?status:{{x}}}", &fields(&[("status", "hi")])).unwrap(), "{x}");
+    }
+
+    #[test]
+    fn rejects_malformed_placeholders() {
+        assert_eq!(parse("{status").unwrap_err(), "Missing '}' after {status");
+        assert_eq!(parse("{?status:unclosed").unwrap_err(), "Missing '}' after conditional text");
+        assert_eq!(parse("status}").unwrap_err(), "Unexpected '}', use '}}' for a literal brace");
+        assert_eq!(parse("{}").unwrap_err(), "Empty placeholder");
+        assert_eq!(parse("{sta tus}").unwrap_err(), "Unexpected ' ' in placeholder {sta");
+        assert_eq!(parse("{status:x}").unwrap_err(), "Unexpected ':' in placeholder {status");
+    }
+
+    #[test]
+    fn validate_reports_the_line() {
+        assert_eq!(validate("{status}\n{time}"), Ok(()));
+        assert_eq!(validate("{status}\n{time"), Err("Line 2: Missing '}' after {time".to_string()));
+        assert_eq!(render("ok\n}", &Fields::new()), Err("Line 2: Unexpected '}', use '}}' for a literal brace".to_string()));
+    }
+
+    #[test]
+    fn renders_unknown_fields_as_empty() {
+        let fields = fields(&[("status", "Working")]);
+        assert_eq!(render("{status} {nonsense}", &fields).unwrap(), "Working");
+        // A line whose placeholders all came out empty is dropped, labels and all
+        assert_eq!(render("{status}\nPlaying: {media}\nFixed text", &fields).unwrap(), "Working\nFixed text");
+    }
+
+    #[test]
+    fn shows_conditional_text_by_field() {
+        let playing = fields(&[("media", "Song"), ("media.paused", "false")]);
+        let paused = fields(&[("media", "Song"), ("media.paused", "true")]);
+        let template = "{media}{?media.paused: ⏸}{!media.paused: ▶}";
+        assert_eq!(render(template, &playing).unwrap(), "Song ▶");
+        assert_eq!(render(template, &paused).unwrap(), "Song ⏸");
+        assert_eq!(render("{?media.paused:paused}", &Fields::new()).unwrap(), "");
+        assert_eq!(render("{!media.paused:idle}", &Fields::new()).unwrap(), "idle");
+    }
+}
@@ -0,0 +1,99 @@
+// Stand-ins shared by the unit tests: scratch directories, a UDP socket posing as VRChat's
+// OSC input and a fake VRChat OSCQuery server with its mDNS announcement.
+
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, UdpSocket};
+use std::path::PathBuf;
+use std::time::Duration;
+use mdns_sd::{ServiceEvent, ServiceInfo};
+use rosc::{OscMessage, OscPacket};
+use serde_json::{json, Value};
+use crate::modules::app::OscTarget;
+
+// An empty directory of its own for each test
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rustychatbox-test-{}-{}", std::process::id(), name));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// Receives what the app sends, like VRChat listening on 9000
+pub struct OscListener {
+    socket: UdpSocket,
+}
+
+impl OscListener {
+    pub fn new() -> Self {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.set_read_timeout(Some(Duration::from_millis(200))).unwrap();
+        OscListener { socket }
+    }
+
+    pub fn port(&self) -> u16 {
+        self.socket.local_addr().unwrap().port()
+    }
+
+    pub fn target(&self, name: &str) -> OscTarget {
+        OscTarget {
+            name: name.to_string(),
+            port: self.port(),
+            follow_oscquery: false,
+            ..OscTarget::default()
+        }
+    }
+
+    // The next packet, or None once nothing has arrived for a while
+    pub fn recv_packet(&self) -> Option<OscPacket> {
+        let mut buf = [0u8; rosc::decoder::MTU];
+        let size = self.socket.recv(&mut buf).ok()?;
+        Some(rosc::decoder::decode_udp(&buf[..size]).unwrap().1)
+    }
+
+    // The next message, or None once nothing has arrived for a while
+    pub fn recv(&self) -> Option<OscMessage> {
+        match self.recv_packet()? {
+            OscPacket::Message(message) => Some(message),
+            OscPacket::Bundle(bundle) => panic!("Expected a message, got {:?}", bundle),
+        }
+    }
+
+    // Skips anything else, such as typing indicators, on the way to the next message for addr
+    pub fn recv_addr(&self, addr: &str) -> Option<OscMessage> {
+        std::iter::from_fn(|| self.recv()).find(|message| message.addr == addr)
+    }
+}
+
+// Answers every request on 127.0.0.1 with body, like VRChat's OSCQuery server would
+pub fn fake_http_server(body: Value) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let mut buf = [0u8; 2048];
+            let _ = stream.read(&mut buf);
+            let body = body.to_string();
+            let _ = write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            );
+        }
+    });
+    addr
+}
+
+// The mDNS record VRChat publishes for its OSCQuery server, as the browser reports it
+pub fn vrchat_announcement(name: &str, http: SocketAddr) -> ServiceEvent {
+    let host_name = format!("{}.local.", name.to_lowercase());
+    let ip = http.ip().to_string();
+    let info = ServiceInfo::new("_oscjson._tcp.local.", name, &host_name, ip.as_str(), http.port(), None).unwrap();
+    ServiceEvent::ServiceResolved(info)
+}
+
+// A VRChat client on this machine that takes OSC on osc_port
+pub fn fake_vrchat(name: &str, osc_port: u16) -> ServiceEvent {
+    let http = fake_http_server(json!({ "NAME": name, "OSC_IP": "0.0.0.0", "OSC_PORT": osc_port }));
+    vrchat_announcement(name, http)
+}
@@ -3,29 +3,44 @@ mod integrations;
 mod status;
 mod chatting;
 mod options;
+mod router;
+mod rules;
 mod toggle;
 
 use chatting::show_chatting_tab;
 use eframe::egui::{self, Color32, Context, Rounding, Stroke};
 use integrations::show_integrations_tab;
 use options::show_options_tab;
+use router::show_router_tab;
+use rules::show_rules_tab;
 use status::show_status_tab;
 use toggle::toggle_switch;
 use types::{ChatTab, IntegrationsTab, StatusTab, Tab};
-use std::fs;
+use std::collections::HashMap;
+use std::sync::mpsc::Receiver;
 use std::time::{Instant, Duration};
-use log::{error, info, debug};
+use log::{error, info, debug, warn};
 use arboard::Clipboard;
-use crate::config::Config;
-use crate::osc::OscClient;
+use crate::compose;
+use crate::config::{Config, LoadError, CONFIG_VERSION};
+use crate::import::{self, ImportReport, ImportSettings};
+use crate::persist::Persister;
+use crate::profiles::Profiles;
+use crate::template::{self, Fields};
+use crate::osc::{ChatboxMessage, DiscoveredService, OscClient, OscEvent, OscQueryService, OscRouter, OscServer, SendOutcome};
 use crate::modules::{
     activity::{WindowActivityModule, WindowActivityOptions},
-    app::AppOptionsOptions,
+    app::{AppOptionsOptions, OscOptions, OscTarget},
     chatting::ChatOptions,
     component::{ComponentStatsModule, ComponentStatsOptions},
     extra::ExtraOptions,
+    integration::Integration,
+    layout::LayoutOptions,
     media::{MediaLinkModule, MediaLinkOptions},
     network::{NetworkStats, NetworkStatsOptions},
+    parameters::{ParameterModule, ParameterOptions, ParameterSource},
+    router::RouterOptions,
+    rules::{RuleContext, RuleEngine, RuleOptions, RuleSampler},
     status::{StatusModule, StatusOptions},
     time::{TimeModule, TimeOptions},
 };
@@ -39,30 +54,89 @@ pub struct App {
     integrations_tab: IntegrationsTab,
     media_link: MediaLinkOptions,
     network_stats: NetworkStatsOptions,
+    parameter_options: ParameterOptions,
     status_tab: StatusTab,
     status_options: StatusOptions,
     time_options: TimeOptions,
     window_activity: WindowActivityOptions,
     osc_client: OscClient,
+    osc_server: Option<OscServer>,
+    osc_receive_error: Option<String>,
+    // Connection settings the client and server currently run with
+    applied_osc_options: OscOptions,
+    // Set with --osc-target; used instead of the configured targets for the whole run
+    osc_target_override: Option<OscTarget>,
+    osc_options_edited: Option<Instant>,
+    osc_events: Option<Receiver<OscEvent>>,
+    router_options: RouterOptions,
+    layout_options: LayoutOptions,
+    rule_options: RuleOptions,
+    rule_engine: RuleEngine,
+    // Running only while rules are on
+    rule_sampler: Option<RuleSampler>,
+    profiles: Profiles,
+    persister: Persister,
+    profile_name_edit: String,
+    profile_error: Option<String>,
+    // MagicChatBox folder or file to import from, and how the last import went
+    import_path: String,
+    import_result: Option<Result<ImportReport, String>>,
+    // Why the active profile's file couldn't be loaded, until dismissed
+    config_error: Option<String>,
+    // Fields from the last sample, so the template preview doesn't re-run integrations every frame
+    template_fields: Fields,
+    template_fields_sampled: Instant,
+    applied_router_options: RouterOptions,
+    router: Option<OscRouter>,
+    oscquery: Option<OscQueryService>,
+    oscquery_target: Option<DiscoveredService>,
+    current_avatar: Option<String>,
+    avatar_parameters: HashMap<String, rosc::OscType>,
     components_module: ComponentStatsModule,
     media_module: MediaLinkModule,
+    parameter_module: ParameterModule,
     status_module: StatusModule,
     window_activity_module: WindowActivityModule,
     osc_preview: String,
     send_to_vrchat: bool,
     last_osc_send: Instant,
+    // When tick last refreshed the chatbox, None until the first tick
+    last_tick_send: Option<Instant>,
     config_changed: bool,
     pending_scroll_to: Option<egui::Id>,
-    clipboard: Clipboard,
+    // None when running headless
+    clipboard: Option<Clipboard>,
     live_edit_enabled: bool,
     previous_osc_preview: String,
+    // Integrations shortened or left out of the last composed message
+    osc_trimmed: Vec<compose::Trimmed>,
+    page: usize,
+    page_shown: Instant,
+    activity_marquee: compose::Marquee,
+    media_marquee: compose::Marquee,
     last_activity_update: Instant,
     cached_activity: Option<String>,
     first_update: bool,
+    last_chat_edit: Instant,
 }
 
+// Drop the typing indicator when the draft has been left untouched this long
+const TYPING_TIMEOUT: Duration = Duration::from_secs(5);
+// Wait for edits to settle so dragging a port doesn't rebind on every frame
+const OSC_REBIND_DELAY: Duration = Duration::from_millis(750);
+// How often the layout preview samples integrations while nothing is being sent
+const TEMPLATE_PREVIEW_INTERVAL: Duration = Duration::from_secs(1);
+
 impl App {
-    pub fn new(osc_client: OscClient, config: Config, clipboard: Clipboard) -> Self {
+    pub fn new(
+        osc_client: OscClient,
+        osc_server: Option<OscServer>,
+        oscquery: Option<OscQueryService>,
+        config: Config,
+        config_error: Option<LoadError>,
+        profiles: Profiles,
+        clipboard: Option<Clipboard>,
+    ) -> Self {
         let mut app_options = AppOptionsOptions {
             app_options: config.app_options,
             enabled: true,
@@ -80,12 +154,22 @@ impl App {
     
         let window_activity_options = config.window_activity_options.unwrap_or_default();
         let window_activity_enabled = config.window_activity_enabled.unwrap_or(true);
+        let osc_events = osc_server.as_ref().map(|server| {
+            info!("Receiving OSC on port {}", server.port());
+            server.subscribe()
+        });
     
+        let applied_osc_options = app_options.app_options.osc_options.clone();
+        let router = osc_server
+            .as_ref()
+            .filter(|_| config.router_options.enabled)
+            .map(|server| OscRouter::start(server, &config.router_options));
+
         info!("Initializing App with OSC client and config");
-        Self {
+        let mut app = Self {
             current_tab: config.current_tab,
             app_options,
-            chat_tab: config.chat_tab,
+            chat_tab: ChatTab::default(),
             chat_options: config.chat_options,
             component_stats: config.component_stats_options,
             extra_options: config.extra_options,
@@ -96,9 +180,11 @@ impl App {
                 current_time_enabled: config.current_time_enabled,
                 medialink_enabled: config.medialink_enabled,
                 window_activity_enabled,
+                priorities: config.integration_priorities,
             },
             media_link: config.media_link_options,
             network_stats: NetworkStatsOptions::new(config.network_stats_options.config),
+            parameter_options: config.parameter_options,
             status_tab: config.status_tab,
             status_options: config.status_options,
             time_options: config.time_options,
@@ -107,22 +193,70 @@ impl App {
                 ..window_activity_options.clone()
             },
             osc_client,
+            osc_server,
+            osc_receive_error: None,
+            applied_osc_options,
+            osc_target_override: None,
+            osc_options_edited: None,
+            osc_events,
+            applied_router_options: config.router_options.clone(),
+            router_options: config.router_options,
+            layout_options: config.layout_options,
+            rule_options: config.rule_options,
+            rule_engine: RuleEngine::new(),
+            rule_sampler: None,
+            persister: Persister::new(profiles.active_path()),
+            profiles,
+            profile_name_edit: String::new(),
+            profile_error: None,
+            import_path: String::new(),
+            import_result: None,
+            config_error: config_error.as_ref().map(|e| e.message.clone()),
+            template_fields: Fields::new(),
+            template_fields_sampled: Instant::now().checked_sub(TEMPLATE_PREVIEW_INTERVAL).unwrap_or_else(Instant::now),
+            router,
+            oscquery,
+            oscquery_target: None,
+            current_avatar: None,
+            avatar_parameters: HashMap::new(),
             components_module: ComponentStatsModule::new(),
             media_module: MediaLinkModule::new(),
+            parameter_module: ParameterModule::new(),
             status_module,
             window_activity_module: WindowActivityModule::new(&window_activity_options),
             osc_preview: String::new(),
             send_to_vrchat: config.send_to_vrchat,
             last_osc_send: Instant::now(),
+            last_tick_send: None,
             config_changed: false,
             pending_scroll_to: None,
             clipboard,
             live_edit_enabled: config.live_edit_enabled,
             previous_osc_preview: String::new(),
+            osc_trimmed: Vec::new(),
+            page: 0,
+            page_shown: Instant::now(),
+            activity_marquee: compose::Marquee::default(),
+            media_marquee: compose::Marquee::default(),
             last_activity_update: Instant::now(),
             cached_activity: None,
             first_update: true,
+            last_chat_edit: Instant::now(),
+        };
+        app.persister.set_held(config_error.is_some_and(|e| e.held));
+        // A profile asked for with --profile only exists in memory so far, and duplicating or
+        // renaming works on its file
+        if !app.profiles.active_path().exists() {
+            let config = app.to_config();
+            app.persister.save(&config);
         }
+        app
+    }
+
+    // The client was created with this target, so later target edits must leave it alone
+    pub fn with_osc_target_override(mut self, target: Option<OscTarget>) -> Self {
+        self.osc_target_override = target;
+        self
     }
 
     fn update_osc_preview(&mut self) {
@@ -133,8 +267,6 @@ impl App {
             .unwrap()
             .as_millis() as u64;
     
-        const MAX_LINE_WIDTH: usize = 27;
-    
         let should_update = self.send_to_vrchat
             && self.chat_options.can_send()
             && self.last_osc_send.elapsed().as_secs_f32() >= self.app_options.app_options.osc_options.update_rate;
@@ -144,124 +276,28 @@ impl App {
             && self.chat_options.override_display_time
             && self.last_osc_send.elapsed().as_secs_f32() >= self.app_options.app_options.osc_options.update_rate.max(self.chat_options.display_time_seconds);
     
-        let active_chat_message = self.chat_options.messages.iter_mut().find(|m| m.editing);
+        let layout = compose::Layout::new(self.app_options.app_options.osc_options.separate_lines);
+        let segments = if should_update || should_update_live {
+            self.collect_layout_segments()
+        } else {
+            Vec::new()
+        };
+        // A chat message on screen holds the page rotation until it's gone
+        let chat_preempts = self.chat_options.queued_message.is_some()
+            || self.chat_options.messages.iter().any(|m| m.editing);
+        let integration_composition = if !(should_update || should_update_live) {
+            compose::Composition::default()
+        } else if self.app_options.app_options.osc_options.paging {
+            self.current_page(&segments, &layout, chat_preempts)
+        } else {
+            compose::compose_prioritized(None, &segments, &layout)
+        };
+        let active_chat_message = self.chat_options.messages.iter().find(|m| m.editing).cloned();
     
         if should_update || should_update_live {
-            let mut parts = Vec::new();
-    
-            // Status
-            if self.integrations_tab.personal_status_enabled {
-                if let Some(status) = self.status_module.get_current_message(&self.status_options) {
-                    parts.push(status);
-                }
-            }
-    
-            // Window Activity
-            if self.integrations_tab.window_activity_enabled {
-                if let Some(activity) = self.window_activity_module.get_formatted_activity(&self.window_activity) {
-                    parts.push(activity);
-                }
-            }
-    
-            // Component Stats
-            if self.integrations_tab.component_stats_enabled {
-                let stats = self.components_module.get_formatted_stats(&self.component_stats);
-                if !stats.is_empty() {
-                    let stat_parts: Vec<&str> = stats.split('|').collect();
-                    let mut stat_pairs = Vec::new();
-                    for chunk in stat_parts.chunks(2) {
-                        if chunk.len() == 2 {
-                            stat_pairs.push(format!("{} | {}", chunk[0].trim(), chunk[1].trim()));
-                        } else {
-                            stat_pairs.push(chunk[0].trim().to_string());
-                        }
-                    }
-                    parts.extend(stat_pairs);
-                }
-            }
-    
-            // Time
-            if self.integrations_tab.current_time_enabled {
-                let time = TimeModule::get_local_time(&self.time_options);
-                parts.push(time);
-            }
-    
-            // Network Stats
-            if self.integrations_tab.network_stats_enabled {
-                let interfaces = NetworkStats::get_interfaces();
-                if let Some(iface) = interfaces.first() {
-                    let stats = NetworkStats::get_formatted_stats(&self.network_stats.config, &iface.name);
-                    if !stats.is_empty() {
-                        parts.push(stats);
-                    }
-                }
-            }
-    
-            // MediaLink
-            if self.integrations_tab.medialink_enabled {
-                if let Some(track) = self.media_module.get_formatted_track(&self.media_link) {
-                    parts.push(track);
-                }
-            }
-    
-            let separator = if self.app_options.app_options.osc_options.separate_lines {
-                "\n"
-            } else {
-                " | "
-            };
-            let mut lines = Vec::new();
-    
-            for (i, part) in parts.iter().enumerate() {
-                let is_last_part = i == parts.len() - 1;
-    
-                let part_lines = part.split('\n').collect::<Vec<_>>();
-    
-                for part_line in part_lines {
-                    let trimmed_line = part_line.trim();
-                    if trimmed_line.is_empty() {
-                        continue;
-                    }
-    
-                    let mut current_line = String::new();
-                    let words = trimmed_line.split_whitespace();
-    
-                    for word in words {
-                        let word_len = word.len();
-                        let space_len = if current_line.is_empty() { 0 } else { 1 };
-                        let potential_len = current_line.len() + space_len + word_len;
-    
-                        if potential_len > MAX_LINE_WIDTH && !current_line.is_empty() {
-                            lines.push(current_line);
-                            current_line = word.to_string();
-                        } else if word_len > MAX_LINE_WIDTH {
-                            if !current_line.is_empty() {
-                                lines.push(current_line);
-                            }
-                            lines.push(word[..MAX_LINE_WIDTH].to_string());
-                            current_line = String::new();
-                        } else {
-                            if !current_line.is_empty() {
-                                current_line.push(' ');
-                            }
-                            current_line.push_str(word);
-                        }
-                    }
-    
-                    if !current_line.is_empty() {
-                        lines.push(current_line);
-                    }
-                }
-    
-                if !is_last_part && !self.app_options.app_options.osc_options.separate_lines {
-                    if lines.last().map_or(true, |last| last.len() + separator.len() <= MAX_LINE_WIDTH) {
-                        lines.last_mut().map(|last| *last += separator);
-                    } else {
-                        lines.push(separator.to_string());
-                    }
-                }
-            }
-    
-            self.previous_osc_preview = lines.join("\n");
+            let composition = integration_composition;
+            self.previous_osc_preview = composition.lines.join("\n");
+            self.osc_trimmed = composition.trimmed;
     
             if let Some(ref message) = active_chat_message {
                 let message_text = if self.chat_options.live_editing && message.editing {
@@ -274,39 +310,9 @@ impl App {
                 } else {
                     message_text.clone()
                 };
-                let mut message_lines = Vec::new();
-    
-                let mut current_line = String::new();
-                let words = formatted_message.split_whitespace();
-                for word in words {
-                    let word_len = word.len();
-                    let space_len = if current_line.is_empty() { 0 } else { 1 };
-                    let potential_len = current_line.len() + space_len + word_len;
-    
-                    if potential_len > MAX_LINE_WIDTH && !current_line.is_empty() {
-                        message_lines.push(current_line);
-                        current_line = word.to_string();
-                    } else if word_len > MAX_LINE_WIDTH {
-                        if !current_line.is_empty() {
-                            message_lines.push(current_line);
-                        }
-                        message_lines.push(word[..MAX_LINE_WIDTH].to_string());
-                        current_line = String::new();
-                    } else {
-                        if !current_line.is_empty() {
-                            current_line.push(' ');
-                        }
-                        current_line.push_str(word);
-                    }
-                }
-                if !current_line.is_empty() {
-                    message_lines.push(current_line);
-                }
-    
-                if !self.previous_osc_preview.is_empty() {
-                    message_lines.push(self.previous_osc_preview.clone());
-                }
-                self.osc_preview = message_lines.join("\n");
+                let composition = compose::compose_prioritized(Some(&formatted_message), &segments, &layout);
+                self.osc_preview = composition.lines.join("\n");
+                self.osc_trimmed = composition.trimmed;
             } else {
                 self.osc_preview = self.previous_osc_preview.clone();
             }
@@ -316,16 +322,13 @@ impl App {
             if let Some(ref message) = active_chat_message {
                 if message.editing && self.send_to_vrchat && !self.osc_preview.is_empty() {
                     if (now_ms - message.sent_at_ms) / 1000 < self.chat_options.chat_timeout as u64 {
-                        if let Err(e) = self.osc_client.send_chatbox_message(
-                            &self.osc_preview,
-                            self.chat_options.play_fx_sound,
-                            self.extra_options.slim_mode,
-                        ) {
-                            error!("Failed to send live edit OSC message: {}", e);
-                        } else {
-                            info!("Sent live edit OSC message: {}", self.osc_preview);
+                        let chatbox_message = ChatboxMessage::new(self.osc_preview.clone())
+                            .slim(self.extra_options.slim_mode);
+                        match self.send_chatbox(chatbox_message) {
+                            Ok(SendOutcome::Sent) => info!("Sent live edit OSC message: {}", self.osc_preview),
+                            Ok(outcome) => debug!("Live edit OSC message {:?}", outcome),
+                            Err(e) => error!("Failed to send live edit OSC message: {}", e),
                         }
-                        self.last_osc_send = Instant::now();
                     }
                 }
             }
@@ -338,135 +341,674 @@ impl App {
                 } else {
                     message
                 };
-                let mut lines = Vec::new();
-    
-                let mut current_line = String::new();
-                let words = formatted_message.split_whitespace();
-                for word in words {
-                    let word_len = word.len();
-                    let space_len = if current_line.is_empty() { 0 } else { 1 };
-                    let potential_len = current_line.len() + space_len + word_len;
+                let composition = compose::compose_prioritized(Some(&formatted_message), &segments, &layout);
+                self.osc_preview = composition.lines.join("\n");
+                self.osc_trimmed = composition.trimmed;
     
-                    if potential_len > MAX_LINE_WIDTH && !current_line.is_empty() {
-                        lines.push(current_line);
-                        current_line = word.to_string();
-                    } else if word_len > MAX_LINE_WIDTH {
-                        if !current_line.is_empty() {
-                            lines.push(current_line);
-                        }
-                        lines.push(word[..MAX_LINE_WIDTH].to_string());
-                        current_line = String::new();
-                    } else {
-                        if !current_line.is_empty() {
-                            current_line.push(' ');
-                        }
-                        current_line.push_str(word);
+                if self.send_to_vrchat {
+                    let chatbox_message = ChatboxMessage::new(self.osc_preview.clone())
+                        .notify(self.chat_options.play_fx_sound)
+                        .slim(self.extra_options.slim_mode)
+                        .deferred();
+                    match self.send_chatbox(chatbox_message) {
+                        Ok(SendOutcome::Sent) => info!("Sent queued OSC message: {}", self.osc_preview),
+                        Ok(outcome) => debug!("Queued OSC message {:?}", outcome),
+                        Err(e) => error!("Failed to send queued OSC message: {}", e),
                     }
                 }
-                if !current_line.is_empty() {
-                    lines.push(current_line);
-                }
-    
-                if !self.previous_osc_preview.is_empty() {
-                    lines.push(self.previous_osc_preview.clone());
+                self.chat_options.add_message(formatted_message);
+            } else if self.send_to_vrchat {
+                let chatbox_message = ChatboxMessage::new(self.osc_preview.clone())
+                    .slim(self.extra_options.slim_mode);
+                match self.send_chatbox(chatbox_message) {
+                    Ok(SendOutcome::Sent) => {}
+                    Ok(outcome) => debug!("OSC message {:?}", outcome),
+                    Err(e) => error!("Failed to send OSC message: {}", e),
                 }
-                self.osc_preview = lines.join("\n");
-    
-                if self.send_to_vrchat {
-                    if let Err(e) = self.osc_client.send_chatbox_message(
-                        &self.osc_preview,
-                        self.chat_options.play_fx_sound,
-                        self.extra_options.slim_mode,
-                    ) {
-                        error!("Failed to send queued OSC message: {}", e);
+            }
+        }
+    }
+
+    fn current_page(&mut self, segments: &[compose::Segment], layout: &compose::Layout, hold: bool) -> compose::Composition {
+        let osc_options = &self.app_options.app_options.osc_options;
+        let mut pages = compose::paginate(segments, layout, osc_options.page_indicator);
+        if pages.is_empty() {
+            return compose::Composition::default();
+        }
+        if hold {
+            self.page_shown = Instant::now();
+        } else if self.page_shown.elapsed().as_secs_f32() >= osc_options.page_dwell {
+            self.page += 1;
+            self.page_shown = Instant::now();
+        }
+        self.page %= pages.len();
+        pages.swap_remove(self.page)
+    }
+
+    // The integration toggle, unless a matching rule says otherwise
+    fn integration_enabled(&self, integration: Integration) -> bool {
+        self.rule_engine.effects().integration(integration).unwrap_or(match integration {
+            Integration::Status => self.integrations_tab.personal_status_enabled,
+            Integration::Activity => self.integrations_tab.window_activity_enabled,
+            Integration::Components => self.integrations_tab.component_stats_enabled,
+            Integration::Time => self.integrations_tab.current_time_enabled,
+            Integration::Network => self.integrations_tab.network_stats_enabled,
+            Integration::Media => self.integrations_tab.medialink_enabled,
+        })
+    }
+
+    fn update_rules(&mut self) {
+        if !self.rule_options.enabled {
+            self.rule_engine.clear();
+            self.rule_sampler = None;
+            self.status_module.set_override(None);
+            return;
+        }
+        if !self.rule_engine.due() {
+            return;
+        }
+        let uses_idle = self.rule_options.uses_idle();
+        let sampler = self.rule_sampler.get_or_insert_with(|| RuleSampler::start(uses_idle));
+        sampler.set_wants_idle(uses_idle);
+        let context = RuleContext {
+            vr_active: self.window_activity_module.is_vr_active(),
+            focused_app: self.window_activity_module.current_title(),
+            media_status: sampler.media_status(),
+            time: chrono::Local::now().time(),
+            idle: sampler.idle(),
+        };
+        let effects = self.rule_engine.evaluate(&self.rule_options, &context);
+        self.status_module.set_override(effects.status.as_deref());
+    }
+
+    fn collect_layout_segments(&mut self) -> Vec<compose::Segment> {
+        let layout_template = match &self.rule_engine.effects().template {
+            Some(name) => self.layout_options.templates.iter().find(|t| &t.name == name),
+            None => self.layout_options.active_template().filter(|_| self.layout_options.enabled),
+        };
+        let Some(layout_template) = layout_template else {
+            return self.collect_segments();
+        };
+        let text = layout_template.text.clone();
+        self.template_fields = self.collect_fields();
+        self.template_fields_sampled = Instant::now();
+        match template::render(&text, &self.template_fields) {
+            Ok(rendered) => vec![compose::Segment::new("Layout", rendered)],
+            Err(e) => {
+                error!("Failed to render layout template: {}", e);
+                self.collect_segments()
+            }
+        }
+    }
+
+    // Keeps the layout preview current when sending is off or held back by a chat message
+    fn sample_template_fields(&mut self) {
+        if self.template_fields_sampled.elapsed() < TEMPLATE_PREVIEW_INTERVAL {
+            return;
+        }
+        // Marquees scroll once per send, so the preview works on copies
+        let activity_marquee = self.activity_marquee.clone();
+        let media_marquee = self.media_marquee.clone();
+        self.template_fields = self.collect_fields();
+        self.template_fields_sampled = Instant::now();
+        self.activity_marquee = activity_marquee;
+        self.media_marquee = media_marquee;
+    }
+
+    // Named values from every enabled integration, for layout templates
+    fn collect_fields(&mut self) -> Fields {
+        let mut fields = Fields::new();
+
+        if self.integration_enabled(Integration::Status) {
+            if let Some(status) = self.status_module.get_current_message(&self.status_options) {
+                fields.insert("status".to_string(), status);
+            }
+        }
+
+        if self.integration_enabled(Integration::Activity) {
+            if let Some(activity) = self.window_activity_module.get_formatted_activity(&self.window_activity, Some(&mut self.activity_marquee)) {
+                fields.insert("activity".to_string(), activity);
+            }
+            fields.insert("activity.app".to_string(), self.window_activity_module.current_title());
+            fields.insert("activity.vr".to_string(), self.window_activity_module.is_vr_active().to_string());
+        }
+
+        if self.integration_enabled(Integration::Components) {
+            let stats = self.components_module.get_formatted_stats(&self.component_stats);
+            let stat_parts: Vec<&str> = stats.split('|').map(str::trim).filter(|s| !s.is_empty()).collect();
+            let pairs: Vec<String> = stat_parts.chunks(2).map(|chunk| chunk.join(" | ")).collect();
+            fields.insert("components".to_string(), pairs.join("\n"));
+            fields.insert("cpu".to_string(), format!("{:.0}", self.components_module.get_cpu_usage()));
+            if let Some(gpu) = self.components_module.get_gpu_usage() {
+                fields.insert("gpu".to_string(), format!("{:.0}", gpu));
+            }
+            let (used, total) = self.components_module.get_memory_usage();
+            if total > 0 {
+                fields.insert("memory".to_string(), format!("{:.0}", used as f64 / total as f64 * 100.0));
+            }
+        }
+
+        if self.integration_enabled(Integration::Time) {
+            let (hour, minute) = TimeModule::get_hour_minute(&self.time_options);
+            fields.insert("time".to_string(), TimeModule::get_local_time(&self.time_options));
+            fields.insert("time.clock".to_string(), TimeModule::get_clock(&self.time_options));
+            fields.insert("time.hour".to_string(), format!("{:02}", hour));
+            fields.insert("time.minute".to_string(), format!("{:02}", minute));
+        }
+
+        if self.integration_enabled(Integration::Network) {
+            if let Some(iface) = NetworkStats::get_interfaces().first() {
+                fields.insert("network".to_string(), NetworkStats::get_formatted_stats(&self.network_stats.config, &iface.name));
+            }
+        }
+
+        if self.integration_enabled(Integration::Media) {
+            let info = self.media_module.get_track_info();
+            if let Some(track) = self.media_module.format_track(info.clone(), &self.media_link, Some(&mut self.media_marquee)) {
+                fields.insert("media".to_string(), track);
+            }
+            if let Some((status, artist, title)) = info {
+                fields.insert("media.playing".to_string(), (status == "Playing").to_string());
+                fields.insert("media.paused".to_string(), (status == "Paused").to_string());
+                fields.insert("media.status".to_string(), status);
+                fields.insert("media.artist".to_string(), artist);
+                fields.insert("media.title".to_string(), title);
+            }
+        }
+
+        fields
+    }
+
+    fn segment(&self, integration: Integration, text: String) -> compose::Segment {
+        let (priority, max_lines) = self.integrations_tab.priority_of(integration);
+        compose::Segment {
+            priority,
+            max_lines,
+            ..compose::Segment::new(integration.name(), text)
+        }
+    }
+
+    // Samples every enabled integration, in chatbox order
+    fn collect_segments(&mut self) -> Vec<compose::Segment> {
+        let mut parts = Vec::new();
+
+        // Status
+        if self.integration_enabled(Integration::Status) {
+            if let Some(status) = self.status_module.get_current_message(&self.status_options) {
+                parts.push(self.segment(Integration::Status, status));
+            }
+        }
+
+        // Window Activity
+        if self.integration_enabled(Integration::Activity) {
+            if let Some(activity) = self.window_activity_module.get_formatted_activity(&self.window_activity, Some(&mut self.activity_marquee)) {
+                parts.push(self.segment(Integration::Activity, activity));
+            }
+        }
+
+        // Component Stats
+        if self.integration_enabled(Integration::Components) {
+            let stats = self.components_module.get_formatted_stats(&self.component_stats);
+            if !stats.is_empty() {
+                let stat_parts: Vec<&str> = stats.split('|').collect();
+                let mut stat_pairs = Vec::new();
+                for chunk in stat_parts.chunks(2) {
+                    if chunk.len() == 2 {
+                        stat_pairs.push(format!("{} | {}", chunk[0].trim(), chunk[1].trim()));
                     } else {
-                        info!("Sent queued OSC message: {}", self.osc_preview);
+                        stat_pairs.push(chunk[0].trim().to_string());
                     }
-                    self.last_osc_send = Instant::now();
                 }
-                self.chat_options.add_message(formatted_message);
-            } else if self.send_to_vrchat {
-                if let Err(e) = self.osc_client.send_chatbox_message(
-                    &self.osc_preview,
-                    self.chat_options.play_fx_sound,
-                    self.extra_options.slim_mode,
-                ) {
-                    error!("Failed to send OSC message: {}", e);
+                // Pairs stay on their own lines, or run on like separate integrations would
+                let separator = if self.app_options.app_options.osc_options.separate_lines { "\n" } else { " | " };
+                parts.push(self.segment(Integration::Components, stat_pairs.join(separator)));
+            }
+        }
+
+        // Time
+        if self.integration_enabled(Integration::Time) {
+            let time = TimeModule::get_local_time(&self.time_options);
+            parts.push(self.segment(Integration::Time, time));
+        }
+
+        // Network Stats
+        if self.integration_enabled(Integration::Network) {
+            let interfaces = NetworkStats::get_interfaces();
+            if let Some(iface) = interfaces.first() {
+                let stats = NetworkStats::get_formatted_stats(&self.network_stats.config, &iface.name);
+                if !stats.is_empty() {
+                    parts.push(self.segment(Integration::Network, stats));
                 }
-                self.last_osc_send = Instant::now();
             }
         }
+
+        // MediaLink
+        if self.integration_enabled(Integration::Media) {
+            if let Some(track) = self.media_module.get_formatted_track(&self.media_link, Some(&mut self.media_marquee)) {
+                parts.push(self.segment(Integration::Media, track));
+            }
+        }
+
+        parts
+    }
+
+    pub(crate) fn send_chatbox(&mut self, message: ChatboxMessage) -> anyhow::Result<SendOutcome> {
+        let outcome = self.osc_client.send_chatbox(message)?;
+        if outcome == SendOutcome::Sent {
+            self.last_osc_send = Instant::now();
+        }
+        Ok(outcome)
     }
 
-    pub fn save_config_if_needed(&mut self, config_path: &std::path::Path) {
-        if self.config_changed {
-            debug!("Saving configuration to {}", config_path.display());
-            let config = Config {
-                app_options: self.app_options.app_options.clone(),
-                personal_status_enabled: self.integrations_tab.personal_status_enabled,
-                component_stats_enabled: self.integrations_tab.component_stats_enabled,
-                network_stats_enabled: self.integrations_tab.network_stats_enabled,
-                current_time_enabled: self.integrations_tab.current_time_enabled,
-                medialink_enabled: self.integrations_tab.medialink_enabled,
-                window_activity_enabled: Some(self.integrations_tab.window_activity_enabled),
-                window_activity_options: Some(self.window_activity.clone()),
-                chat_options: self.chat_options.clone(),
-                chat_tab: self.chat_tab.clone(),
-                component_stats_options: self.component_stats.clone(),
-                extra_options: self.extra_options.clone(),
-                media_link_options: self.media_link.clone(),
-                network_stats_options: self.network_stats.clone(),
-                status_options: self.status_options.clone(),
-                status_tab: self.status_tab.clone(),
-                status_messages: self.status_module.messages.clone(),
-                time_options: self.time_options.clone(),
-                current_tab: self.current_tab.clone(),
-                send_to_vrchat: self.send_to_vrchat,
-                live_edit_enabled: self.live_edit_enabled,
-            };
-            if let Ok(json) = serde_json::to_string_pretty(&config) {
-                if let Err(e) = fs::write(config_path, json) {
-                    error!("Failed to save config: {}", e);
-                } else {
-                    info!("Configuration saved successfully");
+    fn flush_deferred_chatbox(&mut self) {
+        match self.osc_client.flush_pending() {
+            Ok(Some(_)) => self.last_osc_send = Instant::now(),
+            Ok(None) => {}
+            Err(e) => error!("Failed to send deferred OSC message: {}", e),
+        }
+    }
+
+    fn poll_osc_events(&mut self) {
+        let Some(events) = &self.osc_events else {
+            return;
+        };
+        for event in events.try_iter() {
+            match event {
+                OscEvent::AvatarChange(id) => {
+                    info!("Avatar changed to {}", id);
+                    // Parameters belong to the previous avatar
+                    self.avatar_parameters.clear();
+                    self.parameter_module.reset();
+                    self.current_avatar = Some(id);
                 }
-            } else {
-                error!("Failed to serialize config to JSON");
+                OscEvent::AvatarParameter { name, value } => {
+                    debug!("Avatar parameter {} = {:?}", name, value);
+                    self.avatar_parameters.insert(name, value);
+                }
+            }
+        }
+    }
+
+    fn follow_discovered_vrchat(&mut self) {
+        let Some(oscquery) = &self.oscquery else {
+            return;
+        };
+        let client = oscquery.vrchat_client();
+        if client == self.oscquery_target {
+            return;
+        }
+        if let Some(client) = &client {
+            info!("Sending OSC to discovered {} at {}:{}", client.name, client.ip, client.osc_port);
+        }
+        self.osc_client
+            .set_discovered_addr(client.as_ref().map(|client| std::net::SocketAddr::new(client.ip, client.osc_port)));
+        self.oscquery_target = client;
+    }
+
+    fn apply_osc_options(&mut self) {
+        if self.osc_options_edited.is_none_or(|edited| edited.elapsed() < OSC_REBIND_DELAY) {
+            return;
+        }
+        self.osc_options_edited = None;
+        let options = self.app_options.app_options.osc_options.clone();
+        if options.targets != self.applied_osc_options.targets {
+            match &self.osc_target_override {
+                Some(target) => info!("OSC targets changed, still sending to {} from the command line", target.addr()),
+                None => {
+                    info!("OSC targets changed, retargeting client");
+                    self.osc_client.set_targets(&options.targets);
+                }
+            }
+        }
+        if options.capture_mode != self.applied_osc_options.capture_mode
+            || options.capture_format != self.applied_osc_options.capture_format
+        {
+            if let Err(e) = self.osc_client.set_capture(options.capture_mode, options.capture_format) {
+                error!("Failed to start OSC capture: {}", e);
             }
+        }
+        let restart_server = options.receive_port != self.applied_osc_options.receive_port
+            || options.oscquery_enabled != self.applied_osc_options.oscquery_enabled;
+        if restart_server || self.router_options != self.applied_router_options {
+            // The router holds a subscription on the server, so it goes first
+            self.router = None;
+        }
+        if restart_server {
+            info!("OSC receive settings changed, restarting server");
+            // Release the old port before binding again
+            self.oscquery = None;
+            self.osc_server = None;
+            self.osc_events = None;
+            self.oscquery_target = None;
+            self.osc_client.set_discovered_addr(None);
+            match crate::osc::start_receiver(&options) {
+                Ok((server, oscquery)) => {
+                    info!("Receiving OSC on port {}", server.port());
+                    self.osc_events = Some(server.subscribe());
+                    self.osc_server = Some(server);
+                    self.oscquery = oscquery;
+                    self.osc_receive_error = None;
+                }
+                Err(e) => {
+                    error!("{}", e);
+                    self.osc_receive_error = Some(e.to_string());
+                }
+            }
+        }
+        self.applied_osc_options = options;
+        if self.router.is_none() && self.router_options.enabled {
+            if let Some(server) = &self.osc_server {
+                self.router = Some(OscRouter::start(server, &self.router_options));
+            }
+        }
+        self.applied_router_options = self.router_options.clone();
+    }
+
+    fn update_avatar_parameters(&mut self) {
+        if !self.send_to_vrchat || !self.parameter_options.enabled {
+            return;
+        }
+        let components_module = &mut self.components_module;
+        let media_module = &self.media_module;
+        let time_options = &self.time_options;
+        let updates = self.parameter_module.due_updates(&self.parameter_options, |source| match source {
+            ParameterSource::TimeHour => Some(TimeModule::get_hour_minute(time_options).0 as f32),
+            ParameterSource::TimeMinute => Some(TimeModule::get_hour_minute(time_options).1 as f32),
+            ParameterSource::CpuUsage => Some(components_module.get_cpu_usage()),
+            ParameterSource::GpuUsage => components_module.get_gpu_usage(),
+            ParameterSource::MemoryUsage => {
+                let (used, total) = components_module.get_memory_usage();
+                (total > 0).then(|| used as f32 / total as f32 * 100.0)
+            }
+            ParameterSource::MediaPlaying => Some(if media_module.is_playing() { 1.0 } else { 0.0 }),
+        });
+        for (name, value) in updates {
+            debug!("Sending avatar parameter {} = {:?}", name, value);
+            if let Err(e) = self.osc_client.send_parameter(&name, value) {
+                error!("Failed to send avatar parameter {}: {}", name, e);
+            }
+        }
+    }
+
+    fn update_typing_indicator(&mut self) {
+        let typing = self.send_to_vrchat
+            && self.chat_options.typing_indicator
+            && (self.chat_tab.is_focused || !self.chat_tab.message.is_empty())
+            && self.last_chat_edit.elapsed() < TYPING_TIMEOUT;
+        if let Err(e) = self.osc_client.set_typing(typing) {
+            error!("Failed to send typing indicator: {}", e);
+        }
+    }
+
+    fn to_config(&self) -> Config {
+        Config {
+            version: CONFIG_VERSION,
+            app_options: self.app_options.app_options.clone(),
+            personal_status_enabled: self.integrations_tab.personal_status_enabled,
+            component_stats_enabled: self.integrations_tab.component_stats_enabled,
+            network_stats_enabled: self.integrations_tab.network_stats_enabled,
+            current_time_enabled: self.integrations_tab.current_time_enabled,
+            medialink_enabled: self.integrations_tab.medialink_enabled,
+            window_activity_enabled: Some(self.integrations_tab.window_activity_enabled),
+            window_activity_options: Some(self.window_activity.clone()),
+            integration_priorities: self.integrations_tab.priorities.clone(),
+            chat_options: self.chat_options.clone(),
+            component_stats_options: self.component_stats.clone(),
+            extra_options: self.extra_options.clone(),
+            media_link_options: self.media_link.clone(),
+            network_stats_options: self.network_stats.clone(),
+            parameter_options: self.parameter_options.clone(),
+            router_options: self.router_options.clone(),
+            layout_options: self.layout_options.clone(),
+            rule_options: self.rule_options.clone(),
+            status_options: self.status_options.clone(),
+            status_tab: self.status_tab.clone(),
+            status_messages: self.status_module.messages.clone(),
+            time_options: self.time_options.clone(),
+            current_tab: self.current_tab.clone(),
+            send_to_vrchat: self.send_to_vrchat,
+            live_edit_enabled: self.live_edit_enabled,
+        }
+    }
+
+    // Swaps in every setting from config. The open tab and chat draft stay as they are,
+    // and connection changes go through apply_osc_options like any other edit.
+    fn apply_config(&mut self, config: Config) {
+        let mut app_options = config.app_options;
+        app_options.osc_options.update_rate = app_options.osc_options.update_rate.clamp(1.6, 10.0);
+        self.app_options.app_options = app_options;
+        let window_activity_enabled = config.window_activity_enabled.unwrap_or(true);
+        self.integrations_tab = IntegrationsTab {
+            personal_status_enabled: config.personal_status_enabled,
+            component_stats_enabled: config.component_stats_enabled,
+            network_stats_enabled: config.network_stats_enabled,
+            current_time_enabled: config.current_time_enabled,
+            medialink_enabled: config.medialink_enabled,
+            window_activity_enabled,
+            priorities: config.integration_priorities,
+        };
+        self.window_activity = WindowActivityOptions {
+            enabled: window_activity_enabled,
+            ..config.window_activity_options.unwrap_or_default()
+        };
+        self.chat_options = config.chat_options;
+        self.component_stats = config.component_stats_options;
+        self.extra_options = config.extra_options;
+        self.media_link = config.media_link_options;
+        self.network_stats = NetworkStatsOptions::new(config.network_stats_options.config);
+        self.parameter_options = config.parameter_options;
+        self.router_options = config.router_options;
+        self.layout_options = config.layout_options;
+        self.rule_options = config.rule_options;
+        self.rule_engine.clear();
+        self.status_options = config.status_options;
+        self.status_tab = config.status_tab;
+        self.status_module = StatusModule::new();
+        for message in config.status_messages {
+            self.status_module.add_message(message);
+        }
+        self.time_options = config.time_options;
+        self.send_to_vrchat = config.send_to_vrchat;
+        self.live_edit_enabled = config.live_edit_enabled;
+        self.parameter_module.reset();
+        // Apply connection changes on the next frame instead of after the usual settle delay
+        self.osc_options_edited = Some(Instant::now().checked_sub(OSC_REBIND_DELAY).unwrap_or_else(Instant::now));
+    }
+
+    pub fn save_config_if_needed(&mut self) {
+        if self.config_changed {
+            self.persister.mark_changed();
             self.config_changed = false;
         }
+        if self.persister.is_due() {
+            let config = self.to_config();
+            self.persister.save(&config);
+        }
     }
-}
 
-impl eframe::App for App {
-    fn update(&mut self, ctx: &Context, _frame: &mut eframe::Frame) {
+    // Writes pending changes right away instead of waiting out the save delay
+    pub fn flush_config(&mut self) {
+        if self.config_changed {
+            self.persister.mark_changed();
+            self.config_changed = false;
+        }
+        if self.persister.is_pending() {
+            let config = self.to_config();
+            self.persister.save(&config);
+        }
+    }
+
+    // Picks up edits made to the active profile's file while the app is running
+    pub fn reload_config_if_changed(&mut self) {
+        if !self.persister.poll_external_change() {
+            return;
+        }
+        let path = self.profiles.active_path();
+        match Config::load(&path) {
+            Ok(config) => {
+                info!("Reloading {} after an outside change", path.display());
+                if self.config_changed || self.persister.is_pending() {
+                    warn!("Discarding unsaved changes in favour of the edited file");
+                }
+                self.config_changed = false;
+                self.persister.discard_pending();
+                self.persister.set_held(false);
+                self.config_error = None;
+                self.apply_config(config);
+            }
+            Err(e) => {
+                let message = format!(
+                    "Failed to reload {} after it was edited: {}. The current settings stay in use and won't be saved until the file loads again.",
+                    path.display(),
+                    e
+                );
+                error!("{}", message);
+                self.persister.set_held(true);
+                self.config_error = Some(message);
+            }
+        }
+    }
+
+    fn load_active_profile(&mut self) {
+        self.persister.set_path(self.profiles.active_path());
+        let (config, config_error) = Config::load_or_create(self.profiles.active_path());
+        self.persister.set_held(config_error.as_ref().is_some_and(|e| e.held));
+        self.config_error = config_error.map(|e| e.message);
+        self.apply_config(config);
+    }
+
+    fn switch_profile(&mut self, name: &str) {
+        if name == self.profiles.active() {
+            return;
+        }
+        // Keep whatever was changed in the profile being left
+        self.flush_config();
+        self.profiles.set_active(name);
+        info!("Switching to profile {}", name);
+        self.load_active_profile();
+    }
+
+    fn import_magicchatbox(&mut self) {
+        let mut settings = ImportSettings {
+            status_messages: self.status_module.messages.clone(),
+            active_status: None,
+            status_options: self.status_options.clone(),
+            time_options: self.time_options.clone(),
+            media_link_options: self.media_link.clone(),
+            component_stats_options: self.component_stats.clone(),
+        };
+        let path = std::path::PathBuf::from(self.import_path.trim());
+        let result = import::import_magicchatbox(&path, &mut settings);
+        if result.is_ok() {
+            self.status_module.messages = settings.status_messages;
+            if let Some(message) = settings.active_status {
+                self.status_module.select_message(&message);
+            }
+            self.status_options = settings.status_options;
+            self.time_options = settings.time_options;
+            self.media_link = settings.media_link_options;
+            self.component_stats = settings.component_stats_options;
+            self.integrations_tab.personal_status_enabled = self.status_options.enabled;
+            self.integrations_tab.current_time_enabled = self.time_options.config.enabled;
+            self.integrations_tab.medialink_enabled = self.media_link.enabled;
+            self.integrations_tab.component_stats_enabled = self.component_stats.enabled;
+            self.config_changed = true;
+        } else if let Err(e) = &result {
+            error!("MagicChatBox import failed: {}", e);
+        }
+        self.import_result = Some(result);
+    }
+
+    fn show_profile_menu(&mut self, ui: &mut egui::Ui) {
+        let active = self.profiles.active().to_string();
+        let mut switch_to = None;
+        egui::ComboBox::from_id_source("profile_switcher")
+            .selected_text(&active)
+            .show_ui(ui, |ui| {
+                for name in self.profiles.names() {
+                    if ui.selectable_label(name == active, &name).clicked() {
+                        switch_to = Some(name);
+                    }
+                }
+            });
+        ui.menu_button("⚙", |ui| {
+            ui.label("Profile name:");
+            ui.text_edit_singleline(&mut self.profile_name_edit);
+            let name = self.profile_name_edit.trim().to_string();
+            let mut result = None;
+            if ui.button("Duplicate current").clicked() {
+                self.flush_config();
+                result = Some(self.profiles.duplicate(&active, &name).map(|_| switch_to = Some(name.clone())));
+            }
+            if ui.button("Rename current").clicked() {
+                self.flush_config();
+                result = Some(self.profiles.rename(&active, &name));
+                self.persister.set_path(self.profiles.active_path());
+            }
+            if ui.button("Delete current").clicked() {
+                self.flush_config();
+                result = Some(self.profiles.delete(&active).map(|_| self.load_active_profile()));
+            }
+            match result {
+                Some(Ok(())) => {
+                    self.profile_error = None;
+                    self.profile_name_edit.clear();
+                    ui.close_menu();
+                }
+                Some(Err(e)) => {
+                    error!("{}", e);
+                    self.profile_error = Some(e);
+                }
+                None => {}
+            }
+            if let Some(e) = &self.profile_error {
+                ui.colored_label(Color32::RED, e);
+            }
+        });
+        ui.label("Profile:");
+        if let Some(name) = switch_to {
+            self.switch_profile(&name);
+        }
+    }
 
+    // Everything that runs on its own: OSC, integrations, rules and sending. Called each frame
+    // by the GUI and on a timer when headless.
+    pub fn tick(&mut self) {
         if self.first_update {
             self.first_update = false;
             debug!("First update completed");
         }
 
+        self.apply_osc_options();
+        self.poll_osc_events();
+        self.follow_discovered_vrchat();
+        self.update_typing_indicator();
+        self.update_avatar_parameters();
+        self.update_rules();
+        self.flush_deferred_chatbox();
+
         if self.last_activity_update.elapsed() >= Duration::from_millis(500) {
-            self.cached_activity = self.window_activity_module.get_formatted_activity(&self.window_activity);
+            self.cached_activity = self.window_activity_module.get_formatted_activity(&self.window_activity, None);
             self.last_activity_update = Instant::now();
             debug!("Updated cached activity");
         }
 
-        static mut LAST_UPDATE: Option<Instant> = None;
         let now = Instant::now();
-        let should_update = unsafe {
-            if let Some(last) = LAST_UPDATE {
-                now.duration_since(last).as_secs_f32() >= self.app_options.app_options.osc_options.update_rate
-            } else {
-                true
-            }
-        };
-    
+        let should_update = self.last_tick_send.is_none_or(|last| {
+            now.duration_since(last).as_secs_f32() >= self.app_options.app_options.osc_options.update_rate
+        });
+
         if should_update {
             self.update_osc_preview();
-            unsafe { LAST_UPDATE = Some(now); }
+            self.last_tick_send = Some(now);
         }
-    
+    }
+}
+
+impl eframe::App for App {
+    fn update(&mut self, ctx: &Context, _frame: &mut eframe::Frame) {
+        self.tick();
+
         // Set custom visual style
         let mut visuals = egui::Visuals::default();
         visuals.dark_mode = true;
@@ -580,6 +1122,28 @@ impl eframe::App for App {
                         self.config_changed = true;
                         info!("Switched to Chatting tab");
                     }
+                    let mut button = egui::Button::new("Rules").min_size(egui::vec2(100.0, 40.0));
+                    if self.current_tab == Tab::Rules {
+                        button = button.fill(enabled_color);
+                    } else {
+                        button = button.fill(inactive_tab_color);
+                    }
+                    if ui.add(button).clicked() {
+                        self.current_tab = Tab::Rules;
+                        self.config_changed = true;
+                        info!("Switched to Rules tab");
+                    }
+                    let mut button = egui::Button::new("Router").min_size(egui::vec2(100.0, 40.0));
+                    if self.current_tab == Tab::Router {
+                        button = button.fill(enabled_color);
+                    } else {
+                        button = button.fill(inactive_tab_color);
+                    }
+                    if ui.add(button).clicked() {
+                        self.current_tab = Tab::Router;
+                        self.config_changed = true;
+                        info!("Switched to Router tab");
+                    }
                     let mut button = egui::Button::new("Options").min_size(egui::vec2(100.0, 40.0));
                     if self.current_tab == Tab::Options {
                         button = button.fill(enabled_color);
@@ -599,9 +1163,22 @@ impl eframe::App for App {
                         self.config_changed = true;
                         debug!("Send to VRChat toggle changed to {}", self.send_to_vrchat);
                     }
+                    ui.separator();
+                    self.show_profile_menu(ui);
                 });
             });
         });
+
+        if let Some(error) = self.config_error.clone() {
+            egui::TopBottomPanel::top("config_error_panel").show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button("Dismiss").clicked() {
+                        self.config_error = None;
+                    }
+                    ui.add(egui::Label::new(egui::RichText::new(error).color(Color32::RED)).wrap());
+                });
+            });
+        }
     
         // Right side panel with OSC preview
         egui::SidePanel::right("right_panel")
@@ -636,6 +1213,17 @@ impl eframe::App for App {
                         });
                     });
                 });
+                for trimmed in &self.osc_trimmed {
+                    let action = match trimmed.action {
+                        compose::TrimAction::Shortened => "shortened",
+                        compose::TrimAction::Dropped => "left out",
+                    };
+                    ui.label(
+                        egui::RichText::new(format!("{} {}: {}", trimmed.name, action, trimmed.reason))
+                            .color(Color32::from_rgb(0xff, 0xa5, 0x00))
+                            .text_style(egui::TextStyle::Small),
+                    );
+                }
                 ui.separator();
                 if self.current_tab == Tab::Chatting && self.chat_options.edit_messages && self.chat_options.live_editing {
                     ui.horizontal(|ui| {
@@ -665,6 +1253,16 @@ impl eframe::App for App {
                     show_chatting_tab(ui, self);
                 });
             }
+            Tab::Rules => {
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    show_rules_tab(ui, self);
+                });
+            }
+            Tab::Router => {
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    show_router_tab(ui, self);
+                });
+            }
             Tab::Options => {
                 egui::CentralPanel::default().show(ctx, |ui| {
                     show_options_tab(ui, self);
@@ -674,4 +1272,186 @@ impl eframe::App for App {
     
         ctx.request_repaint_after(Duration::from_millis(100)); // 10 FPS
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{fake_vrchat, temp_dir, OscListener};
+    use mdns_sd::ServiceEvent;
+    use rosc::OscType;
+
+    fn test_app(name: &str, targets: &[OscTarget], oscquery: Option<OscQueryService>) -> App {
+        let profiles = Profiles::load(&temp_dir(name).join("config.json"), None);
+        let client = OscClient::new(targets).unwrap();
+        App::new(client, None, oscquery, Config::default(), None, profiles, None)
+    }
+
+    #[test]
+    #[ignore = "registers with mDNS, which needs multicast"]
+    fn follows_the_discovered_vrchat_client() {
+        let configured = OscListener::new();
+        let vrchat = OscListener::new();
+        let target = OscTarget {
+            follow_oscquery: true,
+            ..configured.target("VRChat")
+        };
+        let oscquery = OscQueryService::start(9127).unwrap();
+        oscquery.announce(fake_vrchat("VRChat-Client-F011A0", vrchat.port()));
+        let mut app = test_app("follow-oscquery", &[target], Some(oscquery));
+
+        app.follow_discovered_vrchat();
+        assert_eq!(app.oscquery_target.as_ref().map(|t| t.osc_port), Some(vrchat.port()));
+        app.send_chatbox(ChatboxMessage::new("found you")).unwrap();
+        let message = vrchat.recv_addr("/chatbox/input").expect("sent to the discovered client");
+        assert_eq!(message.args[0], OscType::String("found you".to_string()));
+        assert!(configured.recv().is_none());
+
+        // Back to the configured address once the client is gone
+        let fullname = "VRChat-Client-F011A0._oscjson._tcp.local.".to_string();
+        app.oscquery.as_ref().unwrap().announce(ServiceEvent::ServiceRemoved("_oscjson._tcp.local.".to_string(), fullname));
+        app.follow_discovered_vrchat();
+        assert_eq!(app.oscquery_target, None);
+        app.osc_client.send_parameter("Test", OscType::Bool(true)).unwrap();
+        assert_eq!(configured.recv().map(|m| m.addr).as_deref(), Some("/avatar/parameters/Test"));
+        assert!(vrchat.recv().is_none());
+    }
+
+    #[test]
+    fn puts_the_status_back_when_a_rule_stops_matching() {
+        use crate::modules::rules::{Rule, RuleAction};
+        let profiles = Profiles::load(&temp_dir("rule-status").join("config.json"), None);
+        let config = Config {
+            status_messages: vec!["Chilling".to_string(), "AFK".to_string()],
+            rule_options: RuleOptions {
+                enabled: true,
+                rules: vec![Rule {
+                    name: "Always".to_string(),
+                    enabled: true,
+                    conditions: Vec::new(),
+                    actions: vec![RuleAction::SelectStatus("AFK".to_string())],
+                }],
+            },
+            ..Config::default()
+        };
+        let mut app = App::new(OscClient::new(&[]).unwrap(), None, None, config, None, profiles, None);
+        let status = |app: &App| app.status_module.get_current_message(&app.status_options);
+
+        app.update_rules();
+        assert_eq!(status(&app).as_deref(), Some("AFK"));
+        app.rule_options.rules[0].enabled = false;
+        app.rule_engine = RuleEngine::new();
+        app.update_rules();
+        assert_eq!(status(&app).as_deref(), Some("Chilling"));
+
+        // Turning rules off altogether puts it back too
+        app.rule_options.rules[0].enabled = true;
+        app.rule_engine = RuleEngine::new();
+        app.update_rules();
+        assert_eq!(status(&app).as_deref(), Some("AFK"));
+        app.rule_options.enabled = false;
+        app.update_rules();
+        assert_eq!(status(&app).as_deref(), Some("Chilling"));
+    }
+
+    #[test]
+    fn keeps_the_command_line_target_when_targets_change() {
+        let configured = OscListener::new();
+        let command_line = OscListener::new();
+        let mut app = test_app("osc-target-override", &[command_line.target("Command line")], None)
+            .with_osc_target_override(Some(command_line.target("Command line")));
+
+        // As after a hot reload or a profile switch
+        app.app_options.app_options.osc_options.targets = vec![configured.target("VRChat")];
+        app.osc_options_edited = Some(Instant::now() - OSC_REBIND_DELAY);
+        app.apply_osc_options();
+        assert!(app.osc_options_edited.is_none());
+        app.osc_client.send_parameter("Test", OscType::Bool(true)).unwrap();
+        assert_eq!(command_line.recv().map(|m| m.addr).as_deref(), Some("/avatar/parameters/Test"));
+        assert!(configured.recv().is_none());
+    }
+
+    #[test]
+    fn writes_a_profile_created_at_launch() {
+        let dir = temp_dir("profile-created");
+        let profiles = Profiles::load(&dir.join("config.json"), Some("Streaming"));
+        let client = OscClient::new(&[]).unwrap();
+        let mut app = App::new(client, None, None, Config::default(), None, profiles, None);
+        assert!(dir.join("profiles").join("Streaming.json").exists());
+        app.profiles.duplicate("Streaming", "Copy").unwrap();
+        app.profiles.rename("Streaming", "Live").unwrap();
+        assert_eq!(app.profiles.names(), ["Default", "Copy", "Live"]);
+    }
+
+    // Directories where the next few seconds' backups would go make copying the file fail
+    fn block_backups(path: &std::path::Path) {
+        let now = chrono::Local::now();
+        for seconds in 0..5 {
+            let at = now + chrono::Duration::seconds(seconds);
+            let name = format!("{}.corrupt-{}", path.file_name().unwrap().to_string_lossy(), at.format("%Y%m%d-%H%M%S"));
+            std::fs::create_dir_all(path.with_file_name(name)).unwrap();
+        }
+    }
+
+    #[test]
+    fn holds_saves_when_a_broken_config_couldnt_be_backed_up() {
+        let dir = temp_dir("held-at-launch");
+        let path = dir.join("config.json");
+        std::fs::write(&path, "{ broken").unwrap();
+        block_backups(&path);
+        let profiles = Profiles::load(&path, None);
+        let (config, config_error) = Config::load_or_create(&path);
+        assert!(config_error.as_ref().is_some_and(|e| e.held));
+        let client = OscClient::new(&[]).unwrap();
+        let mut app = App::new(client, None, None, config, config_error, profiles, None);
+        app.config_changed = true;
+        app.flush_config();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "{ broken");
+    }
+
+    #[test]
+    fn holds_saves_when_a_broken_profile_couldnt_be_backed_up() {
+        let mut app = test_app("held-on-switch", &[], None);
+        let broken = app.profiles.path("Broken");
+        std::fs::write(&broken, "{ broken").unwrap();
+        block_backups(&broken);
+        app.switch_profile("Broken");
+        assert!(app.config_error.is_some());
+        app.config_changed = true;
+        app.flush_config();
+        assert_eq!(std::fs::read_to_string(&broken).unwrap(), "{ broken");
+
+        // Backed up, so the defaults can be saved over it
+        let backed_up = app.profiles.path("BackedUp");
+        std::fs::write(&backed_up, "{ broken").unwrap();
+        app.switch_profile("BackedUp");
+        app.config_changed = true;
+        app.flush_config();
+        assert!(Config::load(&backed_up).is_ok());
+    }
+
+    #[test]
+    fn tick_sends_the_chatbox_to_the_target() {
+        let vrchat = OscListener::new();
+        let profiles = Profiles::load(&temp_dir("tick").join("config.json"), None);
+        let client = OscClient::new(&[vrchat.target("VRChat")]).unwrap();
+        let config = Config {
+            status_messages: vec!["Testing headless".to_string()],
+            ..Config::default()
+        };
+        let mut app = App::new(client, None, None, config, None, profiles, None);
+        app.last_osc_send = Instant::now().checked_sub(Duration::from_secs(10)).unwrap_or_else(Instant::now);
+
+        app.tick();
+        let message = vrchat.recv_addr("/chatbox/input").expect("nothing was sent");
+        let OscType::String(text) = &message.args[0] else {
+            panic!("unexpected chatbox text {:?}", message.args[0]);
+        };
+        assert!(text.contains("Testing headless"), "{}", text);
+        assert_eq!(message.args[1..], [OscType::Bool(true), OscType::Bool(false)]);
+
+        // The next tick waits for the update rate
+        app.last_osc_send = Instant::now().checked_sub(Duration::from_secs(10)).unwrap_or_else(Instant::now);
+        app.tick();
+        assert!(vrchat.recv_addr("/chatbox/input").is_none());
+    }
+}
@@ -1,5 +1,7 @@
 use eframe::egui::{self, Ui, Align};
 use log::{debug, info, error};
+use crate::compose;
+use crate::osc::{ChatboxMessage, SendOutcome};
 use crate::ui::App;
 
 pub fn show_chatting_tab(ui: &mut Ui, app: &mut App) {
@@ -20,17 +22,17 @@ pub fn show_chatting_tab(ui: &mut Ui, app: &mut App) {
                 );
                 if response.changed() {
                     debug!("Chat message input changed");
-                    app.config_changed = true;
+                    app.last_chat_edit = std::time::Instant::now();
                 }
                 if response.has_focus() != app.chat_tab.is_focused {
                     debug!("Chat input focus changed to {}", response.has_focus());
                     app.chat_tab.is_focused = response.has_focus();
-                    app.config_changed = true;
+                    app.last_chat_edit = std::time::Instant::now();
                 }
-                if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) && !app.chat_tab.message.is_empty() && app.chat_tab.message.len() <= 140 {
+                if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) && !app.chat_tab.message.is_empty() && compose::chatbox_len(&app.chat_tab.message) <= compose::MAX_CHAT_INPUT {
                     let message = app.chat_tab.message.clone();
                     debug!("Enter key pressed to send chat message: {}", message);
-                    if app.chat_options.can_send() && app.last_osc_send.elapsed().as_secs_f32() >= app.app_options.app_options.osc_options.update_rate {
+                    if app.chat_options.can_send() {
                         let formatted_message = if app.chat_options.add_speech_bubble {
                             format!("🗨 {}", message)
                         } else {
@@ -38,12 +40,15 @@ pub fn show_chatting_tab(ui: &mut Ui, app: &mut App) {
                         };
                         app.osc_preview = formatted_message.clone();
                         if app.send_to_vrchat {
-                            if let Err(e) = app.osc_client.send_chatbox_message(&formatted_message, app.chat_options.play_fx_sound, app.extra_options.slim_mode) {
-                                error!("Failed to send OSC chat message: {}", e);
-                            } else {
-                                info!("Sent chat message to OSC: {}", formatted_message);
+                            let chatbox_message = ChatboxMessage::new(formatted_message.clone())
+                                .notify(app.chat_options.play_fx_sound)
+                                .slim(app.extra_options.slim_mode)
+                                .deferred();
+                            match app.send_chatbox(chatbox_message) {
+                                Ok(SendOutcome::Sent) => info!("Sent chat message to OSC: {}", formatted_message),
+                                Ok(outcome) => debug!("Chat message {:?}: {}", outcome, formatted_message),
+                                Err(e) => error!("Failed to send OSC chat message: {}", e),
                             }
-                            app.last_osc_send = std::time::Instant::now();
                         }
                         app.chat_options.add_message(message);
                         app.chat_tab.message.clear();
@@ -57,22 +62,22 @@ pub fn show_chatting_tab(ui: &mut Ui, app: &mut App) {
                         app.config_changed = true;
                     }
                 }
-                ui.label(format!("{}/140", app.chat_tab.message.len()));
+                ui.label(format!("{}/{}", compose::chatbox_len(&app.chat_tab.message), compose::MAX_CHAT_INPUT));
                 if ui.button("Paste").clicked() {
                     debug!("Paste button clicked");
-                    if let Ok(text) = app.clipboard.get_text() {
-                        app.chat_tab.message = text.chars().take(140).collect();
+                    if let Some(Ok(text)) = app.clipboard.as_mut().map(|clipboard| clipboard.get_text()) {
+                        app.chat_tab.message = compose::truncate_to_len(&text, compose::MAX_CHAT_INPUT).to_string();
                         app.chat_tab.is_focused = true;
-                        app.config_changed = true;
+                        app.last_chat_edit = std::time::Instant::now();
                         info!("Pasted text into chat input");
                     } else {
                         error!("Failed to paste from clipboard");
                     }
                 }
-                if ui.button("Send").clicked() && !app.chat_tab.message.is_empty() && app.chat_tab.message.len() <= 140 {
+                if ui.button("Send").clicked() && !app.chat_tab.message.is_empty() && compose::chatbox_len(&app.chat_tab.message) <= compose::MAX_CHAT_INPUT {
                     let message = app.chat_tab.message.clone();
                     debug!("Send button clicked for chat message: {}", message);
-                    if app.chat_options.can_send() && app.last_osc_send.elapsed().as_secs_f32() >= app.app_options.app_options.osc_options.update_rate {
+                    if app.chat_options.can_send() {
                         let formatted_message = if app.chat_options.add_speech_bubble {
                             format!("🗨 {}", message)
                         } else {
@@ -80,12 +85,15 @@ pub fn show_chatting_tab(ui: &mut Ui, app: &mut App) {
                         };
                         app.osc_preview = formatted_message.clone();
                         if app.send_to_vrchat {
-                            if let Err(e) = app.osc_client.send_chatbox_message(&formatted_message, app.chat_options.play_fx_sound, app.extra_options.slim_mode) {
-                                error!("Failed to send OSC chat message: {}", e);
-                            } else {
-                                info!("Sent chat message to OSC: {}", formatted_message);
+                            let chatbox_message = ChatboxMessage::new(formatted_message.clone())
+                                .notify(app.chat_options.play_fx_sound)
+                                .slim(app.extra_options.slim_mode)
+                                .deferred();
+                            match app.send_chatbox(chatbox_message) {
+                                Ok(SendOutcome::Sent) => info!("Sent chat message to OSC: {}", formatted_message),
+                                Ok(outcome) => debug!("Chat message {:?}: {}", outcome, formatted_message),
+                                Err(e) => error!("Failed to send OSC chat message: {}", e),
                             }
-                            app.last_osc_send = std::time::Instant::now();
                         }
                         app.chat_options.add_message(message);
                         app.chat_tab.message.clear();
@@ -124,6 +132,8 @@ pub fn show_chatting_tab(ui: &mut Ui, app: &mut App) {
                         remaining_times.push(0);
                     }
                 }
+                // Sent once the loop lets go of the message list
+                let mut outgoing = Vec::new();
                 let mut index = 0;
                 for (_index, message) in app.chat_options.messages.iter_mut().enumerate().rev() {
                     if ((now_ms - message.sent_at_ms) / 1000) < app.chat_options.chat_timeout as u64 {
@@ -148,13 +158,12 @@ pub fn show_chatting_tab(ui: &mut Ui, app: &mut App) {
                                         message.text.clone()
                                     };
                                     app.osc_preview = formatted_message.clone();
-                                    if app.send_to_vrchat && app.last_osc_send.elapsed().as_secs_f32() >= app.app_options.app_options.osc_options.update_rate {
-                                        if let Err(e) = app.osc_client.send_chatbox_message(&formatted_message, app.chat_options.play_fx_sound, app.extra_options.slim_mode) {
-                                            error!("Failed to send edited OSC message: {}", e);
-                                        } else {
-                                            info!("Sent edited chat message to OSC: {}", formatted_message);
-                                        }
-                                        app.last_osc_send = std::time::Instant::now();
+                                    if app.send_to_vrchat {
+                                        let chatbox_message = ChatboxMessage::new(formatted_message.clone())
+                                            .notify(app.chat_options.play_fx_sound)
+                                            .slim(app.extra_options.slim_mode)
+                                            .deferred();
+                                        outgoing.push((chatbox_message, "edited chat message"));
                                     }
                                     message.editing = false;
                                     app.config_changed = true;
@@ -166,13 +175,11 @@ pub fn show_chatting_tab(ui: &mut Ui, app: &mut App) {
                                         message.editing = false;
                                         message.edit_text = message.text.clone();
                                         app.osc_preview = app.previous_osc_preview.clone();
-                                        if app.send_to_vrchat && !app.osc_preview.is_empty() && app.last_osc_send.elapsed().as_secs_f32() >= app.app_options.app_options.osc_options.update_rate {
-                                            if let Err(e) = app.osc_client.send_chatbox_message(&app.osc_preview, false, app.extra_options.slim_mode) {
-                                                error!("Failed to send OSC message after cancel: {}", e);
-                                            } else {
-                                                info!("Sent previous OSC message after cancel: {}", app.osc_preview);
-                                            }
-                                            app.last_osc_send = std::time::Instant::now();
+                                        if app.send_to_vrchat && !app.osc_preview.is_empty() {
+                                            let chatbox_message = ChatboxMessage::new(app.osc_preview.clone())
+                                                .slim(app.extra_options.slim_mode)
+                                                .deferred();
+                                            outgoing.push((chatbox_message, "previous OSC message after cancel"));
                                         }
                                         app.config_changed = true;
                                     }
@@ -187,20 +194,18 @@ pub fn show_chatting_tab(ui: &mut Ui, app: &mut App) {
                                         if !message.editing {
                                             message.edit_text = message.text.clone();
                                             app.osc_preview = app.previous_osc_preview.clone();
-                                            if app.send_to_vrchat && !app.osc_preview.is_empty() && app.last_osc_send.elapsed().as_secs_f32() >= app.app_options.app_options.osc_options.update_rate {
-                                                if let Err(e) = app.osc_client.send_chatbox_message(&app.osc_preview, false, app.extra_options.slim_mode) {
-                                                    error!("Failed to send OSC message after edit toggle: {}", e);
-                                                } else {
-                                                    info!("Sent previous OSC message after edit toggle: {}", app.osc_preview);
-                                                }
-                                                app.last_osc_send = std::time::Instant::now();
+                                            if app.send_to_vrchat && !app.osc_preview.is_empty() {
+                                                let chatbox_message = ChatboxMessage::new(app.osc_preview.clone())
+                                                    .slim(app.extra_options.slim_mode)
+                                                    .deferred();
+                                                outgoing.push((chatbox_message, "previous OSC message after edit toggle"));
                                             }
                                         }
                                         app.config_changed = true;
                                     }
                                     if ui.button("Copy").clicked() {
                                         debug!("Copy button clicked for message");
-                                        if let Err(e) = app.clipboard.set_text(&message.text) {
+                                        if let Err(e) = app.clipboard.as_mut().ok_or(arboard::Error::ClipboardNotSupported).and_then(|clipboard| clipboard.set_text(&message.text)) {
                                             error!("Failed to copy message to clipboard: {}", e);
                                         } else {
                                             info!("Copied message to clipboard: {}", message.text);
@@ -208,24 +213,21 @@ pub fn show_chatting_tab(ui: &mut Ui, app: &mut App) {
                                     }
                                     if ui.button("Resend").clicked() {
                                         debug!("Resend button clicked for message");
-                                        if app.last_osc_send.elapsed().as_secs_f32() >= app.app_options.app_options.osc_options.update_rate {
-                                            let formatted_message = if app.chat_options.add_speech_bubble {
-                                                format!("🗨 {}", message.text)
-                                            } else {
-                                                message.text.clone()
-                                            };
-                                            message.sent_at_ms = now_ms;
-                                            app.osc_preview = formatted_message.clone();
-                                            if app.send_to_vrchat {
-                                                if let Err(e) = app.osc_client.send_chatbox_message(&formatted_message, app.chat_options.play_fx_resend && app.chat_options.play_fx_sound, app.extra_options.slim_mode) {
-                                                    error!("Failed to resend OSC message: {}", e);
-                                                } else {
-                                                    info!("Resent chat message to OSC: {}", formatted_message);
-                                                }
-                                                app.last_osc_send = std::time::Instant::now();
-                                            }
-                                            app.config_changed = true;
+                                        let formatted_message = if app.chat_options.add_speech_bubble {
+                                            format!("🗨 {}", message.text)
+                                        } else {
+                                            message.text.clone()
+                                        };
+                                        message.sent_at_ms = now_ms;
+                                        app.osc_preview = formatted_message.clone();
+                                        if app.send_to_vrchat {
+                                            let chatbox_message = ChatboxMessage::new(formatted_message.clone())
+                                                .notify(app.chat_options.play_fx_resend && app.chat_options.play_fx_sound)
+                                                .slim(app.extra_options.slim_mode)
+                                                .deferred();
+                                            outgoing.push((chatbox_message, "resent chat message"));
                                         }
+                                        app.config_changed = true;
                                     }
                                 });
                             } else {
@@ -245,7 +247,7 @@ pub fn show_chatting_tab(ui: &mut Ui, app: &mut App) {
                                     }
                                     if ui.button("Copy").clicked() {
                                         debug!("Copy button clicked for message");
-                                        if let Err(e) = app.clipboard.set_text(&message.text) {
+                                        if let Err(e) = app.clipboard.as_mut().ok_or(arboard::Error::ClipboardNotSupported).and_then(|clipboard| clipboard.set_text(&message.text)) {
                                             error!("Failed to copy message to clipboard: {}", e);
                                         } else {
                                             info!("Copied message to clipboard: {}", message.text);
@@ -253,24 +255,21 @@ pub fn show_chatting_tab(ui: &mut Ui, app: &mut App) {
                                     }
                                     if ui.button("Resend").clicked() {
                                         debug!("Resend button clicked for message");
-                                        if app.last_osc_send.elapsed().as_secs_f32() >= app.app_options.app_options.osc_options.update_rate {
-                                            let formatted_message = if app.chat_options.add_speech_bubble {
-                                                format!("🗨 {}", message.text)
-                                            } else {
-                                                message.text.clone()
-                                            };
-                                            message.sent_at_ms = now_ms;
-                                            app.osc_preview = formatted_message.clone();
-                                            if app.send_to_vrchat {
-                                                if let Err(e) = app.osc_client.send_chatbox_message(&formatted_message, app.chat_options.play_fx_resend && app.chat_options.play_fx_sound, app.extra_options.slim_mode) {
-                                                    error!("Failed to resend OSC message: {}", e);
-                                                } else {
-                                                    info!("Resent chat message to OSC: {}", formatted_message);
-                                                }
-                                                app.last_osc_send = std::time::Instant::now();
-                                            }
-                                            app.config_changed = true;
+                                        let formatted_message = if app.chat_options.add_speech_bubble {
+                                            format!("🗨 {}", message.text)
+                                        } else {
+                                            message.text.clone()
+                                        };
+                                        message.sent_at_ms = now_ms;
+                                        app.osc_preview = formatted_message.clone();
+                                        if app.send_to_vrchat {
+                                            let chatbox_message = ChatboxMessage::new(formatted_message.clone())
+                                                .notify(app.chat_options.play_fx_resend && app.chat_options.play_fx_sound)
+                                                .slim(app.extra_options.slim_mode)
+                                                .deferred();
+                                            outgoing.push((chatbox_message, "resent chat message"));
                                         }
+                                        app.config_changed = true;
                                     }
                                 });
                             }
@@ -279,6 +278,9 @@ pub fn show_chatting_tab(ui: &mut Ui, app: &mut App) {
                         index += 1;
                     }
                 }
+                for (chatbox_message, what) in outgoing {
+                    send_chat(app, chatbox_message, what);
+                }
                 // Control buttons (Stop, Clear history) only when messages exist
                 if !app.chat_options.messages.is_empty() {
                     ui.group(|ui| {
@@ -294,13 +296,11 @@ pub fn show_chatting_tab(ui: &mut Ui, app: &mut App) {
                                 if ui.button("Stop").clicked() {
                                     debug!("Stop button clicked");
                                     app.osc_preview = app.previous_osc_preview.clone();
-                                    if app.send_to_vrchat && !app.osc_preview.is_empty() && app.last_osc_send.elapsed().as_secs_f32() >= app.app_options.app_options.osc_options.update_rate {
-                                        if let Err(e) = app.osc_client.send_chatbox_message(&app.osc_preview, false, app.extra_options.slim_mode) {
-                                            error!("Failed to send OSC message after stop: {}", e);
-                                        } else {
-                                            info!("Sent previous OSC message after stop: {}", app.osc_preview);
-                                        }
-                                        app.last_osc_send = std::time::Instant::now();
+                                    if app.send_to_vrchat && !app.osc_preview.is_empty() {
+                                        let chatbox_message = ChatboxMessage::new(app.osc_preview.clone())
+                                            .slim(app.extra_options.slim_mode)
+                                            .deferred();
+                                        send_chat(app, chatbox_message, "previous OSC message after stop");
                                     }
                                 }
                             });
@@ -310,4 +310,14 @@ pub fn show_chatting_tab(ui: &mut Ui, app: &mut App) {
             }); // Close ui.group
         }); // Close ScrollArea
     }); // Close CentralPanel
-}
\ No newline at end of file
+}
+
+// Goes through the app so the integration refresh waits its turn after a chat send
+fn send_chat(app: &mut App, chatbox_message: ChatboxMessage, what: &str) {
+    let text = chatbox_message.text.clone();
+    match app.send_chatbox(chatbox_message) {
+        Ok(SendOutcome::Sent) => info!("Sent {}: {}", what, text),
+        Ok(outcome) => debug!("{} {:?}: {}", what, outcome, text),
+        Err(e) => error!("Failed to send {}: {}", what, e),
+    }
+}
@@ -187,7 +187,7 @@ pub fn show_integrations_tab(ui: &mut Ui, app: &mut App) {
         });
         ui.label(egui::RichText::new("Show the current media track and artist.").color(Color32::from_rgb(0x3f, 0x3f, 0x3f)));
         if app.integrations_tab.medialink_enabled {
-            if let Some(track) = app.media_module.get_formatted_track(&app.media_link) {
+            if let Some(track) = app.media_module.get_formatted_track(&app.media_link, None) {
                 ui.label(format!("Now playing: {}", track));
             } else {
                 ui.label("No media playing.");
@@ -222,4 +222,44 @@ pub fn show_integrations_tab(ui: &mut Ui, app: &mut App) {
             });
         }
     });
-}
\ No newline at end of file
+    ui.separator();
+
+    // Priority
+    ui.group(|ui| {
+        ui.heading("Priority");
+        ui.label(egui::RichText::new("When the chatbox is full, integrations at the bottom are shortened first, then left out.").color(Color32::from_rgb(0x3f, 0x3f, 0x3f)));
+        let mut move_up = None;
+        let priority_count = app.integrations_tab.priorities.len();
+        egui::Grid::new("integration_priorities").striped(true).show(ui, |ui| {
+            for (index, priority) in app.integrations_tab.priorities.iter_mut().enumerate() {
+                ui.label(format!("{}.", index + 1));
+                ui.label(priority.integration.name());
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(index > 0, egui::Button::new("⏶")).clicked() {
+                        move_up = Some(index);
+                    }
+                    if ui.add_enabled(index + 1 < priority_count, egui::Button::new("⏷")).clicked() {
+                        move_up = Some(index + 1);
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Max lines:");
+                    let response = ui.add(
+                        egui::DragValue::new(&mut priority.max_lines)
+                            .range(0..=crate::compose::MAX_LINES)
+                            .custom_formatter(|n, _| if n == 0.0 { "Any".to_string() } else { format!("{}", n) }),
+                    );
+                    if response.changed() {
+                        app.config_changed = true;
+                    }
+                });
+                ui.end_row();
+            }
+        });
+        if let Some(index) = move_up {
+            debug!("Moved {} up in integration priority", app.integrations_tab.priorities[index].integration.name());
+            app.integrations_tab.priorities.swap(index - 1, index);
+            app.config_changed = true;
+        }
+    });
+}
@@ -1,5 +1,8 @@
 use eframe::egui::{self, Ui, Align};
 use log::{debug, info, error};
+use crate::compose;
+use crate::osc::{ChatboxMessage, SendOutcome};
+use crate::template;
 use crate::ui::App;
 
 pub fn show_options_tab(ui: &mut Ui, app: &mut App) {
@@ -63,16 +66,13 @@ pub fn show_options_tab(ui: &mut Ui, app: &mut App) {
                                 app.config_changed = true;
                                 app.update_osc_preview();
                                 if app.send_to_vrchat && !app.osc_preview.is_empty() {
-                                    if let Err(e) = app.osc_client.send_chatbox_message(
-                                        &app.osc_preview,
-                                        false,
-                                        app.extra_options.slim_mode,
-                                    ) {
-                                        error!("Failed to send OSC message for window activity: {}", e);
-                                    } else {
-                                        info!("Sent OSC message for window activity: {}", app.osc_preview);
+                                    let chatbox_message = ChatboxMessage::new(app.osc_preview.clone())
+                                        .slim(app.extra_options.slim_mode);
+                                    match app.send_chatbox(chatbox_message) {
+                                        Ok(SendOutcome::Sent) => info!("Sent OSC message for window activity: {}", app.osc_preview),
+                                        Ok(outcome) => debug!("OSC message for window activity {:?}", outcome),
+                                        Err(e) => error!("Failed to send OSC message for window activity: {}", e),
                                     }
-                                    app.last_osc_send = std::time::Instant::now();
                                 }
                             }
                         } else {
@@ -256,6 +256,58 @@ pub fn show_options_tab(ui: &mut Ui, app: &mut App) {
                             let response = app.app_options.show_app_options(ui);
                             if response.changed() {
                                 app.config_changed = true;
+                                app.osc_options_edited = Some(std::time::Instant::now());
+                            }
+                            if let Some(target) = &app.osc_target_override {
+                                ui.label(format!("Sending to {} from --osc-target instead of the targets above", target.addr()));
+                            }
+                            match app.osc_client.last_send() {
+                                Some(report) => {
+                                    let ago = report.at.elapsed().as_secs();
+                                    match &report.error {
+                                        Some(e) => ui.colored_label(egui::Color32::RED, format!("Last send {}s ago: {}", ago, e)),
+                                        None => ui.label(format!("Last send {}s ago: OK", ago)),
+                                    };
+                                }
+                                None => {
+                                    ui.label("Nothing sent yet.");
+                                }
+                            }
+                            if let Some(capture) = app.osc_client.capture() {
+                                let mode = if app.osc_client.is_dry_run() { "Dry run" } else { "Recording" };
+                                ui.label(format!("{}: {} packets to {}", mode, capture.packets(), capture.path().display()));
+                            }
+                            match (&app.osc_server, &app.osc_receive_error) {
+                                (_, Some(e)) => {
+                                    ui.colored_label(egui::Color32::RED, format!("Not receiving OSC: {}", e));
+                                }
+                                (Some(server), None) => match server.last_received() {
+                                    Some((at, peer)) => {
+                                        ui.label(format!("Last packet received {}s ago from {} (port {})", at.elapsed().as_secs(), peer, server.port()));
+                                    }
+                                    None => {
+                                        ui.label(format!("Listening on port {}, no packets received yet.", server.port()));
+                                    }
+                                },
+                                (None, None) => {
+                                    ui.colored_label(egui::Color32::RED, "Not receiving OSC.");
+                                }
+                            }
+                            let mut target_errors: Vec<_> = app.osc_client.target_errors().iter().collect();
+                            target_errors.sort();
+                            for (name, e) in target_errors {
+                                ui.colored_label(egui::Color32::RED, format!("{}: {}", name, e));
+                            }
+                            if let Some(oscquery) = &app.oscquery {
+                                ui.label(format!("Advertising as {} (OSCQuery HTTP port {})", oscquery.name(), oscquery.http_port()));
+                                let discovered = oscquery.discovered();
+                                if discovered.is_empty() {
+                                    ui.label("No other OSCQuery services found yet.");
+                                }
+                                for service in discovered {
+                                    let marker = if app.oscquery_target.as_ref() == Some(&service) { " (sending)" } else { "" };
+                                    ui.label(format!("Found {} at {}:{}{}", service.name, service.ip, service.osc_port, marker));
+                                }
                             }
                         }
                     });
@@ -267,6 +319,77 @@ pub fn show_options_tab(ui: &mut Ui, app: &mut App) {
         }
         ui.separator();
 
+        // Chatbox Layout
+        let layout_response = ui.allocate_ui_with_layout(
+            egui::vec2(ui.available_width(), 0.0),
+            egui::Layout::top_down(Align::LEFT),
+            |ui| {
+                ui.group(|ui| {
+                    ui.push_id(egui::Id::new("layout_options"), |ui| {
+                        ui.horizontal(|ui| {
+                            let response = ui.checkbox(&mut app.layout_options.enabled, "");
+                            if response.changed() {
+                                debug!("Layout options enabled checkbox changed");
+                                app.config_changed = true;
+                            }
+                            ui.heading("Chatbox Layout");
+                        });
+                        if app.layout_options.enabled {
+                            let response = app.layout_options.show_layout_options(ui);
+                            if response.changed() {
+                                app.config_changed = true;
+                            }
+                            app.sample_template_fields();
+                            if let Some(layout_template) = app.layout_options.active_template() {
+                                if let Ok(rendered) = template::render(&layout_template.text, &app.template_fields) {
+                                    ui.label("Preview:");
+                                    let layout = compose::Layout::new(app.app_options.app_options.osc_options.separate_lines);
+                                    let lines = compose::compose(None, &[rendered], &layout);
+                                    ui.group(|ui| {
+                                        ui.label(lines.join("\n"));
+                                    });
+                                }
+                            }
+                        }
+                    });
+                });
+            },
+        );
+        if app.pending_scroll_to == Some(egui::Id::new("layout_options")) {
+            scroll_to_rect = Some(layout_response.response.rect);
+        }
+        ui.separator();
+
+        // Avatar Parameters
+        let parameter_response = ui.allocate_ui_with_layout(
+            egui::vec2(ui.available_width(), 0.0),
+            egui::Layout::top_down(Align::LEFT),
+            |ui| {
+                ui.group(|ui| {
+                    ui.push_id(egui::Id::new("parameter_options"), |ui| {
+                        ui.horizontal(|ui| {
+                            let response = ui.checkbox(&mut app.parameter_options.enabled, "");
+                            if response.changed() {
+                                debug!("Avatar parameter options enabled checkbox changed");
+                                app.config_changed = true;
+                            }
+                            ui.heading("Avatar Parameters");
+                        });
+                        if app.parameter_options.enabled {
+                            let response = app.parameter_options.show_parameter_options(ui);
+                            if response.changed() {
+                                app.config_changed = true;
+                            }
+                        }
+                    });
+                });
+            },
+        );
+        if app.pending_scroll_to == Some(egui::Id::new("parameter_options")) {
+            scroll_to_rect = Some(parameter_response.response.rect);
+        }
+        ui.separator();
+
         // Extra Options
         let extra_response = ui.allocate_ui_with_layout(
             egui::vec2(ui.available_width(), 0.0),
@@ -295,6 +418,56 @@ pub fn show_options_tab(ui: &mut Ui, app: &mut App) {
         if app.pending_scroll_to == Some(egui::Id::new("extra_options")) {
             scroll_to_rect = Some(extra_response.response.rect);
         }
+        ui.separator();
+
+        // Import from MagicChatBox
+        ui.group(|ui| {
+            ui.set_width(ui.available_width());
+            ui.push_id(egui::Id::new("magicchatbox_import"), |ui| {
+                ui.heading("Import from MagicChatBox");
+                ui.label("Point this at MagicChatBox's data folder (the one with StatusList.xml and settings.xml) or at a single exported file. Status messages are added to yours; matching settings are replaced.");
+                ui.horizontal(|ui| {
+                    ui.add(egui::TextEdit::singleline(&mut app.import_path).desired_width(300.0).hint_text("Folder or file"));
+                    if ui.add_enabled(!app.import_path.trim().is_empty(), egui::Button::new("Import")).clicked() {
+                        info!("Importing MagicChatBox settings from {}", app.import_path.trim());
+                        app.import_magicchatbox();
+                    }
+                });
+                match &app.import_result {
+                    Some(Ok(report)) => {
+                        ui.label(format!(
+                            "Read {}. Imported {} settings and {} status messages ({} already present).",
+                            report.files.join(", "),
+                            report.imported.len(),
+                            report.status_added,
+                            report.status_duplicates
+                        ));
+                        if !report.invalid.is_empty() {
+                            egui::CollapsingHeader::new(format!("{} settings had unusable values", report.invalid.len()))
+                                .id_source("import_invalid")
+                                .show(ui, |ui| {
+                                    for entry in &report.invalid {
+                                        ui.colored_label(egui::Color32::from_rgb(0xff, 0xa5, 0x00), entry);
+                                    }
+                                });
+                        }
+                        if !report.unmapped.is_empty() {
+                            egui::CollapsingHeader::new(format!("{} settings have no equivalent", report.unmapped.len()))
+                                .id_source("import_unmapped")
+                                .show(ui, |ui| {
+                                    for entry in &report.unmapped {
+                                        ui.label(entry);
+                                    }
+                                });
+                        }
+                    }
+                    Some(Err(e)) => {
+                        ui.colored_label(egui::Color32::RED, e);
+                    }
+                    None => {}
+                }
+            });
+        });
 
         // Perform scroll after rendering all sections
         if let Some(rect) = scroll_to_rect {
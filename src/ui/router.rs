@@ -0,0 +1,50 @@
+use eframe::egui::{self, Ui};
+use crate::ui::App;
+
+pub fn show_router_tab(ui: &mut Ui, app: &mut App) {
+    egui::ScrollArea::vertical().show(ui, |ui| {
+        ui.heading("Router");
+        match &app.osc_server {
+            Some(server) => {
+                ui.label(format!("Packets received on port {} are forwarded to the routes below.", server.port()));
+            }
+            None => {
+                ui.colored_label(egui::Color32::RED, "Not receiving OSC, so there is nothing to forward.");
+            }
+        }
+        ui.separator();
+
+        let response = app.router_options.show_router_options(ui);
+        if response.changed() {
+            app.config_changed = true;
+            app.osc_options_edited = Some(std::time::Instant::now());
+        }
+
+        if let Some(router) = &app.router {
+            ui.separator();
+            egui::Grid::new("router_stats").striped(true).show(ui, |ui| {
+                ui.strong("Route");
+                ui.strong("Forwarded");
+                ui.strong("Filtered");
+                ui.strong("Errors");
+                ui.end_row();
+                for stats in router.stats() {
+                    ui.label(&stats.name);
+                    match &stats.error {
+                        Some(e) => {
+                            ui.colored_label(egui::Color32::RED, e);
+                        }
+                        None => {
+                            ui.label(stats.forwarded.to_string());
+                            ui.label(stats.filtered.to_string());
+                            ui.label(stats.errors.to_string());
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
+            // Keep the counters moving while the tab is open
+            ui.ctx().request_repaint_after(std::time::Duration::from_millis(500));
+        }
+    });
+}
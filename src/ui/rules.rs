@@ -0,0 +1,24 @@
+use eframe::egui::{self, Ui};
+use crate::ui::App;
+
+pub fn show_rules_tab(ui: &mut Ui, app: &mut App) {
+    egui::ScrollArea::vertical().show(ui, |ui| {
+        ui.heading("Rules");
+        ui.label("Rules switch integrations, the status and the layout template while their conditions match. Nothing they change is saved.");
+        let effects = app.rule_engine.effects();
+        if app.rule_options.enabled && !effects.matched.is_empty() {
+            ui.colored_label(egui::Color32::GREEN, format!("Matching now: {}", effects.matched.join(", ")));
+        }
+        ui.separator();
+
+        let status_messages = app.status_module.messages.clone();
+        let templates: Vec<String> = app.layout_options.templates.iter().map(|t| t.name.clone()).collect();
+        let response = app.rule_options.show_rule_options(ui, &status_messages, &templates);
+        if response.changed() {
+            app.config_changed = true;
+        }
+        if app.rule_options.enabled {
+            ui.ctx().request_repaint_after(std::time::Duration::from_secs(1));
+        }
+    });
+}
@@ -1,5 +1,6 @@
 use eframe::egui::Ui;
 use log::{debug, info, error};
+use crate::osc::{ChatboxMessage, SendOutcome};
 use crate::ui::App;
 
 pub fn show_status_tab(ui: &mut Ui, app: &mut App) {
@@ -22,13 +23,13 @@ pub fn show_status_tab(ui: &mut Ui, app: &mut App) {
         ui.label(format!("Current status: {}", current));
         if ui.button("Send to OSC").clicked() {
             debug!("Send to OSC button clicked for status: {}", current);
-            if app.last_osc_send.elapsed().as_secs_f32() >= app.app_options.app_options.osc_options.update_rate {
-                if let Err(e) = app.osc_client.send_chatbox_message(current.as_str(), false, app.extra_options.slim_mode) {
-                    error!("Failed to send status to OSC: {}", e);
-                } else {
-                    info!("Sent status to OSC: {}", current);
-                }
-                app.last_osc_send = std::time::Instant::now();
+            let chatbox_message = ChatboxMessage::new(current.clone())
+                .slim(app.extra_options.slim_mode)
+                .deferred();
+            match app.send_chatbox(chatbox_message) {
+                Ok(SendOutcome::Sent) => info!("Sent status to OSC: {}", current),
+                Ok(outcome) => debug!("Status {:?}: {}", outcome, current),
+                Err(e) => error!("Failed to send status to OSC: {}", e),
             }
         }
     } else {
@@ -1,19 +1,60 @@
 use serde::{Serialize, Deserialize};
+use crate::modules::integration::Integration;
 
 #[derive(Clone, Serialize, Deserialize, PartialEq)]
 pub enum Tab {
     Integrations,
     Status,
     Chatting,
+    Rules,
+    Router,
     Options,
 }
 
-#[derive(Clone, Serialize, Deserialize)]
+// Unsent draft and focus of the chat input, never saved
+#[derive(Clone, Default)]
 pub struct ChatTab {
     pub message: String,
     pub is_focused: bool,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IntegrationPriority {
+    pub integration: Integration,
+    // 0 for no limit
+    pub max_lines: usize,
+}
+
+// Most important first
+pub fn default_priorities() -> Vec<IntegrationPriority> {
+    [
+        Integration::Status,
+        Integration::Media,
+        Integration::Time,
+        Integration::Activity,
+        Integration::Components,
+        Integration::Network,
+    ]
+    .into_iter()
+    .map(|integration| IntegrationPriority { integration, max_lines: 0 })
+    .collect()
+}
+
+// Drops duplicates and appends integrations missing from an older config
+pub fn normalize_priorities(priorities: &mut Vec<IntegrationPriority>) {
+    let mut seen = Vec::new();
+    priorities.retain(|p| {
+        let first = !seen.contains(&p.integration);
+        seen.push(p.integration);
+        first
+    });
+    for integration in Integration::ALL {
+        if !seen.contains(&integration) {
+            priorities.push(IntegrationPriority { integration, max_lines: 0 });
+        }
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct IntegrationsTab {
     pub personal_status_enabled: bool,
@@ -22,6 +63,17 @@ pub struct IntegrationsTab {
     pub current_time_enabled: bool,
     pub medialink_enabled: bool,
     pub window_activity_enabled: bool,
+    pub priorities: Vec<IntegrationPriority>,
+}
+
+impl IntegrationsTab {
+    pub fn priority_of(&self, integration: Integration) -> (usize, usize) {
+        self.priorities
+            .iter()
+            .position(|p| p.integration == integration)
+            .map(|index| (index, self.priorities[index].max_lines))
+            .unwrap_or((self.priorities.len(), 0))
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize)]